@@ -2,7 +2,7 @@ use std::collections::BTreeMap;
 use std::io::{stdout, Seek, SeekFrom, Write};
 use std::sync::atomic::Ordering;
 
-use fil_proofs_tooling::{measure, Metadata};
+use fil_proofs_tooling::{hex32, measure, Metadata};
 use filecoin_proofs::constants::{
     DEFAULT_POREP_PROOF_PARTITIONS, POST_CHALLENGED_NODES, POST_CHALLENGE_COUNT,
 };
@@ -117,6 +117,11 @@ pub fn run(sector_size: usize) -> anyhow::Result<()> {
 
     let seed = [0u8; 32];
     let comm_r = seal_pre_commit_output.comm_r;
+    info!(
+        "seal_pre_commit: comm_r={} comm_d={}",
+        hex32(&comm_r),
+        hex32(&seal_pre_commit_output.comm_d)
+    );
 
     let _seal_commit_output = seal_commit(
         porep_config,
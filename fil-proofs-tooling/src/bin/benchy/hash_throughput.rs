@@ -0,0 +1,100 @@
+use fil_proofs_tooling::{measure_many, Metadata};
+use merkletree::hash::Algorithm;
+use serde::Serialize;
+use storage_proofs::hasher::{
+    Blake2sHasher, Domain, HashFunction, Hasher, PedersenHasher, Sha256Hasher,
+};
+use storage_proofs::util::NODE_SIZE;
+
+/// The height passed to `node`, the hashers in use ignore it.
+const NODE_HEIGHT: usize = 0;
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Report {
+    hasher: String,
+    nodes: usize,
+    samples: usize,
+    hash_wall_time_us: u64,
+    hash_throughput_mb_s: f64,
+    node_wall_time_us: u64,
+    node_time_ns: f64,
+}
+
+/// Hashes `data` in one go with `H::Function::hash`.
+fn hash_buffer<H: Hasher>(data: &[u8]) -> H::Domain {
+    <H::Function as HashFunction<H::Domain>>::hash(data)
+}
+
+/// Folds `nodes` into a single node, one `node` call per element, the same way tree building
+/// combines two children.
+fn hash_nodes<H: Hasher>(nodes: &[H::Domain]) -> H::Domain {
+    let mut hasher = H::Function::default();
+
+    nodes.iter().fold(H::Domain::default(), |acc, node| {
+        hasher.reset();
+        hasher.node(acc, *node, NODE_HEIGHT)
+    })
+}
+
+/// Benchmarks native hashing over a fixed buffer of `nodes` random nodes, `samples` times.
+fn bench<H: Hasher>(hasher: &str, nodes: usize, samples: usize) -> anyhow::Result<Report> {
+    let rng = &mut rand::thread_rng();
+    let domains: Vec<H::Domain> = (0..nodes).map(|_| H::Domain::random(rng)).collect();
+    let data: Vec<u8> = domains.iter().flat_map(|d| d.into_bytes()).collect();
+
+    let hash = measure_many(samples, || Ok(hash_buffer::<H>(&data)))?;
+    let node = measure_many(samples, || Ok(hash_nodes::<H>(&domains)))?;
+
+    let hash_wall_time = hash.wall_time.median;
+    let node_wall_time = node.wall_time.median;
+
+    Ok(Report {
+        hasher: hasher.into(),
+        nodes,
+        samples,
+        hash_wall_time_us: hash_wall_time.as_micros() as u64,
+        hash_throughput_mb_s: (nodes * NODE_SIZE) as f64
+            / (1024. * 1024.)
+            / hash_wall_time.as_secs_f64(),
+        node_wall_time_us: node_wall_time.as_micros() as u64,
+        node_time_ns: node_wall_time.as_nanos() as f64 / nodes as f64,
+    })
+}
+
+pub fn run(size: usize, samples: usize) -> anyhow::Result<()> {
+    let nodes = size / NODE_SIZE;
+
+    let reports = vec![
+        bench::<PedersenHasher>("pedersen", nodes, samples)?,
+        bench::<Blake2sHasher>("blake2s", nodes, samples)?,
+        bench::<Sha256Hasher>("sha256", nodes, samples)?,
+    ];
+
+    // print reports
+    let wrapped = Metadata::wrap(reports)?;
+    serde_json::to_writer(std::io::stdout(), &wrapped)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bench_small<H: Hasher>(hasher: &str) {
+        let report = bench::<H>(hasher, 8, 2).unwrap();
+
+        assert_eq!(report.hasher, hasher);
+        assert_eq!(report.nodes, 8);
+        assert_eq!(report.samples, 2);
+        assert!(report.node_time_ns > 0.);
+    }
+
+    #[test]
+    fn test_bench_small() {
+        bench_small::<PedersenHasher>("pedersen");
+        bench_small::<Blake2sHasher>("blake2s");
+        bench_small::<Sha256Hasher>("sha256");
+    }
+}
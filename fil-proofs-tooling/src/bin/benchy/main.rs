@@ -1,4 +1,5 @@
 use std::io::{stdin, stdout};
+use std::path::{Path, PathBuf};
 
 use clap::{value_t, App, Arg, SubCommand};
 
@@ -7,6 +8,11 @@ use crate::flarp::FlarpInputs;
 mod election_post;
 mod flarp;
 mod hash_fns;
+mod hash_throughput;
+mod merkletree;
+mod pedersen_compression;
+mod ppor;
+mod seal;
 mod shared;
 mod stacked;
 
@@ -135,12 +141,136 @@ fn main() {
                 .help("skip generation (and verification) of PoSt proof"),
         );
 
+    let merkletree_cmd = SubCommand::with_name("merkletree-build")
+        .about("Benchmark serial vs parallel merkle tree construction")
+        .arg(
+            Arg::with_name("size")
+                .long("size")
+                .required(true)
+                .help("The data size in KiB")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("samples")
+                .long("samples")
+                .default_value("5")
+                .help("How many times to build each tree")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("store-path")
+                .long("store-path")
+                .help("Directory in which the trees are stored (default: the system temp dir)")
+                .takes_value(true),
+        );
+
+    let hash_throughput_cmd = SubCommand::with_name("hash")
+        .about("Benchmark native hashing throughput of each hasher")
+        .arg(
+            Arg::with_name("size")
+                .long("size")
+                .default_value("1024")
+                .help("The size in KiB of the buffer to hash")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("samples")
+                .long("samples")
+                .default_value("5")
+                .help("How many times to hash the buffer")
+                .takes_value(true),
+        );
+
+    let pedersen_compression_cmd = SubCommand::with_name("pedersen-compression")
+        .about("Compare the precomputed and the generic pedersen compression")
+        .arg(
+            Arg::with_name("blocks")
+                .long("blocks")
+                .default_value("10000")
+                .help("How many random blocks to compress")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("samples")
+                .long("samples")
+                .default_value("5")
+                .help("How many times to compress all blocks")
+                .takes_value(true),
+        );
+
+    let ppor_cmd = SubCommand::with_name("ppor")
+        .about("Benchmark a full PoR groth proof round-trip, reporting setup, proving and verifying times")
+        .arg(
+            Arg::with_name("leaves")
+                .long("leaves")
+                .default_value("1024")
+                .help("The number of leaves of the tree, a power of two")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("challenges")
+                .long("challenges")
+                .default_value("1")
+                .help("How many challenges to prove and verify")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("hasher")
+                .long("hasher")
+                .default_value("pedersen")
+                .help("Which hasher should be used. Available: \"pedersen\", \"sha256\", \"blake2s\" (default \"pedersen\")")
+                .takes_value(true),
+        );
+
+    let seal_cmd = SubCommand::with_name("seal")
+        .about("Benchmark sealing and PoSt, reporting per-phase timings")
+        .arg(
+            Arg::with_name("sector-size")
+                .long("sector-size")
+                .required(true)
+                .help("The sector size in KiB")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("sectors")
+                .long("sectors")
+                .default_value("1")
+                .help("How many sectors to seal")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("scratch-dir")
+                .long("scratch-dir")
+                .help(
+                    "Directory in which temporary files are created (default: the system temp dir)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("resume")
+                .long("resume")
+                .value_name("WORK_DIR")
+                .takes_value(true)
+                .help("Continue the interrupted run in WORK_DIR from its last checkpoints"),
+        )
+        .arg(
+            Arg::with_name("no-progress")
+                .long("no-progress")
+                .takes_value(false)
+                .help("Do not draw a progress bar, e.g. when logging to a file"),
+        );
+
     let matches = App::new("benchy")
         .version("0.1")
         .subcommand(stacked_cmd)
         .subcommand(election_post_cmd)
         .subcommand(hash_cmd)
         .subcommand(flarp_cmd)
+        .subcommand(merkletree_cmd)
+        .subcommand(hash_throughput_cmd)
+        .subcommand(pedersen_compression_cmd)
+        .subcommand(ppor_cmd)
+        .subcommand(seal_cmd)
         .get_matches();
 
     match matches.subcommand() {
@@ -194,6 +324,59 @@ fn main() {
             serde_json::to_writer(stdout(), &outputs)
                 .expect("failed to write FlarpOutput to stdout")
         }
+        ("merkletree-build", Some(m)) => {
+            let size_kibs = value_t!(m, "size", usize)
+                .expect("could not convert `size` CLI argument to `usize`");
+            let samples = value_t!(m, "samples", usize)
+                .expect("could not convert `samples` CLI argument to `usize`");
+            let store_path = m
+                .value_of("store-path")
+                .map(PathBuf::from)
+                .unwrap_or_else(std::env::temp_dir);
+            merkletree::run(size_kibs * 1024, samples, &store_path)
+                .expect("merkletree-build failed");
+        }
+        ("hash", Some(m)) => {
+            let size_kibs = value_t!(m, "size", usize)
+                .expect("could not convert `size` CLI argument to `usize`");
+            let samples = value_t!(m, "samples", usize)
+                .expect("could not convert `samples` CLI argument to `usize`");
+            hash_throughput::run(size_kibs * 1024, samples).expect("hash failed");
+        }
+        ("pedersen-compression", Some(m)) => {
+            let blocks = value_t!(m, "blocks", usize)
+                .expect("could not convert `blocks` CLI argument to `usize`");
+            let samples = value_t!(m, "samples", usize)
+                .expect("could not convert `samples` CLI argument to `usize`");
+            pedersen_compression::run(blocks, samples).expect("pedersen-compression failed");
+        }
+        ("ppor", Some(m)) => {
+            let leaves = value_t!(m, "leaves", usize)
+                .expect("could not convert `leaves` CLI argument to `usize`");
+            let challenges = value_t!(m, "challenges", usize)
+                .expect("could not convert `challenges` CLI argument to `usize`");
+            let hasher = value_t!(m, "hasher", String)
+                .expect("could not convert `hasher` CLI argument to `String`");
+            ppor::run(&hasher, leaves, challenges).expect("ppor failed");
+        }
+        ("seal", Some(m)) => {
+            let sector_size_kibs = value_t!(m, "sector-size", usize)
+                .expect("could not convert `sector-size` CLI argument to `usize`");
+            let sectors = value_t!(m, "sectors", usize)
+                .expect("could not convert `sectors` CLI argument to `usize`");
+            let scratch_dir = m
+                .value_of("scratch-dir")
+                .map(PathBuf::from)
+                .unwrap_or_else(std::env::temp_dir);
+            seal::run(
+                sector_size_kibs * 1024,
+                sectors,
+                &scratch_dir,
+                m.value_of("resume").map(Path::new),
+                !m.is_present("no-progress"),
+            )
+            .expect("seal failed");
+        }
         _ => panic!("carnation"),
     }
 }
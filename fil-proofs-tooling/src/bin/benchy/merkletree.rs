@@ -0,0 +1,151 @@
+use std::cell::Cell;
+use std::fs;
+use std::path::Path;
+
+use anyhow::ensure;
+use fil_proofs_tooling::{measure_many, Metadata};
+use merkletree::store::{StoreConfig, DEFAULT_CACHED_ABOVE_BASE_LAYER};
+use serde::Serialize;
+use storage_proofs::hasher::{Blake2sHasher, Domain, Hasher, PedersenHasher, Sha256Hasher};
+use storage_proofs::merkle::{create_merkle_tree, MerkleTree};
+use storage_proofs::util::{data_at_node, NODE_SIZE};
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Report {
+    hasher: String,
+    leaves: usize,
+    samples: usize,
+    serial_wall_time_us: u64,
+    parallel_wall_time_us: u64,
+    speedup: f64,
+    store_size_bytes: u64,
+}
+
+fn build_serial<H: Hasher>(data: &[u8]) -> anyhow::Result<MerkleTree<H::Domain, H::Function>> {
+    MerkleTree::try_from_iter(
+        (0..data.len() / NODE_SIZE).map(|i| H::Domain::try_from_bytes(data_at_node(data, i)?)),
+    )
+}
+
+fn build_parallel<H: Hasher>(
+    data: &[u8],
+    config: Option<StoreConfig>,
+) -> anyhow::Result<MerkleTree<H::Domain, H::Function>> {
+    create_merkle_tree::<H>(config, data.len() / NODE_SIZE, data)
+}
+
+/// Total size of the files in `dir`.
+fn dir_size(dir: &Path) -> anyhow::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        size += entry?.metadata()?.len();
+    }
+
+    Ok(size)
+}
+
+/// Benchmarks building trees over `leaves` random leaves. The parallel builds store their trees
+/// in a fresh directory below `store_path`, which is removed afterwards.
+fn bench<H: Hasher>(
+    hasher: &str,
+    leaves: usize,
+    samples: usize,
+    store_path: &Path,
+) -> anyhow::Result<Report> {
+    let rng = &mut rand::thread_rng();
+    let data: Vec<u8> = (0..leaves)
+        .flat_map(|_| H::Domain::random(rng).into_bytes())
+        .collect();
+
+    let store_dir = tempfile::Builder::new()
+        .prefix("merkletree-build")
+        .tempdir_in(store_path)?;
+    let builds = Cell::new(0);
+    let store_config = || {
+        builds.set(builds.get() + 1);
+        StoreConfig::new(
+            store_dir.path(),
+            format!("{}-{}", hasher, builds.get()),
+            DEFAULT_CACHED_ABOVE_BASE_LAYER,
+        )
+    };
+
+    ensure!(
+        build_serial::<H>(&data)?.root()
+            == build_parallel::<H>(&data, Some(store_config()))?.root(),
+        "serial and parallel builds produced different roots"
+    );
+    let store_size_bytes = dir_size(store_dir.path())?;
+
+    let serial = measure_many(samples, || build_serial::<H>(&data))?;
+    let parallel = measure_many(samples, || build_parallel::<H>(&data, Some(store_config())))?;
+
+    store_dir.close()?;
+
+    let serial_wall_time = serial.wall_time.median;
+    let parallel_wall_time = parallel.wall_time.median;
+
+    Ok(Report {
+        hasher: hasher.into(),
+        leaves,
+        samples,
+        serial_wall_time_us: serial_wall_time.as_micros() as u64,
+        parallel_wall_time_us: parallel_wall_time.as_micros() as u64,
+        speedup: serial_wall_time.as_secs_f64() / parallel_wall_time.as_secs_f64(),
+        store_size_bytes,
+    })
+}
+
+pub fn run(size: usize, samples: usize, store_path: &Path) -> anyhow::Result<()> {
+    let leaves = size / NODE_SIZE;
+
+    let reports = vec![
+        bench::<PedersenHasher>("pedersen", leaves, samples, store_path)?,
+        bench::<Sha256Hasher>("sha256", leaves, samples, store_path)?,
+        bench::<Blake2sHasher>("blake2s", leaves, samples, store_path)?,
+    ];
+
+    // print reports
+    let wrapped = Metadata::wrap(reports)?;
+    serde_json::to_writer(std::io::stdout(), &wrapped)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roots_match<H: Hasher>() {
+        let rng = &mut rand::thread_rng();
+        let data: Vec<u8> = (0..64)
+            .flat_map(|_| H::Domain::random(rng).into_bytes())
+            .collect();
+
+        let serial = build_serial::<H>(&data).unwrap();
+        let parallel = build_parallel::<H>(&data, None).unwrap();
+
+        assert_eq!(serial.root(), parallel.root());
+    }
+
+    #[test]
+    fn test_roots_match() {
+        roots_match::<PedersenHasher>();
+        roots_match::<Sha256Hasher>();
+        roots_match::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn test_bench_small() {
+        let store_path = tempfile::tempdir().unwrap();
+        let report = bench::<Sha256Hasher>("sha256", 64, 2, store_path.path()).unwrap();
+
+        assert_eq!(report.leaves, 64);
+        assert_eq!(report.samples, 2);
+
+        // The trees were stored below `store_path`, and removed afterwards.
+        assert!(report.store_size_bytes >= (64 * NODE_SIZE) as u64);
+        assert_eq!(fs::read_dir(store_path.path()).unwrap().count(), 0);
+    }
+}
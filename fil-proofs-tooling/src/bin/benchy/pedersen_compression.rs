@@ -0,0 +1,84 @@
+use anyhow::ensure;
+use fil_proofs_tooling::{measure_many, Metadata};
+use paired::bls12_381::{Bls12, Fr};
+use rand::RngCore;
+use serde::Serialize;
+use storage_proofs::crypto::pedersen::{
+    pedersen, pedersen_compression_generic, Bits, JJ_PARAMS, PEDERSEN_BLOCK_BYTES,
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Report {
+    blocks: usize,
+    samples: usize,
+    precomputed_ns: f64,
+    generic_ns: f64,
+}
+
+/// Compresses every block with the BLS12-381 specific implementation, which uses precomputed
+/// window tables on x86_64.
+fn compress_precomputed(blocks: &[Vec<u8>]) -> Vec<Fr> {
+    blocks.iter().map(|block| pedersen(block)).collect()
+}
+
+/// Compresses every block with the engine generic implementation, which uses no precomputation.
+fn compress_generic(blocks: &[Vec<u8>]) -> Vec<Fr> {
+    blocks
+        .iter()
+        .map(|block| pedersen_compression_generic::<Bls12, _>(Bits::new(block), &JJ_PARAMS))
+        .collect()
+}
+
+/// Times both compression implementations over `blocks` random two block inputs, after checking
+/// that they agree on all of them.
+fn bench(blocks: usize, samples: usize) -> anyhow::Result<Report> {
+    let rng = &mut rand::thread_rng();
+    let data: Vec<Vec<u8>> = (0..blocks)
+        .map(|_| {
+            let mut block = vec![0u8; 2 * PEDERSEN_BLOCK_BYTES];
+            rng.fill_bytes(&mut block);
+            block
+        })
+        .collect();
+
+    ensure!(
+        compress_precomputed(&data) == compress_generic(&data),
+        "pedersen compression implementations disagree"
+    );
+
+    let precomputed = measure_many(samples, || Ok(compress_precomputed(&data)))?;
+    let generic = measure_many(samples, || Ok(compress_generic(&data)))?;
+
+    Ok(Report {
+        blocks,
+        samples,
+        precomputed_ns: precomputed.wall_time.median.as_nanos() as f64 / blocks as f64,
+        generic_ns: generic.wall_time.median.as_nanos() as f64 / blocks as f64,
+    })
+}
+
+pub fn run(blocks: usize, samples: usize) -> anyhow::Result<()> {
+    let report = bench(blocks, samples)?;
+
+    // print report
+    let wrapped = Metadata::wrap(report)?;
+    serde_json::to_writer(std::io::stdout(), &wrapped)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_small() {
+        let report = bench(4, 2).unwrap();
+
+        assert_eq!(report.blocks, 4);
+        assert_eq!(report.samples, 2);
+        assert!(report.precomputed_ns > 0.);
+        assert!(report.generic_ns > 0.);
+    }
+}
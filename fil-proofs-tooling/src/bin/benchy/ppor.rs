@@ -0,0 +1,172 @@
+use anyhow::{bail, ensure};
+use bellperson::groth16;
+use fil_proofs_tooling::{measure, Metadata};
+use log::info;
+use paired::bls12_381::Bls12;
+use rand::Rng;
+use serde::Serialize;
+use storage_proofs::circuit::por::{ppor_constraint_count, PoRCompound};
+use storage_proofs::compound_proof::{self, CompoundProof};
+use storage_proofs::hasher::{Blake2sHasher, Domain, Hasher, PedersenHasher, Sha256Hasher};
+use storage_proofs::merkle::create_merkle_tree;
+use storage_proofs::merklepor;
+use storage_proofs::proof::NoRequirements;
+use storage_proofs::util::data_at_node;
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Inputs {
+    hasher: String,
+    leaves: usize,
+    challenges: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Outputs {
+    constraints: usize,
+    setup_wall_time_ms: u64,
+    setup_cpu_time_ms: u64,
+    proving_wall_time_ms: u64,
+    proving_cpu_time_ms: u64,
+    verifying_wall_time_ms: u64,
+    verifying_cpu_time_ms: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Report {
+    inputs: Inputs,
+    outputs: Outputs,
+}
+
+/// Generates fresh groth parameters for a PoR over `leaves` leaves, then proves and verifies
+/// `challenges` random challenges against them. Verification fails the run if any proof does not
+/// verify.
+fn bench<H: 'static + Hasher>(leaves: usize, challenges: usize) -> anyhow::Result<Report> {
+    ensure!(
+        leaves.is_power_of_two() && leaves > 1,
+        "leaves must be a power of two larger than one, got {}",
+        leaves
+    );
+    ensure!(challenges > 0, "at least one challenge is required");
+
+    let mut rng = rand::thread_rng();
+    let data: Vec<u8> = (0..leaves)
+        .flat_map(|_| H::Domain::random(&mut rng).into_bytes())
+        .collect();
+    let tree = create_merkle_tree::<H>(None, leaves, &data)?;
+
+    let setup_params = compound_proof::SetupParams {
+        vanilla_params: merklepor::SetupParams {
+            leaves,
+            private: false,
+            bind_context: false,
+        },
+        partitions: None,
+    };
+    let pub_params = PoRCompound::<H>::setup(&setup_params)?;
+    let constraints = ppor_constraint_count::<H>(&pub_params.vanilla_params)?;
+    info!("constraints: {}", constraints);
+
+    // Generate the parameters directly, the parameter cache would hide the cost on later runs.
+    let setup = measure(|| {
+        let circuit = PoRCompound::<H>::blank_circuit(&pub_params.vanilla_params);
+        Ok(groth16::generate_random_parameters::<Bls12, _, _>(
+            circuit, &mut rng,
+        )?)
+    })?;
+    let groth_params = setup.return_value;
+
+    let pub_inputs = (0..challenges)
+        .map(|_| merklepor::PublicInputs {
+            challenge: rng.gen_range(0, leaves),
+            commitment: Some(tree.root()),
+            context: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+
+    let proving = measure(|| {
+        pub_inputs
+            .iter()
+            .map(|pub_in| {
+                let leaf = H::Domain::try_from_bytes(data_at_node(&data, pub_in.challenge)?)?;
+                let priv_in = merklepor::PrivateInputs::<H>::new(leaf, &tree);
+
+                PoRCompound::<H>::prove(&pub_params, pub_in, &priv_in, &groth_params)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+    })?;
+    let proofs = proving.return_value;
+
+    let verifying = measure(|| {
+        for (pub_in, proof) in pub_inputs.iter().zip(&proofs) {
+            ensure!(
+                PoRCompound::<H>::verify(&pub_params, pub_in, proof, &NoRequirements)?,
+                "proof of challenge {} failed to verify",
+                pub_in.challenge
+            );
+        }
+
+        Ok(())
+    })?;
+
+    Ok(Report {
+        inputs: Inputs {
+            hasher: H::name(),
+            leaves,
+            challenges,
+        },
+        outputs: Outputs {
+            constraints,
+            setup_wall_time_ms: setup.wall_time.as_millis() as u64,
+            setup_cpu_time_ms: setup.cpu_time.as_millis() as u64,
+            proving_wall_time_ms: proving.wall_time.as_millis() as u64,
+            proving_cpu_time_ms: proving.cpu_time.as_millis() as u64,
+            verifying_wall_time_ms: verifying.wall_time.as_millis() as u64,
+            verifying_cpu_time_ms: verifying.cpu_time.as_millis() as u64,
+        },
+    })
+}
+
+pub fn run(hasher: &str, leaves: usize, challenges: usize) -> anyhow::Result<()> {
+    info!(
+        "Benchy PPoR: hasher={} leaves={} challenges={}",
+        hasher, leaves, challenges
+    );
+
+    let report = match hasher {
+        "pedersen" => bench::<PedersenHasher>(leaves, challenges)?,
+        "sha256" => bench::<Sha256Hasher>(leaves, challenges)?,
+        "blake2s" => bench::<Blake2sHasher>(leaves, challenges)?,
+        _ => bail!("invalid hasher: {}", hasher),
+    };
+
+    // print report
+    let wrapped = Metadata::wrap(report)?;
+    serde_json::to_writer(std::io::stdout(), &wrapped)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_small() {
+        let report = bench::<PedersenHasher>(8, 2).unwrap();
+
+        assert_eq!(report.inputs.hasher, PedersenHasher::name());
+        assert_eq!(report.inputs.leaves, 8);
+        assert_eq!(report.inputs.challenges, 2);
+        assert!(report.outputs.constraints > 0);
+    }
+
+    #[test]
+    fn test_bench_rejects_bad_inputs() {
+        assert!(bench::<PedersenHasher>(6, 1).is_err());
+        assert!(bench::<PedersenHasher>(8, 0).is_err());
+        assert!(run("md5", 8, 1).is_err());
+    }
+}
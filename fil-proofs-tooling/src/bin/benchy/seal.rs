@@ -0,0 +1,794 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{stderr, stdout, Read, Seek, SeekFrom, Stderr, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use anyhow::ensure;
+use fil_proofs_tooling::{hex32, measure, FuncMeasurement, Metadata};
+use filecoin_proofs::constants::{
+    DEFAULT_POREP_PROOF_PARTITIONS, POST_CHALLENGED_NODES, POST_CHALLENGE_COUNT,
+};
+use filecoin_proofs::types::{
+    Commitment, PieceInfo, PoRepConfig, PoRepProofPartitions, PoStConfig, SealPreCommitOutput,
+    SectorSize, UnpaddedBytesAmount,
+};
+use filecoin_proofs::{
+    add_piece, generate_candidates, generate_piece_commitment, generate_post, seal_commit,
+    seal_pre_commit, verify_post, verify_seal, PrivateReplicaInfo, PublicReplicaInfo,
+};
+use log::{info, warn};
+use pbr::ProgressBar;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+use storage_proofs::sector::SectorId;
+
+use crate::shared::{CHALLENGE_COUNT, PROVER_ID, RANDOMNESS, TICKET_BYTES};
+
+const SEED: [u8; 32] = [0; 32];
+
+/// Prefix of the directory below the scratch dir in which the sectors of a run are sealed.
+const WORK_DIR_PREFIX: &str = "benchy-seal-";
+
+/// Name of the checkpoint file within a sector directory.
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Inputs {
+    sector_size: usize,
+    sectors: usize,
+    resume: bool,
+}
+
+/// Timings of a single phase, summed over all sectors.
+#[derive(Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Phase {
+    cpu_time_ms: u64,
+    wall_time_ms: u64,
+}
+
+impl Phase {
+    fn add<T>(&mut self, m: &FuncMeasurement<T>) {
+        self.cpu_time_ms += as_ms(m.cpu_time);
+        self.wall_time_ms += as_ms(m.wall_time);
+    }
+}
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Outputs {
+    add_piece: Phase,
+    seal_pre_commit: Phase,
+    seal_commit: Phase,
+    verify_seal: Phase,
+    generate_candidates: Phase,
+    generate_post: Phase,
+    verify_post: Phase,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Report {
+    inputs: Inputs,
+    outputs: Outputs,
+}
+
+impl Report {
+    /// Print all results to stdout
+    pub fn print(&self) {
+        let wrapped = Metadata::wrap(&self).expect("failed to retrieve metadata");
+        serde_json::to_writer(stdout(), &wrapped).expect("cannot write report JSON to stdout");
+    }
+}
+
+fn as_ms(d: Duration) -> u64 {
+    d.as_millis() as u64
+}
+
+/// Phases run for every sector: add piece, pre-commit, commit and seal verification.
+const SECTOR_PHASES: u64 = 4;
+
+/// Phases run once over all sectors: candidate generation, PoSt generation and verification.
+const POST_PHASES: u64 = 3;
+
+/// Progress of a run, advanced once per phase of every sector. It is only ever advanced in
+/// between measurements, so drawing it does not count towards the reported timings. The bar is
+/// drawn on stderr, stdout only carries the report.
+struct Progress {
+    bar: Option<ProgressBar<Stderr>>,
+    done: u64,
+    total: u64,
+}
+
+impl Progress {
+    fn new(sectors: usize, show: bool) -> Self {
+        let total = sectors as u64 * SECTOR_PHASES + POST_PHASES;
+        let bar = if show {
+            let mut bar = ProgressBar::on(stderr(), total);
+            bar.show_speed = false;
+            Some(bar)
+        } else {
+            None
+        };
+
+        Progress {
+            bar,
+            done: 0,
+            total,
+        }
+    }
+
+    /// Marks `phase` as done, also for phases skipped because they were resumed from a
+    /// checkpoint.
+    fn advance(&mut self, phase: &str) {
+        self.done += 1;
+        if let Some(bar) = self.bar.as_mut() {
+            bar.message(&format!("{} ", phase));
+            bar.inc();
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(bar) = self.bar.as_mut() {
+            bar.finish();
+        }
+    }
+}
+
+/// The sealing stages of a sector, in the order in which they are run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Stage {
+    AddPiece,
+    PreCommit,
+    Commit,
+}
+
+/// Records the last completed stage of a sector, together with the checksums of all files in the
+/// sector directory at that point. A checkpoint is only resumed from if these files are unchanged.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Checkpoint {
+    stage: Stage,
+    piece_commitment: Commitment,
+    comm_r: Option<Commitment>,
+    comm_d: Option<Commitment>,
+    /// Hex encoded blake2s checksums, keyed by the path relative to the sector directory.
+    checksums: BTreeMap<PathBuf, String>,
+}
+
+impl Checkpoint {
+    /// Loads the checkpoint of `sector_dir`. Returns `None` if there is none, or if any of the
+    /// checkpointed files is missing or was modified since.
+    fn load(sector_dir: &Path) -> anyhow::Result<Option<Checkpoint>> {
+        let path = sector_dir.join(CHECKPOINT_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let checkpoint: Checkpoint = match serde_json::from_reader(File::open(&path)?) {
+            Ok(checkpoint) => checkpoint,
+            Err(err) => {
+                warn!("ignoring unreadable checkpoint {:?}: {}", path, err);
+                return Ok(None);
+            }
+        };
+
+        for (file, expected) in &checkpoint.checksums {
+            let file = sector_dir.join(file);
+            let valid = file.is_file() && &checksum(&file)? == expected;
+            if !valid {
+                warn!("ignoring checkpoint {:?}, {:?} changed", path, file);
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(checkpoint))
+    }
+
+    /// Checksums all files of `sector_dir` and persists the checkpoint there.
+    fn save(&mut self, sector_dir: &Path) -> anyhow::Result<()> {
+        self.checksums.clear();
+        for file in list_files(sector_dir)? {
+            let relative = file.strip_prefix(sector_dir)?.to_path_buf();
+            if relative != Path::new(CHECKPOINT_FILE) {
+                self.checksums.insert(relative, checksum(&file)?);
+            }
+        }
+
+        // Write to a temporary file first, so a crash never leaves a truncated checkpoint behind.
+        let tmp_path = sector_dir.join(format!("{}.tmp", CHECKPOINT_FILE));
+        serde_json::to_writer(File::create(&tmp_path)?, self)?;
+        fs::rename(&tmp_path, sector_dir.join(CHECKPOINT_FILE))?;
+
+        Ok(())
+    }
+}
+
+/// All files below `dir`, recursively.
+fn list_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(list_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
+
+fn checksum(path: &Path) -> anyhow::Result<String> {
+    let mut file = File::open(path)?;
+    let mut state = blake2s_simd::State::new();
+    let mut buf = vec![0u8; 1 << 16];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        state.update(&buf[..read]);
+    }
+
+    Ok(hex32(state.finalize().as_array()))
+}
+
+fn porep_config(sector_size: usize) -> PoRepConfig {
+    PoRepConfig {
+        sector_size: SectorSize(sector_size as u64),
+        partitions: PoRepProofPartitions(DEFAULT_POREP_PROOF_PARTITIONS.load(Ordering::Relaxed)),
+    }
+}
+
+/// Runs the sealing stages of a single sector in `sector_dir` up to and including `until`,
+/// checkpointing after each of them. With `resume`, the stages completed by a valid checkpoint
+/// are skipped, otherwise the sector directory is recreated from scratch.
+fn seal_sector(
+    sector_dir: &Path,
+    sector_id: SectorId,
+    porep_config: PoRepConfig,
+    resume: bool,
+    until: Stage,
+    outputs: &mut Outputs,
+    progress: &mut Progress,
+) -> anyhow::Result<Checkpoint> {
+    let checkpoint = if resume {
+        Checkpoint::load(sector_dir)?
+    } else {
+        None
+    };
+
+    let cache_dir = sector_dir.join("cache");
+    let piece_path = sector_dir.join("piece");
+    let staged_path = sector_dir.join("staged");
+    let sealed_path = sector_dir.join("sealed");
+
+    let sector_size_unpadded_bytes_ammount = UnpaddedBytesAmount::from(porep_config);
+
+    let mut checkpoint = match checkpoint {
+        Some(checkpoint) => {
+            info!(
+                "resuming sector {:?} after {:?}",
+                sector_id, checkpoint.stage
+            );
+            checkpoint
+        }
+        None => {
+            if sector_dir.exists() {
+                fs::remove_dir_all(sector_dir)?;
+            }
+            fs::create_dir_all(&cache_dir)?;
+
+            // Generate the data from which we will create a replica. It is seeded by the sector
+            // id, so the same sector always yields the same replica.
+            let rng = &mut XorShiftRng::seed_from_u64(u64::from(sector_id));
+            let piece_bytes: Vec<u8> = (0..usize::from(sector_size_unpadded_bytes_ammount))
+                .map(|_| rng.gen())
+                .collect();
+
+            let mut piece_file = File::create(&piece_path)?;
+            piece_file.write_all(&piece_bytes)?;
+            piece_file.sync_all()?;
+            let mut piece_file = File::open(&piece_path)?;
+
+            let piece_info =
+                generate_piece_commitment(&mut piece_file, sector_size_unpadded_bytes_ammount)?;
+            piece_file.seek(SeekFrom::Start(0))?;
+
+            let mut staged_file = File::create(&staged_path)?;
+            let add_piece_measurement = measure(|| {
+                add_piece(
+                    &mut piece_file,
+                    &mut staged_file,
+                    sector_size_unpadded_bytes_ammount,
+                    &[],
+                )
+            })?;
+            outputs.add_piece.add(&add_piece_measurement);
+            staged_file.sync_all()?;
+
+            let mut checkpoint = Checkpoint {
+                stage: Stage::AddPiece,
+                piece_commitment: piece_info.commitment,
+                comm_r: None,
+                comm_d: None,
+                checksums: BTreeMap::new(),
+            };
+            checkpoint.save(sector_dir)?;
+            checkpoint
+        }
+    };
+    progress.advance("add piece");
+
+    let piece_infos = vec![PieceInfo {
+        commitment: checkpoint.piece_commitment,
+        size: sector_size_unpadded_bytes_ammount,
+    }];
+
+    if checkpoint.stage < Stage::PreCommit && until >= Stage::PreCommit {
+        File::create(&sealed_path)?;
+
+        let seal_pre_commit_measurement = measure(|| {
+            seal_pre_commit(
+                porep_config,
+                &cache_dir,
+                &staged_path,
+                &sealed_path,
+                PROVER_ID,
+                sector_id,
+                TICKET_BYTES,
+                &piece_infos,
+            )
+        })?;
+        outputs.seal_pre_commit.add(&seal_pre_commit_measurement);
+
+        let pre_commit = seal_pre_commit_measurement.return_value;
+        info!(
+            "seal_pre_commit: sector={:?} comm_r={} comm_d={}",
+            sector_id,
+            hex32(&pre_commit.comm_r),
+            hex32(&pre_commit.comm_d)
+        );
+
+        checkpoint.stage = Stage::PreCommit;
+        checkpoint.comm_r = Some(pre_commit.comm_r);
+        checkpoint.comm_d = Some(pre_commit.comm_d);
+        checkpoint.save(sector_dir)?;
+    }
+    if until >= Stage::PreCommit {
+        progress.advance("pre-commit");
+    }
+
+    if checkpoint.stage < Stage::Commit && until >= Stage::Commit {
+        let pre_commit = SealPreCommitOutput {
+            comm_r: checkpoint
+                .comm_r
+                .expect("pre-commit checkpoint without comm_r"),
+            comm_d: checkpoint
+                .comm_d
+                .expect("pre-commit checkpoint without comm_d"),
+        };
+
+        let seal_commit_measurement = measure(|| {
+            seal_commit(
+                porep_config,
+                &cache_dir,
+                PROVER_ID,
+                sector_id,
+                TICKET_BYTES,
+                SEED,
+                pre_commit,
+                &piece_infos,
+            )
+        })?;
+        outputs.seal_commit.add(&seal_commit_measurement);
+        fs::write(
+            sector_dir.join("proof"),
+            &seal_commit_measurement.return_value.proof,
+        )?;
+
+        checkpoint.stage = Stage::Commit;
+        checkpoint.save(sector_dir)?;
+    }
+    if until >= Stage::Commit {
+        progress.advance("commit");
+    }
+
+    Ok(checkpoint)
+}
+
+/// The directory the sectors of a run are sealed in. It is removed when dropped, so also when the
+/// run fails.
+struct WorkDir(PathBuf);
+
+impl WorkDir {
+    /// Creates a new directory below `scratch_dir`, unique to this run.
+    fn create(scratch_dir: &Path) -> anyhow::Result<Self> {
+        let dir = tempfile::Builder::new()
+            .prefix(WORK_DIR_PREFIX)
+            .tempdir_in(scratch_dir)?;
+
+        Ok(WorkDir(dir.into_path()))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_dir_all(&self.0) {
+            warn!("failed to remove work dir {:?}: {}", self.0, err);
+        }
+    }
+}
+
+/// Seals `sectors` sectors of `sector_size` bytes and runs an election PoSt over them. All files
+/// are created in a new directory below `scratch_dir`, which is removed once done. If a previous
+/// run was interrupted, `resume` names its directory, and its sectors are continued from their last
+/// checkpoint instead.
+fn bench(
+    sector_size: usize,
+    sectors: usize,
+    scratch_dir: &Path,
+    resume: Option<&Path>,
+    progress: &mut Progress,
+) -> anyhow::Result<Report> {
+    let work_dir = match resume {
+        Some(dir) => {
+            ensure!(
+                dir.is_dir(),
+                "cannot resume from {:?}, no such directory",
+                dir
+            );
+            WorkDir(dir.to_path_buf())
+        }
+        None => WorkDir::create(scratch_dir)?,
+    };
+    info!(
+        "sealing in {:?}, pass it to --resume to continue an interrupted run",
+        work_dir.path()
+    );
+
+    let porep_config = porep_config(sector_size);
+
+    let mut outputs = Outputs::default();
+    let mut pub_replica_info: BTreeMap<SectorId, PublicReplicaInfo> = BTreeMap::new();
+    let mut priv_replica_info: BTreeMap<SectorId, PrivateReplicaInfo> = BTreeMap::new();
+
+    for i in 0..sectors {
+        let sector_id = SectorId::from(i as u64);
+        let sector_dir = work_dir.path().join(format!("sector-{}", i));
+
+        let checkpoint = seal_sector(
+            &sector_dir,
+            sector_id,
+            porep_config,
+            resume.is_some(),
+            Stage::Commit,
+            &mut outputs,
+            progress,
+        )?;
+        let comm_r = checkpoint.comm_r.expect("sealed sector without comm_r");
+        let comm_d = checkpoint.comm_d.expect("sealed sector without comm_d");
+        let proof = fs::read(sector_dir.join("proof"))?;
+
+        let verify_seal_measurement = measure(|| {
+            verify_seal(
+                porep_config,
+                comm_r,
+                comm_d,
+                PROVER_ID,
+                sector_id,
+                TICKET_BYTES,
+                SEED,
+                &proof,
+            )
+        })?;
+        outputs.verify_seal.add(&verify_seal_measurement);
+        ensure!(
+            verify_seal_measurement.return_value,
+            "seal proof for sector {:?} did not verify",
+            sector_id
+        );
+        progress.advance("verify seal");
+
+        pub_replica_info.insert(sector_id, PublicReplicaInfo::new(comm_r)?);
+        priv_replica_info.insert(
+            sector_id,
+            PrivateReplicaInfo::new(
+                sector_dir
+                    .join("sealed")
+                    .to_str()
+                    .expect("file name is not a UTF-8 string")
+                    .to_string(),
+                comm_r,
+                sector_dir.join("cache"),
+            )?,
+        );
+    }
+
+    // Measure PoSt generation and verification over all sealed sectors.
+    let post_config = PoStConfig {
+        sector_size: SectorSize(sector_size as u64),
+        challenge_count: POST_CHALLENGE_COUNT,
+        challenged_nodes: POST_CHALLENGED_NODES,
+    };
+
+    let gen_candidates_measurement = measure(|| {
+        generate_candidates(
+            post_config,
+            &RANDOMNESS,
+            CHALLENGE_COUNT,
+            &priv_replica_info,
+            PROVER_ID,
+        )
+    })?;
+    outputs.generate_candidates.add(&gen_candidates_measurement);
+    progress.advance("generate candidates");
+
+    let candidates = gen_candidates_measurement.return_value;
+
+    let gen_post_measurement = measure(|| {
+        generate_post(
+            post_config,
+            &RANDOMNESS,
+            &priv_replica_info,
+            candidates.clone(),
+            PROVER_ID,
+        )
+    })?;
+    outputs.generate_post.add(&gen_post_measurement);
+    progress.advance("generate PoSt");
+
+    let verify_post_measurement = measure(|| {
+        verify_post(
+            post_config,
+            &RANDOMNESS,
+            CHALLENGE_COUNT,
+            &gen_post_measurement.return_value,
+            &pub_replica_info,
+            &candidates,
+            PROVER_ID,
+        )
+    })?;
+    outputs.verify_post.add(&verify_post_measurement);
+    ensure!(verify_post_measurement.return_value, "PoSt did not verify");
+    progress.advance("verify PoSt");
+    progress.finish();
+
+    Ok(Report {
+        inputs: Inputs {
+            sector_size,
+            sectors,
+            resume: resume.is_some(),
+        },
+        outputs,
+    })
+}
+
+pub fn run(
+    sector_size: usize,
+    sectors: usize,
+    scratch_dir: &Path,
+    resume: Option<&Path>,
+    show_progress: bool,
+) -> anyhow::Result<()> {
+    info!(
+        "Benchy Seal: sector-size={} sectors={} scratch-dir={:?} resume={:?}",
+        sector_size, sectors, scratch_dir, resume
+    );
+
+    let mut progress = Progress::new(sectors, show_progress);
+    bench(sector_size, sectors, scratch_dir, resume, &mut progress)?.print();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use filecoin_proofs::constants::SECTOR_SIZE_ONE_KIB;
+
+    #[test]
+    #[ignore]
+    fn test_seal_cleans_up_scratch_dir() {
+        let scratch_dir = tempfile::tempdir().unwrap();
+
+        let mut progress = Progress::new(1, false);
+        let report = bench(
+            SECTOR_SIZE_ONE_KIB as usize,
+            1,
+            scratch_dir.path(),
+            None,
+            &mut progress,
+        )
+        .unwrap();
+        assert_eq!(report.inputs.sectors, 1);
+        assert_eq!(progress.done, progress.total);
+
+        assert_eq!(
+            fs::read_dir(scratch_dir.path()).unwrap().count(),
+            0,
+            "scratch files were left behind"
+        );
+    }
+
+    #[test]
+    fn test_work_dir_is_unique_and_removed() {
+        let scratch_dir = tempfile::tempdir().unwrap();
+
+        let a = WorkDir::create(scratch_dir.path()).unwrap();
+        let b = WorkDir::create(scratch_dir.path()).unwrap();
+        assert_ne!(a.path(), b.path());
+        fs::create_dir_all(a.path().join("sector-0")).unwrap();
+
+        drop(a);
+        drop(b);
+        assert_eq!(
+            fs::read_dir(scratch_dir.path()).unwrap().count(),
+            0,
+            "work dirs were left behind"
+        );
+    }
+
+    #[test]
+    fn test_resume_from_missing_dir_fails() {
+        let scratch_dir = tempfile::tempdir().unwrap();
+        let missing = scratch_dir.path().join("missing");
+
+        let result = bench(
+            SECTOR_SIZE_ONE_KIB as usize,
+            1,
+            scratch_dir.path(),
+            Some(&missing),
+            &mut Progress::new(1, false),
+        );
+        assert!(result.is_err());
+        assert!(!missing.exists());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_resume_reproduces_comm_r() {
+        let porep_config = porep_config(SECTOR_SIZE_ONE_KIB as usize);
+        let sector_id = SectorId::from(0);
+
+        let uninterrupted_dir = tempfile::tempdir().unwrap();
+        let uninterrupted = seal_sector(
+            uninterrupted_dir.path(),
+            sector_id,
+            porep_config,
+            false,
+            Stage::Commit,
+            &mut Outputs::default(),
+            &mut Progress::new(1, false),
+        )
+        .unwrap();
+
+        // Interrupt the run once the replica has been created.
+        let resumed_dir = tempfile::tempdir().unwrap();
+        let interrupted = seal_sector(
+            resumed_dir.path(),
+            sector_id,
+            porep_config,
+            false,
+            Stage::PreCommit,
+            &mut Outputs::default(),
+            &mut Progress::new(1, false),
+        )
+        .unwrap();
+        assert_eq!(interrupted.stage, Stage::PreCommit);
+
+        let resumed = seal_sector(
+            resumed_dir.path(),
+            sector_id,
+            porep_config,
+            true,
+            Stage::Commit,
+            &mut Outputs::default(),
+            &mut Progress::new(1, false),
+        )
+        .unwrap();
+        assert_eq!(resumed.stage, Stage::Commit);
+        assert_eq!(resumed.comm_r, interrupted.comm_r);
+        assert_eq!(resumed.comm_r, uninterrupted.comm_r);
+        assert_eq!(
+            fs::read(resumed_dir.path().join("proof")).unwrap().len(),
+            fs::read(uninterrupted_dir.path().join("proof"))
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_progress_completes() {
+        let scratch_dir = tempfile::tempdir().unwrap();
+        let sectors = 2;
+
+        let mut progress = Progress::new(sectors, false);
+        let report = bench(
+            SECTOR_SIZE_ONE_KIB as usize,
+            sectors,
+            scratch_dir.path(),
+            None,
+            &mut progress,
+        )
+        .unwrap();
+
+        assert_eq!(progress.total, 2 * SECTOR_PHASES + POST_PHASES);
+        assert_eq!(progress.done, progress.total);
+
+        // Every phase was measured, with the progress updates outside the measurements.
+        assert!(report.outputs.seal_pre_commit.wall_time_ms > 0);
+        assert!(report.outputs.seal_commit.wall_time_ms > 0);
+    }
+
+    #[test]
+    fn test_progress_counts_resumed_phases() {
+        let porep_config = porep_config(SECTOR_SIZE_ONE_KIB as usize);
+        let sector_dir = tempfile::tempdir().unwrap();
+
+        let mut progress = Progress::new(1, false);
+        seal_sector(
+            sector_dir.path(),
+            SectorId::from(0),
+            porep_config,
+            false,
+            Stage::AddPiece,
+            &mut Outputs::default(),
+            &mut progress,
+        )
+        .unwrap();
+        assert_eq!(progress.done, 1);
+
+        // Resuming skips adding the piece, but still counts it.
+        let mut resumed = Progress::new(1, false);
+        seal_sector(
+            sector_dir.path(),
+            SectorId::from(0),
+            porep_config,
+            true,
+            Stage::AddPiece,
+            &mut Outputs::default(),
+            &mut resumed,
+        )
+        .unwrap();
+        assert_eq!(resumed.done, 1);
+    }
+
+    #[test]
+    fn test_modified_checkpoint_is_ignored() {
+        let porep_config = porep_config(SECTOR_SIZE_ONE_KIB as usize);
+        let sector_dir = tempfile::tempdir().unwrap();
+
+        seal_sector(
+            sector_dir.path(),
+            SectorId::from(0),
+            porep_config,
+            false,
+            Stage::AddPiece,
+            &mut Outputs::default(),
+            &mut Progress::new(1, false),
+        )
+        .unwrap();
+        let checkpoint = Checkpoint::load(sector_dir.path()).unwrap().unwrap();
+        assert_eq!(checkpoint.stage, Stage::AddPiece);
+        assert!(checkpoint.checksums.contains_key(Path::new("staged")));
+
+        let mut staged = fs::read(sector_dir.path().join("staged")).unwrap();
+        staged[0] ^= 1;
+        fs::write(sector_dir.path().join("staged"), &staged).unwrap();
+
+        assert!(Checkpoint::load(sector_dir.path()).unwrap().is_none());
+    }
+}
@@ -3,13 +3,14 @@ use std::sync::atomic::Ordering;
 
 use tempfile::NamedTempFile;
 
-use fil_proofs_tooling::{measure, FuncMeasurement};
+use fil_proofs_tooling::{hex32, measure, FuncMeasurement};
 use filecoin_proofs::constants::DEFAULT_POREP_PROOF_PARTITIONS;
 use filecoin_proofs::types::{PaddedBytesAmount, PoRepConfig, SectorSize, UnpaddedBytesAmount};
 use filecoin_proofs::{
     add_piece, generate_piece_commitment, seal_pre_commit, PieceInfo, PoRepProofPartitions,
     PrivateReplicaInfo, PublicReplicaInfo, SealPreCommitOutput,
 };
+use log::info;
 use storage_proofs::sector::SectorId;
 
 pub(super) const CHALLENGE_COUNT: u64 = 1;
@@ -108,6 +109,13 @@ pub fn create_replicas(
         })
         .expect("seal_pre_commit produced an error");
 
+        info!(
+            "seal_pre_commit: sector={:?} comm_r={} comm_d={}",
+            sector_id,
+            hex32(&seal_pre_commit_output.return_value.comm_r),
+            hex32(&seal_pre_commit_output.return_value.comm_d)
+        );
+
         let priv_info = PrivateReplicaInfo::new(
             sealed_path_string.to_string(),
             seal_pre_commit_output.return_value.comm_r,
@@ -0,0 +1,45 @@
+use anyhow::{ensure, Result};
+
+/// Renders a 32 byte value, e.g. a commitment, as 64 lowercase hex characters.
+pub fn hex32(bytes: &[u8; 32]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parses the output of `hex32` back into bytes.
+pub fn parse_hex32(s: &str) -> Result<[u8; 32]> {
+    ensure!(
+        s.len() == 64 && s.is_ascii(),
+        "expected 64 hex characters, got {:?}",
+        s
+    );
+
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16)?;
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex32() {
+        let mut bytes = [0u8; 32];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = (i * 9) as u8;
+        }
+
+        let s = hex32(&bytes);
+        assert_eq!(s.len(), 64);
+        assert!(s
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)));
+        assert_eq!(parse_hex32(&s).unwrap(), bytes);
+
+        assert!(parse_hex32("abc").is_err());
+        assert!(parse_hex32(&"zz".repeat(32)).is_err());
+    }
+}
@@ -1,5 +1,7 @@
+pub mod fmt;
 pub mod measure;
 pub mod metadata;
 
-pub use measure::{measure, FuncMeasurement};
+pub use fmt::hex32;
+pub use measure::{measure, measure_many, FuncMeasurement, MeasurementStats};
 pub use metadata::Metadata;
@@ -1,6 +1,6 @@
 use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use cpu_time::ProcessTime;
 
 pub struct FuncMeasurement<T> {
@@ -24,3 +24,92 @@ where
         return_value: x,
     })
 }
+
+/// Distribution of a set of measured durations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DurationStats {
+    pub min: Duration,
+    pub median: Duration,
+    pub p95: Duration,
+    pub max: Duration,
+}
+
+impl DurationStats {
+    fn from_samples(mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+
+        // nearest-rank percentile: the sample at rank ceil(p * n / 100)
+        let percentile = |p: usize| samples[(samples.len() * p + 99) / 100 - 1];
+
+        DurationStats {
+            min: samples[0],
+            median: percentile(50),
+            p95: percentile(95),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeasurementStats {
+    pub runs: usize,
+    pub cpu_time: DurationStats,
+    pub wall_time: DurationStats,
+}
+
+/// Runs `f` `n` times, and reports the distribution of cpu and wall time across all runs.
+pub fn measure_many<T, F>(n: usize, f: F) -> Result<MeasurementStats>
+where
+    F: Fn() -> Result<T>,
+{
+    ensure!(n > 0, "at least one run is required");
+
+    let mut cpu_times = Vec::with_capacity(n);
+    let mut wall_times = Vec::with_capacity(n);
+
+    for _ in 0..n {
+        let m = measure(&f)?;
+        cpu_times.push(m.cpu_time);
+        wall_times.push(m.wall_time);
+    }
+
+    Ok(MeasurementStats {
+        runs: n,
+        cpu_time: DurationStats::from_samples(cpu_times),
+        wall_time: DurationStats::from_samples(wall_times),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_many() {
+        let stats = measure_many(20, || Ok((0..1000u64).sum::<u64>())).unwrap();
+
+        assert_eq!(stats.runs, 20);
+
+        for s in &[stats.cpu_time, stats.wall_time] {
+            assert!(s.min <= s.median);
+            assert!(s.median <= s.p95);
+            assert!(s.p95 <= s.max);
+        }
+    }
+
+    #[test]
+    fn test_duration_stats_nearest_rank() {
+        let samples = (1..=10).rev().map(Duration::from_millis).collect();
+        let stats = DurationStats::from_samples(samples);
+
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.median, Duration::from_millis(5));
+        assert_eq!(stats.p95, Duration::from_millis(10));
+        assert_eq!(stats.max, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_measure_many_requires_runs() {
+        assert!(measure_many(0, || Ok(())).is_err());
+    }
+}
@@ -29,6 +29,7 @@ pub(crate) mod util;
 
 pub use self::post::*;
 pub use self::seal::*;
+pub use self::util::commitment_diff;
 use std::io;
 use storage_proofs::pieces::generate_piece_commitment_bytes_from_source;
 
@@ -161,6 +162,17 @@ pub fn generate_piece_commitment<T: std::io::Read>(
     PieceInfo::new(commitment, piece_size)
 }
 
+/// Computes the commitment of a single piece, the root of the merkle tree over the fr32-padded
+/// piece. This is the subtree the piece contributes to its sector's `comm_d`.
+///
+/// # Arguments
+///
+/// * `source` - a readable source of unprocessed piece bytes.
+/// * `piece_size` - the number of unpadded user-bytes which can be read from source before EOF.
+pub fn piece_commitment<T: Read>(source: T, piece_size: UnpaddedBytesAmount) -> Result<Commitment> {
+    generate_piece_commitment(source, piece_size).map(|info| info.commitment)
+}
+
 /// Computes a NUL-byte prefix and/or suffix for `source` using the provided
 /// `piece_lengths` and `piece_size` (such that the `source`, after
 /// preprocessing, will occupy a subtree of a merkle tree built using the bytes
@@ -315,7 +327,7 @@ where
 mod tests {
     use super::*;
 
-    use std::collections::BTreeMap;
+    use std::collections::{BTreeMap, BTreeSet};
     use std::io::{Seek, SeekFrom, Write};
     use std::sync::atomic::Ordering;
     use std::sync::Once;
@@ -324,7 +336,7 @@ mod tests {
     use paired::bls12_381::{Bls12, Fr};
     use rand::{Rng, SeedableRng};
     use rand_xorshift::XorShiftRng;
-    use storage_proofs::election_post::Candidate;
+    use storage_proofs::election_post::{generate_sector_challenge, Candidate};
     use storage_proofs::fr32::bytes_into_fr;
     use tempfile::NamedTempFile;
 
@@ -458,6 +470,176 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_verify_post_rejects_other_randomness() -> Result<()> {
+        let post_config = PoStConfig {
+            sector_size: SectorSize(SECTOR_SIZE_ONE_KIB),
+            challenge_count: crate::constants::POST_CHALLENGE_COUNT,
+            challenged_nodes: crate::constants::POST_CHALLENGED_NODES,
+        };
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        let mut replicas = BTreeMap::new();
+        for sector_id in 0..16u64 {
+            replicas.insert(sector_id.into(), PublicReplicaInfo::new([1; 32])?);
+        }
+        let sectors: BTreeSet<SectorId> = replicas.keys().copied().collect();
+
+        let randomness: [u8; 32] = rng.gen();
+        let winner = Candidate {
+            sector_id: generate_sector_challenge(&randomness, 0, &sectors)?,
+            partial_ticket: Fr::zero(),
+            ticket: [0; 32],
+            sector_challenge_index: 0,
+        };
+        let other_randomness = loop {
+            let other: [u8; 32] = rng.gen();
+            if generate_sector_challenge(&other, 0, &sectors)? != winner.sector_id {
+                break other;
+            }
+        };
+
+        // The sector challenges are checked before the proof, so no parameters are needed to
+        // reject the winner.
+        let verified = verify_post(
+            post_config,
+            &other_randomness,
+            1,
+            &[vec![0u8; SINGLE_PARTITION_PROOF_LEN]][..],
+            &replicas,
+            &[winner.clone()][..],
+            [0; 32],
+        )?;
+        assert!(!verified, "verified PoSt against other randomness");
+
+        assert!(verify_post(
+            post_config,
+            &randomness,
+            1,
+            &[vec![0u8; 2 * SINGLE_PARTITION_PROOF_LEN]][..],
+            &replicas,
+            &[winner][..],
+            [0; 32],
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_comm_d_from_staged() -> Result<()> {
+        let sector_size = SectorSize(SECTOR_SIZE_ONE_KIB);
+        let number_of_bytes_in_piece = UnpaddedBytesAmount::from(PaddedBytesAmount(256));
+
+        let piece_bytes: Vec<u8> = (0..number_of_bytes_in_piece.0)
+            .map(|_| rand::random::<u8>())
+            .collect();
+
+        let mut piece_file = NamedTempFile::new()?;
+        piece_file.write_all(&piece_bytes)?;
+        piece_file.as_file_mut().seek(SeekFrom::Start(0))?;
+
+        let piece_info =
+            generate_piece_commitment(piece_file.as_file_mut(), number_of_bytes_in_piece)?;
+        piece_file.as_file_mut().seek(SeekFrom::Start(0))?;
+
+        // The piece covers a quarter of the sector, the rest is zero-padded.
+        let mut staged_sector_file = NamedTempFile::new()?;
+        add_piece(
+            &mut piece_file,
+            &mut staged_sector_file,
+            number_of_bytes_in_piece,
+            &[],
+        )?;
+
+        let config = PoRepConfig {
+            sector_size,
+            partitions: PoRepProofPartitions(
+                DEFAULT_POREP_PROOF_PARTITIONS.load(Ordering::Relaxed),
+            ),
+        };
+        let expected = compute_comm_d(config, &[piece_info])?;
+
+        assert_eq!(
+            compute_comm_d_from_staged(staged_sector_file.path(), sector_size)?,
+            expected
+        );
+
+        // Padding the file explicitly does not change the commitment.
+        staged_sector_file
+            .as_file_mut()
+            .set_len(u64::from(sector_size))?;
+        assert_eq!(
+            compute_comm_d_from_staged(staged_sector_file.path(), sector_size)?,
+            expected
+        );
+
+        staged_sector_file
+            .as_file_mut()
+            .set_len(u64::from(sector_size) + 1)?;
+        assert!(compute_comm_d_from_staged(staged_sector_file.path(), sector_size).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_piece_commitment_of_full_sector() -> Result<()> {
+        let sector_size = SectorSize(SECTOR_SIZE_ONE_KIB);
+        let piece_size = UnpaddedBytesAmount::from(PaddedBytesAmount::from(sector_size));
+
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let piece_bytes: Vec<u8> = (0..piece_size.0).map(|_| rng.gen()).collect();
+
+        let mut staged_sector_file = NamedTempFile::new()?;
+        add_piece(&piece_bytes[..], &mut staged_sector_file, piece_size, &[])?;
+
+        assert_eq!(
+            compute_comm_d_from_staged(staged_sector_file.path(), sector_size)?,
+            piece_commitment(&piece_bytes[..], piece_size)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_seal_with_tampered_pieces() -> Result<()> {
+        let config = PoRepConfig {
+            sector_size: SectorSize(SECTOR_SIZE_ONE_KIB),
+            partitions: PoRepProofPartitions(
+                DEFAULT_POREP_PROOF_PARTITIONS.load(Ordering::Relaxed),
+            ),
+        };
+        let piece_size = UnpaddedBytesAmount::from(PaddedBytesAmount(SECTOR_SIZE_ONE_KIB / 2));
+
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let piece_infos = (0..2)
+            .map(|_| {
+                let piece_bytes: Vec<u8> = (0..piece_size.0).map(|_| rng.gen()).collect();
+                generate_piece_commitment(&piece_bytes[..], piece_size)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let comm_d = compute_comm_d(config, &piece_infos)?;
+
+        let mut tampered_piece_infos = piece_infos.clone();
+        tampered_piece_infos[1].commitment[0] ^= 1;
+
+        // The pieces are checked before the proof, so no parameters are needed to reject them.
+        let verified = verify_seal_with_pieces(
+            config,
+            [1; 32],
+            comm_d,
+            [0; 32],
+            SectorId::from(0),
+            [0; 32],
+            [0; 32],
+            &[],
+            &tampered_piece_infos,
+        )?;
+        assert!(!verified, "verified seal with tampered pieces");
+
+        Ok(())
+    }
+
     #[test]
     #[ignore]
     fn test_seal_lifecycle() -> Result<()> {
@@ -561,6 +743,14 @@ mod tests {
             "Computed and expected comm_d don't match."
         );
 
+        let staged_comm_d =
+            compute_comm_d_from_staged(staged_sector_file.path(), config.sector_size)?;
+
+        assert_eq!(
+            comm_d, staged_comm_d,
+            "comm_d of the staged sector and the sealed one don't match."
+        );
+
         let verified = verify_seal(
             config,
             comm_r,
@@ -573,6 +763,113 @@ mod tests {
         )?;
         assert!(verified, "failed to verify valid seal");
 
+        let verified = verify_seal_with_pieces(
+            config,
+            comm_r,
+            comm_d,
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            &commit_output.proof,
+            &piece_infos,
+        )?;
+        assert!(verified, "failed to verify valid seal with its pieces");
+
+        let mut tampered_piece_infos = piece_infos.clone();
+        tampered_piece_infos[0].commitment[0] ^= 1;
+
+        let verified = verify_seal_with_pieces(
+            config,
+            comm_r,
+            comm_d,
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            &commit_output.proof,
+            &tampered_piece_infos,
+        )?;
+        assert!(!verified, "verified seal with tampered pieces");
+
+        // The PoSt must be bound to the randomness it was generated with.
+        let post_config = PoStConfig {
+            sector_size: SectorSize(sector_size),
+            challenge_count: crate::constants::POST_CHALLENGE_COUNT,
+            challenged_nodes: crate::constants::POST_CHALLENGED_NODES,
+        };
+        let randomness = rng.gen();
+        let challenge_count = 1;
+
+        let mut priv_replicas = BTreeMap::new();
+        priv_replicas.insert(
+            sector_id,
+            PrivateReplicaInfo::new(
+                sealed_sector_file.path().to_string_lossy().into_owned(),
+                comm_r,
+                cache_dir.path().to_path_buf(),
+            )?,
+        );
+        let mut pub_replicas = BTreeMap::new();
+        pub_replicas.insert(sector_id, PublicReplicaInfo::new(comm_r)?);
+
+        let candidates = generate_candidates(
+            post_config,
+            &randomness,
+            challenge_count,
+            &priv_replicas,
+            prover_id,
+        )?;
+        let proofs = generate_post(
+            post_config,
+            &randomness,
+            &priv_replicas,
+            candidates.clone(),
+            prover_id,
+        )?;
+
+        let verified = verify_post(
+            post_config,
+            &randomness,
+            challenge_count,
+            &proofs,
+            &pub_replicas,
+            &candidates,
+            prover_id,
+        )?;
+        assert!(verified, "failed to verify valid PoSt");
+
+        let mut other_randomness = randomness;
+        other_randomness[0] ^= 1;
+        let verified = verify_post(
+            post_config,
+            &other_randomness,
+            challenge_count,
+            &proofs,
+            &pub_replicas,
+            &candidates,
+            prover_id,
+        );
+        assert!(
+            !verified.unwrap_or(false),
+            "verified PoSt against other randomness"
+        );
+
+        let extended_proofs: Vec<Vec<u8>> = proofs
+            .iter()
+            .map(|proof| [&proof[..], &proof[..]].concat())
+            .collect();
+        assert!(verify_post(
+            post_config,
+            &randomness,
+            challenge_count,
+            &extended_proofs,
+            &pub_replicas,
+            &candidates,
+            prover_id,
+        )
+        .is_err());
+
         Ok(())
     }
 }
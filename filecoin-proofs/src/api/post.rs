@@ -23,6 +23,7 @@ use storage_proofs::stacked::CacheKey;
 
 use crate::api::util::as_safe_commitment;
 use crate::caches::{get_post_params, get_post_verifying_key};
+use crate::constants::SINGLE_PARTITION_PROOF_LEN;
 use crate::parameters::post_setup_params;
 use crate::types::{
     ChallengeSeed, Commitment, PersistentAux, PoStConfig, ProverId, SectorSize, Tree,
@@ -360,21 +361,23 @@ pub fn verify_post(
         "Missmatch between winners and proofs"
     );
 
+    // Check the winners against the randomness before loading the verifying key.
     let sectors = replicas.keys().copied().collect();
-    let vanilla_params = post_setup_params(post_config);
-    let setup_params = compound_proof::SetupParams {
-        vanilla_params,
-        partitions: None,
-    };
-    let pub_params: compound_proof::PublicParams<election_post::ElectionPoSt<DefaultTreeHasher>> =
-        ElectionPoStCompound::setup(&setup_params)?;
-
-    let verifying_key = get_post_verifying_key(post_config)?;
+    let mut comm_rs = Vec::with_capacity(winners.len());
     for (proof, winner) in proofs.iter().zip(winners.iter()) {
+        // Every winner is proven with a single partition, reject proofs carrying more (or less),
+        // rather than verifying only their first partition.
+        ensure!(
+            proof.len() == SINGLE_PARTITION_PROOF_LEN,
+            "PoSt proof is {} bytes, expected a single partition of {} bytes",
+            proof.len(),
+            SINGLE_PARTITION_PROOF_LEN
+        );
+
         let replica = replicas
             .get(&winner.sector_id)
             .with_context(|| format!("Missing replica for sector: {}", winner.sector_id))?;
-        let comm_r = replica.safe_comm_r()?;
+        comm_rs.push(replica.safe_comm_r()?);
 
         if !election_post::is_valid_sector_challenge_index(
             challenge_count,
@@ -391,7 +394,18 @@ pub fn verify_post(
         if expected_sector_id != winner.sector_id {
             return Ok(false);
         }
+    }
+
+    let vanilla_params = post_setup_params(post_config);
+    let setup_params = compound_proof::SetupParams {
+        vanilla_params,
+        partitions: None,
+    };
+    let pub_params: compound_proof::PublicParams<election_post::ElectionPoSt<DefaultTreeHasher>> =
+        ElectionPoStCompound::setup(&setup_params)?;
 
+    let verifying_key = get_post_verifying_key(post_config)?;
+    for ((proof, winner), comm_r) in proofs.iter().zip(winners.iter()).zip(comm_rs) {
         let proof = MultiProof::new_from_reader(None, &proof[..], &verifying_key)?;
         let pub_inputs = election_post::PublicInputs {
             randomness: *randomness,
@@ -3,12 +3,11 @@ use std::io::prelude::*;
 use std::path::Path;
 use std::sync::atomic::Ordering;
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use bincode::{deserialize, serialize};
 use log::info;
 use memmap::MmapOptions;
 use merkletree::store::{StoreConfig, DEFAULT_CACHED_ABOVE_BASE_LAYER};
-use paired::bls12_381::{Bls12, Fr};
 use storage_proofs::circuit::multi_proof::MultiProof;
 use storage_proofs::circuit::stacked::StackedCompound;
 use storage_proofs::compound_proof::{self, CompoundProof};
@@ -21,8 +20,9 @@ use storage_proofs::stacked::{
     self, generate_replica_id, CacheKey, ChallengeRequirements, StackedDrg, Tau, TemporaryAux,
     TemporaryAuxCache,
 };
+use storage_proofs::util::NODE_SIZE;
 
-use crate::api::util::{as_safe_commitment, commitment_from_fr};
+use crate::api::util::{as_safe_commitment, commitment_diff, ToCommitment};
 use crate::caches::{get_stacked_params, get_stacked_verifying_key};
 use crate::constants::{
     DefaultPieceHasher, POREP_WINDOW_MINIMUM_CHALLENGES, SINGLE_PARTITION_PROOF_LEN,
@@ -32,7 +32,7 @@ pub use crate::pieces;
 pub use crate::pieces::verify_pieces;
 use crate::types::{
     Commitment, PaddedBytesAmount, PieceInfo, PoRepConfig, PoRepProofPartitions, ProverId,
-    SealCommitOutput, SealPreCommitOutput, Ticket,
+    SealCommitOutput, SealPreCommitOutput, SectorSize, Ticket,
 };
 
 /// Seals the staged sector at `in_path` in place, saving the resulting replica to `out_path`.
@@ -60,6 +60,13 @@ pub fn seal_pre_commit<R: AsRef<Path>, T: AsRef<Path>, S: AsRef<Path>>(
 ) -> Result<SealPreCommitOutput> {
     info!("seal_pre_commit: start");
     let sector_bytes = usize::from(PaddedBytesAmount::from(porep_config));
+    storage_proofs::enter_span!(
+        "seal_pre_commit",
+        sector_size = sector_bytes,
+        nodes = sector_bytes / NODE_SIZE
+    );
+
+    pieces::check_pieces_fit(porep_config.into(), piece_infos)?;
 
     fs::metadata(&in_path)
         .with_context(|| format!("could not read in_path={:?})", in_path.as_ref()))?;
@@ -119,13 +126,9 @@ pub fn seal_pre_commit<R: AsRef<Path>, T: AsRef<Path>, S: AsRef<Path>>(
         &data,
     )?;
 
-    let comm_d_root: Fr = data_tree.root().into();
-    let comm_d = commitment_from_fr::<Bls12>(comm_d_root);
+    let comm_d = data_tree.root().to_commitment();
 
-    ensure!(
-        verify_pieces(&comm_d, piece_infos, porep_config.into())?,
-        "pieces and comm_d do not match"
-    );
+    ensure_pieces_match(&comm_d, piece_infos, porep_config.into())?;
 
     let replica_id = generate_replica_id::<DefaultTreeHasher, _>(
         &prover_id,
@@ -142,7 +145,7 @@ pub fn seal_pre_commit<R: AsRef<Path>, T: AsRef<Path>, S: AsRef<Path>>(
         Some(config),
     )?;
 
-    let comm_r = commitment_from_fr::<Bls12>(tau.comm_r.into());
+    let comm_r = tau.comm_r.to_commitment();
 
     info!("seal_pre_commit: end");
 
@@ -166,6 +169,25 @@ pub fn seal_pre_commit<R: AsRef<Path>, T: AsRef<Path>, S: AsRef<Path>>(
     Ok(SealPreCommitOutput { comm_r, comm_d })
 }
 
+/// Checks that the commitment of `piece_infos` is `comm_d`, reporting where they differ if not.
+fn ensure_pieces_match(
+    comm_d: &Commitment,
+    piece_infos: &[PieceInfo],
+    sector_size: SectorSize,
+) -> Result<()> {
+    let comm_d_pieces = pieces::compute_comm_d(sector_size, piece_infos)?;
+    if let Some(i) = commitment_diff(comm_d, &comm_d_pieces) {
+        bail!(
+            "pieces and comm_d do not match, first difference at byte {}: {:?} != {:?}",
+            i,
+            comm_d,
+            comm_d_pieces
+        );
+    }
+
+    Ok(())
+}
+
 /// Generates a proof for the pre committed sector.
 ///
 /// # Arguments
@@ -190,15 +212,16 @@ pub fn seal_commit<T: AsRef<Path>>(
     piece_infos: &[PieceInfo],
 ) -> Result<SealCommitOutput> {
     info!("seal_commit:start");
+    storage_proofs::enter_span!(
+        "seal_commit",
+        sector_size = u64::from(PaddedBytesAmount::from(porep_config))
+    );
 
     let SealPreCommitOutput { comm_d, comm_r } = pre_commit;
 
     ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
     ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
-    ensure!(
-        verify_pieces(&comm_d, piece_infos, porep_config.into())?,
-        "pieces and comm_d do not match"
-    );
+    ensure_pieces_match(&comm_d, piece_infos, porep_config.into())?;
 
     let p_aux = {
         let mut p_aux_bytes = vec![];
@@ -316,6 +339,48 @@ pub fn compute_comm_d(porep_config: PoRepConfig, piece_infos: &[PieceInfo]) -> R
     pieces::compute_comm_d(porep_config.sector_size, piece_infos)
 }
 
+/// Computes a sector's `comm_d` from its staged sector file, as written by `add_piece`.
+///
+/// A staged file shorter than the sector is zero-padded, the same way `seal_pre_commit` does.
+///
+/// # Arguments
+///
+/// * `staged_path` - the path of the staged sector file.
+/// * `sector_size` - the number of bytes in the sector.
+pub fn compute_comm_d_from_staged<T: AsRef<Path>>(
+    staged_path: T,
+    sector_size: SectorSize,
+) -> Result<Commitment> {
+    let sector_bytes = usize::from(PaddedBytesAmount::from(sector_size));
+    let nodes = sector_bytes / NODE_SIZE;
+
+    let mut f_data = File::open(&staged_path)
+        .with_context(|| format!("could not open staged_path={:?}", staged_path.as_ref()))?;
+    let staged_bytes = f_data.metadata()?.len() as usize;
+    ensure!(
+        staged_bytes <= sector_bytes,
+        "staged sector of {} bytes exceeds the sector size of {} bytes",
+        staged_bytes,
+        sector_bytes
+    );
+
+    let data_tree = if staged_bytes == sector_bytes {
+        let data = unsafe {
+            MmapOptions::new()
+                .map(&f_data)
+                .with_context(|| format!("could not mmap staged_path={:?}", staged_path.as_ref()))?
+        };
+        create_merkle_tree::<DefaultPieceHasher>(None, nodes, &data)?
+    } else {
+        let mut data = Vec::with_capacity(sector_bytes);
+        f_data.read_to_end(&mut data)?;
+        data.resize(sector_bytes, 0);
+        create_merkle_tree::<DefaultPieceHasher>(None, nodes, &data)?
+    };
+
+    Ok(data_tree.root().to_commitment())
+}
+
 /// Verifies the output of some previously-run seal operation.
 ///
 /// # Arguments
@@ -395,3 +460,39 @@ pub fn verify_seal(
     )
     .map_err(Into::into)
 }
+
+/// Like `verify_seal`, but first checks that `comm_d_in` is the data commitment of the given
+/// pieces, rejecting the seal if it is not.
+///
+/// # Arguments
+///
+/// * `piece_infos` - the piece info (commitment and byte length) for each piece in this sector.
+///
+/// See `verify_seal` for the remaining arguments.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_seal_with_pieces(
+    porep_config: PoRepConfig,
+    comm_r_in: Commitment,
+    comm_d_in: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    proof_vec: &[u8],
+    piece_infos: &[PieceInfo],
+) -> Result<bool> {
+    if !verify_pieces(&comm_d_in, piece_infos, porep_config.into())? {
+        return Ok(false);
+    }
+
+    verify_seal(
+        porep_config,
+        comm_r_in,
+        comm_d_in,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        proof_vec,
+    )
+}
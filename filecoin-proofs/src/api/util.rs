@@ -1,7 +1,6 @@
 use anyhow::{Context, Result};
 use paired::bls12_381::Bls12;
-use paired::Engine;
-use storage_proofs::fr32::{bytes_into_fr, fr_into_bytes};
+use storage_proofs::fr32::bytes_into_fr;
 use storage_proofs::hasher::Domain;
 
 use crate::types::Commitment;
@@ -15,10 +14,78 @@ pub(crate) fn as_safe_commitment<H: Domain, T: AsRef<str>>(
         .with_context(|| format!("Invalid commitment ({})", commitment_name.as_ref(),))
 }
 
-pub(crate) fn commitment_from_fr<E: Engine>(fr: E::Fr) -> Commitment {
-    let mut commitment = [0; 32];
-    for (i, b) in fr_into_bytes::<E>(&fr).iter().enumerate() {
-        commitment[i] = *b;
+/// Returns the index of the first byte in which `a` and `b` differ, or `None` if they are equal.
+pub fn commitment_diff(a: &Commitment, b: &Commitment) -> Option<usize> {
+    a.iter().zip(b.iter()).position(|(x, y)| x != y)
+}
+
+/// Conversion of domain elements into commitments, the inverse of `as_safe_commitment`.
+pub(crate) trait ToCommitment {
+    fn to_commitment(&self) -> Commitment;
+}
+
+/// Writes the bytes of the domain element directly, without going through `Fr`.
+impl<H: Domain> ToCommitment for H {
+    fn to_commitment(&self) -> Commitment {
+        let mut commitment = [0; 32];
+        self.write_bytes(&mut commitment)
+            .expect("a domain element always fits into a commitment");
+        commitment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ff::Field;
+    use paired::bls12_381::Fr;
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use storage_proofs::fr32::fr_into_bytes;
+    use storage_proofs::hasher::{Blake2sHasher, Hasher, PedersenHasher, Sha256Hasher};
+
+    fn to_commitment_matches_fr<H: Hasher>() {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        for _ in 0..10 {
+            let fr = Fr::random(rng);
+            let domain: H::Domain = fr.into();
+
+            assert_eq!(
+                &domain.to_commitment()[..],
+                &fr_into_bytes::<Bls12>(&fr)[..]
+            );
+            assert_eq!(
+                as_safe_commitment::<H::Domain, _>(&domain.to_commitment(), "comm").unwrap(),
+                domain
+            );
+        }
+
+        // A small value only sets the first byte, the remaining ones are zeroed.
+        let commitment = H::Domain::from(Fr::one()).to_commitment();
+        assert_eq!(commitment[0], 1);
+        assert!(commitment[1..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn test_to_commitment() {
+        to_commitment_matches_fr::<PedersenHasher>();
+        to_commitment_matches_fr::<Sha256Hasher>();
+        to_commitment_matches_fr::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn test_commitment_diff() {
+        let a: Commitment = [1; 32];
+        assert_eq!(commitment_diff(&a, &a), None);
+
+        let mut b = a;
+        b[5] = 0;
+        assert_eq!(commitment_diff(&a, &b), Some(5));
+
+        // Only the first difference is reported.
+        b[31] = 0;
+        assert_eq!(commitment_diff(&a, &b), Some(5));
     }
-    commitment
 }
@@ -17,14 +17,6 @@ use storage_proofs::hasher::pedersen::PedersenHasher;
 use storage_proofs::parameter_cache::CacheableParameters;
 use storage_proofs::stacked::StackedDrg;
 
-const PUBLISHED_SECTOR_SIZES: [u64; 5] = [
-    SECTOR_SIZE_ONE_KIB,
-    SECTOR_SIZE_16_MIB,
-    SECTOR_SIZE_256_MIB,
-    SECTOR_SIZE_1_GIB,
-    SECTOR_SIZE_32_GIB,
-];
-
 const SEED: [u8; 16] = [
     0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc, 0xe5,
 ];
@@ -189,7 +181,10 @@ pub fn main() {
             .into_iter()
             .collect()
     } else {
-        PUBLISHED_SECTOR_SIZES.iter().cloned().collect()
+        supported_configs()
+            .iter()
+            .map(|c| u64::from(c.sector_size))
+            .collect()
     };
 
     let is_predictable = matches.is_present("predictable");
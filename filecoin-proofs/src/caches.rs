@@ -22,7 +22,9 @@ use crate::types::*;
 type Bls12GrothParams = groth16::Parameters<Bls12>;
 pub type Bls12VerifyingKey = groth16::VerifyingKey<Bls12>;
 
-type Cache<G> = HashMap<String, Arc<G>>;
+/// Every key maps to its own slot, which is locked while the entry is generated, so concurrent
+/// lookups of the same missing key wait for the first one instead of generating it again.
+type Cache<G> = HashMap<String, Arc<Mutex<Option<Arc<G>>>>>;
 type GrothMemCache = Cache<Bls12GrothParams>;
 type VerifyingKeyMemCache = Cache<Bls12VerifyingKey>;
 
@@ -41,25 +43,26 @@ where
     G: Send + Sync,
 {
     info!("trying parameters memory cache for: {}", &identifier);
-    {
-        let cache = (*cache_ref).lock().unwrap();
+    let slot = {
+        let mut cache = (*cache_ref).lock().unwrap();
+        cache.entry(identifier.clone()).or_default().clone()
+    };
 
-        if let Some(entry) = cache.get(&identifier) {
-            info!("found params in memory cache for {}", &identifier);
-            return Ok(entry.clone());
-        }
+    // Only the slot of this key stays locked while generating, other keys are not blocked. A
+    // generator that panicked poisons the slot, but leaves it empty, so it is safe to reuse.
+    let mut entry = slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(ref params) = *entry {
+        info!("found params in memory cache for {}", &identifier);
+        return Ok(params.clone());
     }
 
     info!("no params in memory cache for {}", &identifier);
 
+    // If the generator fails, the slot stays empty and the next lookup tries again.
     let new_entry = Arc::new(generator()?);
-    let res = new_entry.clone();
-    {
-        let cache = &mut (*cache_ref).lock().unwrap();
-        cache.insert(identifier, new_entry);
-    }
+    *entry = Some(new_entry.clone());
 
-    Ok(res)
+    Ok(new_entry)
 }
 
 #[inline]
@@ -95,10 +98,7 @@ pub fn get_stacked_params(porep_config: PoRepConfig) -> Result<Arc<groth16::Para
     };
 
     Ok(lookup_groth_params(
-        format!(
-            "STACKED[{}]",
-            usize::from(PaddedBytesAmount::from(porep_config))
-        ),
+        porep_config.cache_key(),
         parameters_generator,
     )?)
 }
@@ -116,10 +116,7 @@ pub fn get_post_params(post_config: PoStConfig) -> Result<Arc<groth16::Parameter
     };
 
     Ok(lookup_groth_params(
-        format!(
-            "POST[{}]",
-            usize::from(PaddedBytesAmount::from(post_config))
-        ),
+        post_config.cache_key(),
         parameters_generator,
     )?)
 }
@@ -140,10 +137,7 @@ pub fn get_stacked_verifying_key(porep_config: PoRepConfig) -> Result<Arc<Bls12V
     };
 
     Ok(lookup_verifying_key(
-        format!(
-            "STACKED[{}]",
-            usize::from(PaddedBytesAmount::from(porep_config))
-        ),
+        porep_config.cache_key(),
         vk_generator,
     )?)
 }
@@ -160,11 +154,60 @@ pub fn get_post_verifying_key(post_config: PoStConfig) -> Result<Arc<Bls12Verify
         .map_err(Into::into)
     };
 
-    Ok(lookup_verifying_key(
-        format!(
-            "POST[{}]",
-            usize::from(PaddedBytesAmount::from(post_config))
-        ),
-        vk_generator,
-    )?)
+    Ok(lookup_verifying_key(post_config.cache_key(), vk_generator)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_concurrent_lookups_generate_once() {
+        let cache: Arc<Mutex<Cache<usize>>> = Default::default();
+        let generated = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let cache = cache.clone();
+                let generated = generated.clone();
+
+                thread::spawn(move || {
+                    cache_lookup(&cache, "params".to_string(), || {
+                        thread::sleep(Duration::from_millis(50));
+                        Ok(generated.fetch_add(1, Ordering::SeqCst) + 42)
+                    })
+                    .unwrap()
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(*handle.join().unwrap(), 42);
+        }
+        assert_eq!(generated.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_failed_generation_is_retried() {
+        let cache: Mutex<Cache<usize>> = Default::default();
+
+        assert!(
+            cache_lookup(&cache, "params".to_string(), || Err(anyhow::anyhow!(
+                "boom"
+            )))
+            .is_err()
+        );
+        assert_eq!(
+            *cache_lookup(&cache, "params".to_string(), || Ok(1)).unwrap(),
+            1
+        );
+        assert_eq!(
+            *cache_lookup(&cache, "params".to_string(), || Ok(2)).unwrap(),
+            1
+        );
+    }
 }
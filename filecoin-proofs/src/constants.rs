@@ -1,10 +1,12 @@
 use std::collections::HashMap;
-use std::sync::{atomic::AtomicU64, atomic::AtomicU8, RwLock};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::RwLock;
 
+use anyhow::{anyhow, Result};
 use lazy_static::lazy_static;
 use storage_proofs::util::NODE_SIZE;
 
-use crate::types::UnpaddedBytesAmount;
+use crate::types::{PoRepProofPartitions, PoStProofPartitions, SectorSize, UnpaddedBytesAmount};
 
 pub const SECTOR_SIZE_ONE_KIB: u64 = 1024;
 pub const SECTOR_SIZE_16_MIB: u64 = 1 << 24;
@@ -12,6 +14,77 @@ pub const SECTOR_SIZE_256_MIB: u64 = 1 << 28;
 pub const SECTOR_SIZE_1_GIB: u64 = 1 << 30;
 pub const SECTOR_SIZE_32_GIB: u64 = 1 << 35;
 
+/// The canonical parameters for a supported sector size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SupportedConfig {
+    pub sector_size: SectorSize,
+    /// The default window size in bytes, `DEFAULT_WINDOWS` is initialized from it.
+    pub window_size: u64,
+}
+
+impl SupportedConfig {
+    /// The number of nodes in a sector, `sector_size / NODE_SIZE`.
+    pub fn nodes(&self) -> usize {
+        u64::from(self.sector_size) as usize / NODE_SIZE
+    }
+
+    /// The base degree of the window DRG, currently `WINDOW_DRG_DEGREE` for all sizes.
+    pub fn degree(&self) -> usize {
+        WINDOW_DRG_DEGREE.load(Ordering::Relaxed) as usize
+    }
+
+    /// The degree of the window expander graph, currently `WINDOW_EXP_DEGREE` for all sizes.
+    pub fn expansion_degree(&self) -> usize {
+        WINDOW_EXP_DEGREE.load(Ordering::Relaxed) as usize
+    }
+
+    /// The number of PoRep partitions, currently `DEFAULT_POREP_PROOF_PARTITIONS` for all sizes.
+    pub fn porep_partitions(&self) -> PoRepProofPartitions {
+        PoRepProofPartitions(DEFAULT_POREP_PROOF_PARTITIONS.load(Ordering::Relaxed))
+    }
+
+    /// The number of PoSt partitions, currently `DEFAULT_POST_PROOF_PARTITIONS` for all sizes.
+    pub fn post_partitions(&self) -> PoStProofPartitions {
+        PoStProofPartitions(DEFAULT_POST_PROOF_PARTITIONS.load(Ordering::Relaxed))
+    }
+}
+
+const SUPPORTED_CONFIGS: &[SupportedConfig] = &[
+    SupportedConfig {
+        sector_size: SectorSize(SECTOR_SIZE_ONE_KIB),
+        window_size: 512,
+    },
+    SupportedConfig {
+        sector_size: SectorSize(SECTOR_SIZE_16_MIB),
+        window_size: 4 * 1024 * 1024,
+    },
+    SupportedConfig {
+        sector_size: SectorSize(SECTOR_SIZE_256_MIB),
+        window_size: 64 * 1024 * 1024,
+    },
+    SupportedConfig {
+        sector_size: SectorSize(SECTOR_SIZE_1_GIB),
+        window_size: 128 * 1024 * 1024,
+    },
+    SupportedConfig {
+        sector_size: SectorSize(SECTOR_SIZE_32_GIB),
+        window_size: 128 * 1024 * 1024,
+    },
+];
+
+/// All supported configurations, one per sector size, ordered by sector size.
+pub fn supported_configs() -> &'static [SupportedConfig] {
+    SUPPORTED_CONFIGS
+}
+
+/// Returns the canonical parameters for `sector_size`, or an error if it is not supported.
+pub fn supported_config(sector_size: SectorSize) -> Result<&'static SupportedConfig> {
+    SUPPORTED_CONFIGS
+        .iter()
+        .find(|c| c.sector_size == sector_size)
+        .ok_or_else(|| anyhow!("unsupported sector size: {}", u64::from(sector_size)))
+}
+
 pub const POST_CHALLENGE_COUNT: usize = 40;
 pub const POST_CHALLENGED_NODES: usize = 1;
 
@@ -26,47 +99,22 @@ lazy_static! {
     pub static ref WINDOW_EXP_DEGREE: AtomicU64 = AtomicU64::new(storage_proofs::stacked::EXP_DEGREE as u64);
     pub static ref WRAPPER_EXP_DEGREE: AtomicU64 = AtomicU64::new(storage_proofs::stacked::EXP_DEGREE as u64);
     pub static ref DEFAULT_POREP_PROOF_PARTITIONS: AtomicU8 = AtomicU8::new(10);
-
-    pub static ref DEFAULT_WINDOWS: RwLock<HashMap<u64, SectorInfo>> = RwLock::new({
-        let mut m = HashMap::new();
-        m.insert(
-            SECTOR_SIZE_ONE_KIB,
-            SectorInfo {
-                size: SECTOR_SIZE_ONE_KIB,
-                window_size: 512,
-            },
-        );
-        m.insert(
-            SECTOR_SIZE_16_MIB,
-            SectorInfo {
-                size: SECTOR_SIZE_16_MIB,
-                window_size: 4 * 1024 * 1024,
-            },
-        );
-        m.insert(
-            SECTOR_SIZE_256_MIB,
-            SectorInfo {
-                size: SECTOR_SIZE_256_MIB,
-                window_size: 64 * 1024 * 1024,
-            },
-        );
-        m.insert(
-            SECTOR_SIZE_1_GIB,
-            SectorInfo {
-                size: SECTOR_SIZE_1_GIB,
-                window_size: 128 * 1024 * 1024,
-            },
-        );
-        m.insert(
-            SECTOR_SIZE_32_GIB,
-            SectorInfo {
-                size: SECTOR_SIZE_32_GIB,
-                window_size: 128 * 1024 * 1024,
-            },
-        );
-
-        m
-    });
+    pub static ref DEFAULT_POST_PROOF_PARTITIONS: AtomicU8 = AtomicU8::new(1);
+
+    pub static ref DEFAULT_WINDOWS: RwLock<HashMap<u64, SectorInfo>> = RwLock::new(
+        SUPPORTED_CONFIGS
+            .iter()
+            .map(|c| {
+                let size = u64::from(c.sector_size);
+                let info = SectorInfo {
+                    size,
+                    window_size: c.window_size,
+                };
+
+                (size, info)
+            })
+            .collect()
+    );
 }
 
 pub const SINGLE_PARTITION_PROOF_LEN: usize = 192;
@@ -97,3 +145,40 @@ pub const MIN_PIECE_SIZE: UnpaddedBytesAmount = UnpaddedBytesAmount(127);
 pub type DefaultPieceHasher = storage_proofs::hasher::Sha256Hasher;
 
 pub use storage_proofs::drgraph::DefaultTreeHasher;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::types::PaddedBytesAmount;
+
+    #[test]
+    fn test_supported_configs_consistent() {
+        for config in supported_configs() {
+            let sector_bytes = PaddedBytesAmount::from(config.sector_size);
+            let partitions = usize::from(config.porep_partitions());
+            assert!(partitions > 0);
+            assert!(usize::from(config.post_partitions()) > 0);
+
+            // Every supported size must be accepted by the PoRep setup, with the config's values.
+            let params = crate::parameters::setup_params(sector_bytes, partitions)
+                .unwrap_or_else(|err| panic!("bad config {:?}: {}", config, err));
+            assert_eq!(params.nodes, config.nodes());
+            assert_eq!(params.window_drg_degree, config.degree());
+            assert_eq!(params.window_expansion_degree, config.expansion_degree());
+
+            // A sector is made of whole windows, each a power of two nodes.
+            let window_nodes = params.window_size_nodes;
+            assert!(window_nodes.is_power_of_two(), "bad window: {:?}", config);
+            assert_eq!(config.nodes() % window_nodes, 0, "bad window: {:?}", config);
+
+            assert_eq!(supported_config(config.sector_size).unwrap(), config);
+        }
+
+        for pair in supported_configs().windows(2) {
+            assert!(u64::from(pair[0].sector_size) < u64::from(pair[1].sector_size));
+        }
+
+        assert!(supported_config(SectorSize(SECTOR_SIZE_ONE_KIB + 1)).is_err());
+    }
+}
@@ -655,6 +655,8 @@ pub fn clear_right_bits(byte: &mut u8, offset: usize) {
 // benchmarks.
 const N: usize = 1000;
 const CHUNK_SIZE: usize = 127 * N;
+/// The padded size of `CHUNK_SIZE` bytes.
+const PADDED_CHUNK_SIZE: usize = 128 * N;
 
 pub fn write_padded<R, W>(mut source: R, mut target: W) -> io::Result<usize>
 where
@@ -1006,6 +1008,121 @@ where
     Ok(raw_data.len())
 }
 
+/// Reads from `source` until `buf` is full or `source` is exhausted, returning the number of bytes
+/// read. The readers below rely on full chunks to stay aligned to element boundaries.
+fn read_chunk<R: Read>(source: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match source.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(filled)
+}
+
+/// Copies as much of `buf[*pos..]` as fits into `out`, advancing `pos`.
+fn read_from_buffer(buf: &[u8], pos: &mut usize, out: &mut [u8]) -> usize {
+    let n = min(out.len(), buf.len() - *pos);
+    out[..n].copy_from_slice(&buf[*pos..*pos + n]);
+    *pos += n;
+
+    n
+}
+
+/// A reader which fr32 pads the bytes read from `source` on the fly, producing the same bytes as
+/// `write_padded`. This allows padding to be chained into other IO pipelines.
+///
+/// `source` is read in chunks of `CHUNK_SIZE` bytes, which are a whole number of elements.
+#[derive(Debug)]
+pub struct PaddingReader<R> {
+    source: R,
+    chunk: Vec<u8>,
+    padded: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> PaddingReader<R> {
+    pub fn new(source: R) -> Self {
+        PaddingReader {
+            source,
+            chunk: vec![0; CHUNK_SIZE],
+            padded: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+impl<R: Read> Read for PaddingReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.padded.len() {
+            let n = read_chunk(&mut self.source, &mut self.chunk)?;
+            if n == 0 {
+                return Ok(0);
+            }
+
+            let mut padded = io::Cursor::new(Vec::with_capacity(padded_bytes(n)));
+            write_padded_aux(&FR32_PADDING_MAP, &self.chunk[..n], &mut padded)?;
+            self.padded = padded.into_inner();
+            self.pos = 0;
+        }
+
+        Ok(read_from_buffer(&self.padded, &mut self.pos, out))
+    }
+}
+
+/// A reader which removes the fr32 padding from the bytes read from `source` on the fly, the
+/// inverse of `PaddingReader`.
+///
+/// `source` is read in chunks of `PADDED_CHUNK_SIZE` bytes, which are a whole number of elements.
+#[derive(Debug)]
+pub struct UnpaddingReader<R> {
+    source: R,
+    chunk: Vec<u8>,
+    unpadded: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> UnpaddingReader<R> {
+    pub fn new(source: R) -> Self {
+        UnpaddingReader {
+            source,
+            chunk: vec![0; PADDED_CHUNK_SIZE],
+            unpadded: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+impl<R: Read> Read for UnpaddingReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.unpadded.len() {
+            let n = read_chunk(&mut self.source, &mut self.chunk)?;
+            if n == 0 {
+                return Ok(0);
+            }
+
+            let len = unpadded_bytes(n as u64) as usize;
+            self.unpadded.clear();
+            write_unpadded(&self.chunk[..n], &mut self.unpadded, 0, len)?;
+            self.pos = 0;
+        }
+
+        Ok(read_from_buffer(&self.unpadded, &mut self.pos, out))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1366,6 +1483,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_padding_readers_roundtrip() {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        for len in &[0, 1, 31, 32, 127, 128, 1016, CHUNK_SIZE - 1, CHUNK_SIZE + 5] {
+            let data: Vec<u8> = (0..*len).map(|_| rng.gen()).collect();
+
+            let mut cursor = Cursor::new(Vec::new());
+            write_padded(&data[..], &mut cursor).unwrap();
+            let expected = cursor.into_inner();
+
+            let mut padded = Vec::new();
+            PaddingReader::new(&data[..])
+                .read_to_end(&mut padded)
+                .unwrap();
+            assert_eq!(padded, expected, "padding differs for {} bytes", len);
+
+            let mut unpadded = Vec::new();
+            UnpaddingReader::new(PaddingReader::new(&data[..]))
+                .read_to_end(&mut unpadded)
+                .unwrap();
+            assert_eq!(unpadded, data, "roundtrip differs for {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn test_padding_readers_small_reads() {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let data: Vec<u8> = (0..1000).map(|_| rng.gen()).collect();
+
+        let mut reader = UnpaddingReader::new(PaddingReader::new(&data[..]));
+        let mut unpadded = Vec::new();
+        let mut buf = [0u8; 7];
+        loop {
+            let n = reader.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            unpadded.extend_from_slice(&buf[..n]);
+        }
+
+        assert_eq!(unpadded, data);
+    }
+
     // TODO: Add a test that drops the last part of an element and tries to recover
     // the rest of the data (may already be present in some form in the above tests).
 }
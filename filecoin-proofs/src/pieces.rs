@@ -4,6 +4,7 @@ use std::iter::Iterator;
 
 use anyhow::{ensure, Context, Result};
 use log::info;
+use storage_proofs::error::Error;
 use storage_proofs::hasher::{HashFunction, Hasher};
 use storage_proofs::util::NODE_SIZE;
 
@@ -210,6 +211,21 @@ pub fn sum_piece_bytes_with_alignment(pieces: &[UnpaddedBytesAmount]) -> Unpadde
         })
 }
 
+/// Checks that the pieces, including their alignment padding, fit into a sector of `sector_size`.
+pub fn check_pieces_fit(sector_size: SectorSize, piece_infos: &[PieceInfo]) -> Result<()> {
+    let piece_sizes: Vec<UnpaddedBytesAmount> = piece_infos.iter().map(|info| info.size).collect();
+    let total = u64::from(PaddedBytesAmount::from(sum_piece_bytes_with_alignment(
+        &piece_sizes,
+    )));
+    let capacity = u64::from(sector_size);
+
+    if total > capacity {
+        return Err(Error::PiecesExceedSectorSize { total, capacity }.into());
+    }
+
+    Ok(())
+}
+
 /// Given a list of pieces, find the byte where a given piece does or would start.
 pub fn get_piece_start_byte(
     pieces: &[UnpaddedBytesAmount],
@@ -295,12 +311,11 @@ pub fn get_aligned_source<T: Read>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::api::util::commitment_from_fr;
+    use crate::api::util::ToCommitment;
     use crate::constants::{WINDOW_DRG_DEGREE, WINDOW_EXP_DEGREE};
 
     use std::sync::atomic::Ordering;
 
-    use paired::bls12_381::{Bls12, Fr};
     use rand::{Rng, RngCore, SeedableRng};
     use rand_xorshift::XorShiftRng;
     use storage_proofs::drgraph::{new_seed, Graph};
@@ -363,6 +378,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_check_pieces_fit() {
+        let sector_size = SectorSize(1024);
+        let pieces = |count: usize| -> Vec<PieceInfo> {
+            (0..count)
+                .map(|_| PieceInfo {
+                    commitment: [0; 32],
+                    size: UnpaddedBytesAmount(127),
+                })
+                .collect()
+        };
+
+        // exactly full
+        check_pieces_fit(sector_size, &pieces(8)).unwrap();
+        check_pieces_fit(
+            sector_size,
+            &[PieceInfo {
+                commitment: [0; 32],
+                size: UnpaddedBytesAmount(1016),
+            }],
+        )
+        .unwrap();
+
+        // under-full
+        check_pieces_fit(sector_size, &pieces(3)).unwrap();
+
+        // over-full
+        let err = check_pieces_fit(sector_size, &pieces(9)).unwrap_err();
+        match err.downcast_ref::<Error>() {
+            Some(Error::PiecesExceedSectorSize { total, capacity }) => {
+                assert_eq!(*total, 9 * 128);
+                assert_eq!(*capacity, 1024);
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+
+        // the alignment padding counts, too: a 127 byte piece followed by a 508 byte one
+        // takes 1024 bytes, so a third piece does not fit any more.
+        let mut aligned = pieces(1);
+        aligned.push(PieceInfo {
+            commitment: [0; 32],
+            size: UnpaddedBytesAmount(508),
+        });
+        check_pieces_fit(sector_size, &aligned).unwrap();
+        aligned.push(pieces(1).remove(0));
+        assert!(check_pieces_fit(sector_size, &aligned).is_err());
+    }
+
     #[test]
     fn test_verify_simple_pieces() {
         let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
@@ -653,8 +716,7 @@ mod tests {
         assert_eq!(staged_sector.len(), u64::from(sector_size) as usize);
 
         let data_tree = graph.merkle_tree(&staged_sector)?;
-        let comm_d_root: Fr = data_tree.root().into();
-        let comm_d = commitment_from_fr::<Bls12>(comm_d_root);
+        let comm_d = data_tree.root().to_commitment();
 
         Ok((comm_d, piece_infos))
     }
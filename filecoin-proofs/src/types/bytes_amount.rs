@@ -1,6 +1,8 @@
 use std::ops::{Add, Sub};
 
+use anyhow::{ensure, Result};
 use serde::{Deserialize, Serialize};
+use storage_proofs::util::NODE_SIZE;
 
 use crate::fr32::padded_bytes;
 use crate::fr32::unpadded_bytes;
@@ -110,6 +112,21 @@ impl Sub for PaddedBytesAmount {
     }
 }
 
+/// Returns the number of nodes `amount` occupies once padded, failing if the padded amount is not a
+/// whole number of nodes.
+pub fn node_count(amount: UnpaddedBytesAmount) -> Result<usize> {
+    let padded = usize::from(PaddedBytesAmount::from(amount));
+    ensure!(
+        padded % NODE_SIZE == 0,
+        "{:?} pads to {} bytes, which is not a multiple of the node size {}",
+        amount,
+        padded,
+        NODE_SIZE
+    );
+
+    Ok(padded / NODE_SIZE)
+}
+
 impl From<PoStProofBytesAmount> for usize {
     fn from(x: PoStProofBytesAmount) -> Self {
         x.0
@@ -163,4 +180,18 @@ mod tests {
         // assert_eq!(1u64 + u64::from(e), 3u64);
         // assert_eq!(1usize + usize::from(e), 3usize);
     }
+
+    #[test]
+    fn test_node_count() {
+        assert_eq!(node_count(UnpaddedBytesAmount(0)).unwrap(), 0);
+        assert_eq!(node_count(UnpaddedBytesAmount(127)).unwrap(), 4);
+        assert_eq!(node_count(UnpaddedBytesAmount(254)).unwrap(), 8);
+        assert_eq!(
+            node_count(UnpaddedBytesAmount::from(PaddedBytesAmount(1 << 20))).unwrap(),
+            (1 << 20) / NODE_SIZE
+        );
+
+        assert!(node_count(UnpaddedBytesAmount(100)).is_err());
+        assert!(node_count(UnpaddedBytesAmount(128)).is_err());
+    }
 }
@@ -0,0 +1,73 @@
+use anyhow::{ensure, Context, Result};
+
+use crate::types::Commitment;
+
+/// Encodes `comm` as URL safe base64 without padding, the same encoding as `Domain::to_base64`.
+pub fn commitment_to_base64(comm: &Commitment) -> String {
+    base64::encode_config(comm, base64::URL_SAFE_NO_PAD)
+}
+
+/// Inverse of `commitment_to_base64`, rejecting malformed input and payloads that are not exactly
+/// 32 bytes long.
+pub fn commitment_from_base64(s: &str) -> Result<Commitment> {
+    let bytes = base64::decode_config(s, base64::URL_SAFE_NO_PAD)
+        .with_context(|| format!("invalid base64 encoded commitment {:?}", s))?;
+
+    let mut comm = [0; 32];
+    ensure!(
+        bytes.len() == comm.len(),
+        "base64 encoded commitment is {} bytes, expected {}",
+        bytes.len(),
+        comm.len()
+    );
+    comm.copy_from_slice(&bytes);
+
+    Ok(comm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+    use storage_proofs::hasher::pedersen::PedersenDomain;
+    use storage_proofs::hasher::Domain;
+
+    use crate::api::util::ToCommitment;
+
+    #[test]
+    fn test_commitment_base64_roundtrip() {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        for _ in 0..10 {
+            let comm: Commitment = rng.gen();
+            assert_eq!(
+                commitment_from_base64(&commitment_to_base64(&comm)).unwrap(),
+                comm
+            );
+        }
+
+        // Matches the encoding of the domain element it was created from.
+        let domain = PedersenDomain::random(rng);
+        assert_eq!(
+            commitment_to_base64(&domain.to_commitment()),
+            domain.to_base64()
+        );
+    }
+
+    #[test]
+    fn test_commitment_from_malformed_base64() {
+        assert!(commitment_from_base64("not base64!").is_err());
+        assert!(commitment_from_base64(&base64::encode_config(
+            &[1u8; 31],
+            base64::URL_SAFE_NO_PAD
+        ))
+        .is_err());
+        assert!(commitment_from_base64(&base64::encode_config(
+            &[1u8; 33],
+            base64::URL_SAFE_NO_PAD
+        ))
+        .is_err());
+    }
+}
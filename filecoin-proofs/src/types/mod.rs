@@ -4,6 +4,7 @@ use storage_proofs::merkle::MerkleTree;
 use storage_proofs::stacked;
 
 mod bytes_amount;
+mod commitment;
 mod piece_info;
 mod porep_config;
 mod porep_proof_partitions;
@@ -13,6 +14,7 @@ mod sector_class;
 mod sector_size;
 
 pub use self::bytes_amount::*;
+pub use self::commitment::*;
 pub use self::piece_info::*;
 pub use self::porep_config::*;
 pub use self::porep_proof_partitions::*;
@@ -10,7 +10,7 @@ use storage_proofs::parameter_cache::{self, CacheableParameters};
 use crate::constants::DefaultPieceHasher;
 use crate::types::*;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct PoRepConfig {
     pub sector_size: SectorSize,
     pub partitions: PoRepProofPartitions,
@@ -48,6 +48,11 @@ impl From<PoRepConfig> for SectorSize {
 }
 
 impl PoRepConfig {
+    /// Returns the canonical name of the circuit for this config, used to key in-memory caches.
+    pub fn cache_key(&self) -> String {
+        format!("STACKED[{}, {}]", self.sector_size.0, self.partitions.0)
+    }
+
     /// Returns the cache identifier as used by `storage-proofs::paramater_cache`.
     pub fn get_cache_identifier(&self) -> Result<String> {
         let params =
@@ -75,3 +80,29 @@ impl PoRepConfig {
         Ok(parameter_cache::parameter_cache_params_path(&id))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key() {
+        let a = PoRepConfig {
+            sector_size: SectorSize(1024),
+            partitions: PoRepProofPartitions(2),
+        };
+        let b = PoRepConfig {
+            sector_size: SectorSize(1024),
+            partitions: PoRepProofPartitions(2),
+        };
+        let c = PoRepConfig {
+            sector_size: SectorSize(1024),
+            partitions: PoRepProofPartitions(1),
+        };
+
+        assert_eq!(a, b);
+        assert_eq!(a.cache_key(), b.cache_key());
+        assert_ne!(a, c);
+        assert_ne!(a.cache_key(), c.cache_key());
+    }
+}
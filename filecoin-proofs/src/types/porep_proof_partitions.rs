@@ -1,4 +1,4 @@
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct PoRepProofPartitions(pub u8);
 
 impl From<PoRepProofPartitions> for usize {
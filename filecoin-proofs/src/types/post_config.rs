@@ -9,7 +9,7 @@ use storage_proofs::parameter_cache::{self, CacheableParameters};
 
 use crate::types::*;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct PoStConfig {
     pub sector_size: SectorSize,
     pub challenge_count: usize,
@@ -33,6 +33,14 @@ impl From<PoStConfig> for UnpaddedBytesAmount {
 }
 
 impl PoStConfig {
+    /// Returns the canonical name of the circuit for this config, used to key in-memory caches.
+    pub fn cache_key(&self) -> String {
+        format!(
+            "POST[{}, {}, {}]",
+            self.sector_size.0, self.challenge_count, self.challenged_nodes
+        )
+    }
+
     /// Returns the cache identifier as used by `storage-proofs::paramater_cache`.
     pub fn get_cache_identifier(self) -> Result<String> {
         let params = crate::parameters::post_public_params(self)?;
@@ -61,3 +69,27 @@ impl PoStConfig {
         Ok(parameter_cache::parameter_cache_params_path(&id))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key() {
+        let a = PoStConfig {
+            sector_size: SectorSize(1024),
+            challenge_count: 2,
+            challenged_nodes: 1,
+        };
+        let b = a;
+        let c = PoStConfig {
+            challenge_count: 3,
+            ..a
+        };
+
+        assert_eq!(a, b);
+        assert_eq!(a.cache_key(), b.cache_key());
+        assert_ne!(a, c);
+        assert_ne!(a.cache_key(), c.cache_key());
+    }
+}
@@ -1,7 +1,7 @@
 use crate::constants::SINGLE_PARTITION_PROOF_LEN;
 use crate::types::*;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct PoStProofPartitions(pub u8);
 
 impl From<PoStProofPartitions> for PoStProofBytesAmount {
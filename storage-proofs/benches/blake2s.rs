@@ -6,6 +6,8 @@ use fil_sapling_crypto::jubjub::JubjubEngine;
 use paired::bls12_381::Bls12;
 use rand::{thread_rng, Rng};
 use storage_proofs::circuit::bench::BenchCS;
+use storage_proofs::hasher::blake2s::{Blake2sDomain, Blake2sFunction};
+use storage_proofs::hasher::HashFunction;
 
 struct Blake2sExample<'a> {
     data: &'a [Option<bool>],
@@ -53,6 +55,54 @@ fn blake2s_benchmark(c: &mut Criterion) {
     );
 }
 
+/// Compares hashing many nodes at once against hashing them one by one. `hash_many` only takes
+/// the SIMD path with the `simd` feature, run with and without it to see the difference.
+fn blake2s_many_benchmark(c: &mut Criterion) {
+    let params = vec![8, 64, 1024];
+
+    c.bench(
+        "hash-blake2s-many",
+        ParameterizedBenchmark::new(
+            "hash",
+            |b, count| {
+                let inputs = random_nodes(*count);
+                let inputs: Vec<&[u8]> = inputs.iter().map(|input| &input[..]).collect();
+
+                b.iter(|| {
+                    black_box(
+                        inputs
+                            .iter()
+                            .map(|input| {
+                                <Blake2sFunction as HashFunction<Blake2sDomain>>::hash(input)
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                })
+            },
+            params,
+        )
+        .with_function("hash_many", |b, count| {
+            let inputs = random_nodes(*count);
+            let inputs: Vec<&[u8]> = inputs.iter().map(|input| &input[..]).collect();
+
+            b.iter(|| {
+                black_box(<Blake2sFunction as HashFunction<Blake2sDomain>>::hash_many(
+                    &inputs,
+                ))
+            })
+        }),
+    );
+}
+
+/// `count` random preimages of a tree node, each two nodes long.
+fn random_nodes(count: usize) -> Vec<Vec<u8>> {
+    let mut rng = thread_rng();
+
+    (0..count)
+        .map(|_| (0..64).map(|_| rng.gen()).collect())
+        .collect()
+}
+
 fn blake2s_circuit_benchmark(c: &mut Criterion) {
     let mut rng1 = thread_rng();
     let groth_params = generate_random_parameters::<Bls12, _, _>(
@@ -107,5 +157,10 @@ fn blake2s_circuit_benchmark(c: &mut Criterion) {
     );
 }
 
-criterion_group!(benches, blake2s_benchmark, blake2s_circuit_benchmark);
+criterion_group!(
+    benches,
+    blake2s_benchmark,
+    blake2s_many_benchmark,
+    blake2s_circuit_benchmark
+);
 criterion_main!(benches);
@@ -2,7 +2,9 @@ use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use ff::Field;
 use paired::bls12_381::{Bls12, Fr};
 use rand::thread_rng;
+use storage_proofs::crypto::sloth;
 use storage_proofs::fr32::{bytes_into_fr, fr_into_bytes};
+use storage_proofs::hasher::{Domain, Hasher, PedersenHasher};
 
 fn fr_benchmark(c: &mut Criterion) {
     c.bench_function("fr-to-bytes-32", move |b| {
@@ -21,5 +23,25 @@ fn fr_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, fr_benchmark);
+/// Compares sloth on field elements with the `Hasher` wrappers, which convert from and to domain
+/// elements on every call.
+fn sloth_benchmark(c: &mut Criterion) {
+    c.bench_function("sloth-encode-fr", move |b| {
+        let mut rng = thread_rng();
+        let key = Fr::random(&mut rng);
+        let plaintext = Fr::random(&mut rng);
+
+        b.iter(|| black_box(sloth::encode::<Bls12>(&key, &plaintext)))
+    });
+
+    c.bench_function("sloth-encode-domain", move |b| {
+        let mut rng = thread_rng();
+        let key = <PedersenHasher as Hasher>::Domain::random(&mut rng);
+        let plaintext = <PedersenHasher as Hasher>::Domain::random(&mut rng);
+
+        b.iter(|| black_box(PedersenHasher::sloth_encode(&key, &plaintext).unwrap()))
+    });
+}
+
+criterion_group!(benches, fr_benchmark, sloth_benchmark);
 criterion_main!(benches);
@@ -1,8 +1,11 @@
+use std::sync::Arc;
+
 use criterion::{black_box, criterion_group, criterion_main, Criterion, ParameterizedBenchmark};
 use rand::{thread_rng, Rng};
 use storage_proofs::drgraph::{new_seed, Graph, BASE_DEGREE};
 use storage_proofs::hasher::blake2s::Blake2sHasher;
 use storage_proofs::hasher::pedersen::PedersenHasher;
+use storage_proofs::merkle::MerkleTreeExt;
 use storage_proofs::stacked::{StackedBucketGraph, EXP_DEGREE};
 
 fn merkle_benchmark(c: &mut Criterion) {
@@ -47,5 +50,47 @@ fn merkle_benchmark(c: &mut Criterion) {
     );
 }
 
-criterion_group!(benches, merkle_benchmark);
+fn merkle_proofs_benchmark(c: &mut Criterion) {
+    let n_nodes = 1024;
+    let mut rng = thread_rng();
+    let data: Vec<u8> = (0..32 * n_nodes).map(|_| rng.gen()).collect();
+    let graph = StackedBucketGraph::<PedersenHasher>::new_stacked(
+        n_nodes,
+        BASE_DEGREE,
+        EXP_DEGREE,
+        new_seed(),
+    )
+    .unwrap();
+    let tree = Arc::new(graph.merkle_tree(&data).unwrap());
+    let batched_tree = tree.clone();
+
+    // Clustered challenges, sharing most of their paths.
+    let params = vec![8, 64];
+
+    c.bench(
+        "merkle-proofs",
+        ParameterizedBenchmark::new(
+            "individual",
+            move |b, n_challenges| {
+                let challenges: Vec<usize> = (0..*n_challenges).collect();
+                b.iter(|| {
+                    black_box(
+                        challenges
+                            .iter()
+                            .map(|c| tree.gen_proof(*c).unwrap())
+                            .collect::<Vec<_>>(),
+                    )
+                })
+            },
+            params,
+        )
+        .with_function("batched", move |b, n_challenges| {
+            let challenges: Vec<usize> = (0..*n_challenges).collect();
+            b.iter(|| black_box(batched_tree.gen_proofs(&challenges).unwrap()))
+        })
+        .sample_size(20),
+    );
+}
+
+criterion_group!(benches, merkle_benchmark, merkle_proofs_benchmark);
 criterion_main!(benches);
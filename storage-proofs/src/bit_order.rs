@@ -0,0 +1,91 @@
+//! Conversions between bytes and bits with an explicit bit order.
+//!
+//! The native hashers and the circuits must agree on how bytes are split into bits, otherwise their
+//! hashes diverge. Everything in this crate uses little endian bit order within a byte (least
+//! significant bit first), matching `multipack::bytes_to_bits_le` and `compute_multipacking`. The
+//! SHA-256 gadget is the exception, it consumes and produces big endian bits, which are converted
+//! with `swap_bit_order`.
+
+/// Converts bytes into bits, least significant bit of each byte first.
+pub fn bytes_to_bits_le(bytes: &[u8]) -> Vec<bool> {
+    bytes
+        .iter()
+        .flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1u8 == 1u8))
+        .collect()
+}
+
+/// Converts bits into bytes, the inverse of `bytes_to_bits_le`. The number of bits must be a
+/// multiple of 8.
+pub fn bits_to_bytes_le(bits: &[bool]) -> Vec<u8> {
+    assert_eq!(bits.len() % 8, 0, "bits must be a multiple of 8");
+
+    bits.chunks(8)
+        .map(|byte| {
+            byte.iter()
+                .enumerate()
+                .fold(0u8, |acc, (i, bit)| acc | ((*bit as u8) << i))
+        })
+        .collect()
+}
+
+/// Reverses the order of the bits within each byte, converting little endian bits into big endian
+/// ones and vice versa. The number of bits must be a multiple of 8.
+pub fn swap_bit_order<T: Clone>(bits: &[T]) -> Vec<T> {
+    assert_eq!(bits.len() % 8, 0, "bits must be a multiple of 8");
+
+    bits.chunks(8)
+        .flat_map(|byte| byte.iter().rev())
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bellperson::gadgets::multipack;
+    use ff::PrimeField;
+    use paired::bls12_381::{Bls12, Fr};
+    use rand::Rng;
+
+    use crate::fr32::bytes_into_fr;
+    use crate::test_helper::seeded_rng;
+
+    #[test]
+    fn test_matches_multipack() {
+        let rng = &mut seeded_rng();
+
+        for len in 0..64 {
+            let bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let bits = bytes_to_bits_le(&bytes);
+
+            assert_eq!(bits, multipack::bytes_to_bits_le(&bytes));
+            assert_eq!(bits_to_bytes_le(&bits), bytes);
+        }
+    }
+
+    #[test]
+    fn test_packs_into_same_fr() {
+        let rng = &mut seeded_rng();
+
+        for _ in 0..10 {
+            let mut bytes: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
+            // Clear the top two bits, so the value fits into `CAPACITY` bits.
+            bytes[31] &= 0b0011_1111;
+            let fr = bytes_into_fr::<Bls12>(&bytes).unwrap();
+
+            let bits = bytes_to_bits_le(&bytes);
+            let packed = multipack::compute_multipacking::<Bls12>(&bits[..Fr::CAPACITY as usize]);
+            assert_eq!(packed, vec![fr]);
+        }
+    }
+
+    #[test]
+    fn test_swap_bit_order() {
+        let bits = bytes_to_bits_le(&[0b0000_0001, 0b1000_0000]);
+        let swapped = swap_bit_order(&bits);
+
+        assert_eq!(bits_to_bytes_le(&swapped), vec![0b1000_0000, 0b0000_0001]);
+        assert_eq!(swap_bit_order(&swapped), bits);
+    }
+}
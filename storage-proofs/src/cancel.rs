@@ -0,0 +1,56 @@
+//! Cooperative cancellation of long running operations.
+//!
+//! Building merkle trees, encoding replicas and proving can take minutes. A `CancellationToken`
+//! is checked at loop boundaries of those operations, once it is cancelled they return
+//! `Error::Cancelled` instead of running to completion.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::ensure;
+
+use crate::error::{Error, Result};
+
+/// A cheaply clonable flag, shared between the caller and the operation it may cancel.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests cancellation of every operation observing this token, or any of its clones.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Returns `Error::Cancelled` once the token has been cancelled.
+    pub fn check(&self) -> Result<()> {
+        ensure!(!self.is_cancelled(), Error::Cancelled);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_is_shared_by_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(token.check().is_ok());
+
+        clone.cancel();
+        assert!(token.is_cancelled());
+        match token.check().map_err(|err| err.downcast::<Error>()) {
+            Err(Ok(Error::Cancelled)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Domain separation of challenge derivation, so that PoRep and PoSt never derive the same
+//! challenges from the same inputs.
+
+use anyhow::{ensure, Context};
+use num_bigint::BigUint;
+use num_traits::cast::ToPrimitive;
+use sha2::{Digest, Sha256};
+
+use crate::error::Result;
+use crate::hasher::Domain;
+
+/// The proof type challenges are derived for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeDomain {
+    PoRep,
+    PoSt,
+}
+
+impl ChallengeDomain {
+    /// The tag prepended to the input of every challenge derived in this domain.
+    pub fn tag(self) -> &'static [u8] {
+        match self {
+            ChallengeDomain::PoRep => b"PoRep",
+            ChallengeDomain::PoSt => b"PoSt",
+        }
+    }
+}
+
+/// Derives the challenge with the given `index` in `0..leaves`, as the little endian integer
+/// `Sha256(tag || replica_id || seed || index)` modulo `leaves`.
+pub fn derive_challenge<D: Domain>(
+    domain: ChallengeDomain,
+    replica_id: &D,
+    seed: &[u8; 32],
+    index: u32,
+    leaves: usize,
+) -> Result<usize> {
+    ensure!(leaves > 0, "cannot derive challenges for an empty tree");
+
+    let hash = Sha256::new()
+        .chain(domain.tag())
+        .chain(replica_id.into_bytes())
+        .chain(seed)
+        .chain(&index.to_le_bytes())
+        .result();
+
+    (BigUint::from_bytes_le(hash.as_ref()) % leaves)
+        .to_usize()
+        .context("challenge exceeds size of `usize`")
+}
+
+/// Derives `count` challenges in `0..leaves`, see `derive_challenge`.
+pub fn derive_challenges<D: Domain>(
+    domain: ChallengeDomain,
+    replica_id: &D,
+    seed: &[u8; 32],
+    leaves: usize,
+    count: usize,
+) -> Result<Vec<usize>> {
+    (0..count)
+        .map(|i| derive_challenge(domain, replica_id, seed, i as u32, leaves))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::Rng;
+
+    use crate::hasher::pedersen::PedersenDomain;
+    use crate::test_helper::seeded_rng;
+
+    #[test]
+    fn test_domains_differ() {
+        let rng = &mut seeded_rng();
+        let replica_id = PedersenDomain::random(rng);
+        let seed: [u8; 32] = rng.gen();
+        let leaves = 1 << 30;
+        let count = 20;
+
+        let porep =
+            derive_challenges(ChallengeDomain::PoRep, &replica_id, &seed, leaves, count).unwrap();
+        let post =
+            derive_challenges(ChallengeDomain::PoSt, &replica_id, &seed, leaves, count).unwrap();
+
+        assert_eq!(porep.len(), count);
+        assert!(porep.iter().chain(post.iter()).all(|c| *c < leaves));
+        assert_ne!(porep, post);
+
+        // Derivation is deterministic.
+        assert_eq!(
+            derive_challenges(ChallengeDomain::PoRep, &replica_id, &seed, leaves, count).unwrap(),
+            porep
+        );
+        assert!(derive_challenges(ChallengeDomain::PoRep, &replica_id, &seed, 0, count).is_err());
+    }
+}
@@ -119,13 +119,12 @@ pub fn difference<E: Engine, A, AR, CS: ConstraintSystem<E>>(
 mod tests {
     use super::*;
     use crate::circuit::test::TestConstraintSystem;
+    use crate::test_helper::seeded_rng;
     use paired::bls12_381::{Bls12, Fr};
-    use rand::SeedableRng;
-    use rand_xorshift::XorShiftRng;
 
     #[test]
     fn add_constraint() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         for _ in 0..100 {
             let mut cs = TestConstraintSystem::<Bls12>::new();
@@ -145,7 +144,7 @@ mod tests {
 
     #[test]
     fn sub_constraint() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         for _ in 0..100 {
             let mut cs = TestConstraintSystem::<Bls12>::new();
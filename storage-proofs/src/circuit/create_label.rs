@@ -8,20 +8,44 @@ use ff::PrimeField;
 use fil_sapling_crypto::jubjub::JubjubEngine;
 use log::trace;
 
+use crate::bit_order::swap_bit_order;
 use crate::circuit::uint64;
+use crate::crypto::create_label::KdfConfig;
 
 /// Key derivation function.
 pub fn create_label<E, CS>(
+    cs: CS,
+    id: &[Boolean],
+    parents: Vec<Vec<Boolean>>,
+    window_index: Option<uint64::UInt64>,
+    node: Option<uint64::UInt64>,
+) -> Result<num::AllocatedNum<E>, SynthesisError>
+where
+    E: JubjubEngine,
+    CS: ConstraintSystem<E>,
+{
+    create_label_with(cs, id, parents, window_index, node, &KdfConfig::default())
+}
+
+/// Like `create_label`, but clears as many high bits as `config` asks for, matching
+/// `crypto::create_label::create_label_with`.
+pub fn create_label_with<E, CS>(
     mut cs: CS,
     id: &[Boolean],
     parents: Vec<Vec<Boolean>>,
     window_index: Option<uint64::UInt64>,
     node: Option<uint64::UInt64>,
+    config: &KdfConfig,
 ) -> Result<num::AllocatedNum<E>, SynthesisError>
 where
     E: JubjubEngine,
     CS: ConstraintSystem<E>,
 {
+    // At least 2 high bits must be masked for the label to fit into a field element.
+    if config.kept_bits() > E::Fr::CAPACITY as usize {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
     trace!("circuit: create_label");
     // ciphertexts will become a buffer of the layout
     // id | node | encodedParentNode1 | encodedParentNode1 | ...
@@ -48,12 +72,8 @@ where
             .map(|v| v.get_value().ok_or(SynthesisError::AssignmentMissing))
             .collect::<Result<Vec<bool>, SynthesisError>>()?;
 
-        let le_bits = be_bits
-            .chunks(8)
-            .flat_map(|chunk| chunk.iter().rev())
-            .copied()
-            .take(E::Fr::CAPACITY as usize)
-            .collect::<Vec<bool>>();
+        let mut le_bits = swap_bit_order(&be_bits);
+        le_bits.truncate(config.kept_bits());
 
         Ok(multipack::compute_multipacking::<E>(&le_bits)[0])
     } else {
@@ -69,19 +89,23 @@ mod tests {
     use crate::circuit::test::TestConstraintSystem;
     use crate::crypto;
     use crate::fr32::fr_into_bytes;
+    use crate::hasher::{Hasher, PedersenHasher, Sha256Hasher};
+    use crate::test_helper::seeded_rng;
     use crate::util::bytes_into_boolean_vec_be;
 
     use bellperson::gadgets::boolean::Boolean;
     use bellperson::ConstraintSystem;
     use ff::Field;
     use paired::bls12_381::{Bls12, Fr};
-    use rand::SeedableRng;
+    use proptest::prelude::{any, ProptestConfig};
+    use proptest::{proptest, proptest_helper};
+    use rand::{Rng, SeedableRng};
     use rand_xorshift::XorShiftRng;
 
     #[test]
     fn create_label_circuit_no_node() {
         let mut cs = TestConstraintSystem::<Bls12>::new();
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let m = 20;
 
@@ -131,7 +155,7 @@ mod tests {
     #[test]
     fn create_label_circuit_with_node() {
         let mut cs = TestConstraintSystem::<Bls12>::new();
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let m = 20;
 
@@ -187,4 +211,118 @@ mod tests {
             "circuit and non circuit do not match"
         );
     }
+
+    /// Synthesizes the circuit over `data`, the replica id followed by `m` parent labels, and
+    /// checks it against the native `H::create_label`.
+    fn create_label_matches_native<H: Hasher>(data: &[u8], m: usize) {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let id_bits: Vec<Boolean> = {
+            let mut cs = cs.namespace(|| "id");
+            bytes_into_boolean_vec_be(&mut cs, Some(&data[..32]), 32).unwrap()
+        };
+        let parents_bits: Vec<Vec<Boolean>> = data[32..]
+            .chunks(32)
+            .enumerate()
+            .map(|(i, p)| {
+                let mut cs = cs.namespace(|| format!("parents {}", i));
+                bytes_into_boolean_vec_be(&mut cs, Some(p), p.len()).unwrap()
+            })
+            .collect();
+        assert_eq!(parents_bits.len(), m);
+
+        let out = create_label(
+            cs.namespace(|| "create_label"),
+            &id_bits,
+            parents_bits,
+            None,
+            None,
+        )
+        .expect("key derivation function failed");
+        assert!(cs.is_satisfied(), "constraints not satisfied");
+
+        let expected: Fr = H::create_label(data, m).unwrap().into();
+        assert_eq!(
+            expected,
+            out.get_value().unwrap(),
+            "circuit and non circuit do not match for m = {}",
+            m
+        );
+    }
+
+    #[test]
+    fn create_label_circuit_mask_widths() {
+        let data = vec![1u8; 64];
+
+        for mask_high_bits in &[2, 3, 8, 64] {
+            let config = KdfConfig {
+                mask_high_bits: *mask_high_bits,
+            };
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let id_bits: Vec<Boolean> = {
+                let mut cs = cs.namespace(|| "id");
+                bytes_into_boolean_vec_be(&mut cs, Some(&data[..32]), 32).unwrap()
+            };
+            let parents_bits: Vec<Vec<Boolean>> = {
+                let mut cs = cs.namespace(|| "parents");
+                vec![bytes_into_boolean_vec_be(&mut cs, Some(&data[32..]), 32).unwrap()]
+            };
+
+            let out = create_label_with(
+                cs.namespace(|| "create_label"),
+                &id_bits,
+                parents_bits,
+                None,
+                None,
+                &config,
+            )
+            .expect("key derivation function failed");
+            assert!(cs.is_satisfied(), "constraints not satisfied");
+
+            let expected = crypto::create_label::create_label_with(&data, 1, &config).unwrap();
+            assert_eq!(
+                expected,
+                out.get_value().unwrap(),
+                "circuit and non circuit do not match for mask width {}",
+                mask_high_bits
+            );
+        }
+    }
+
+    #[test]
+    fn create_label_circuit_rejects_narrow_mask() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let id_bits: Vec<Boolean> = {
+            let mut cs = cs.namespace(|| "id");
+            bytes_into_boolean_vec_be(&mut cs, Some(&[1u8; 32]), 32).unwrap()
+        };
+
+        let res = create_label_with(
+            cs.namespace(|| "create_label"),
+            &id_bits,
+            Vec::new(),
+            None,
+            None,
+            &KdfConfig { mask_high_bits: 1 },
+        );
+        match res {
+            Err(SynthesisError::Unsatisfiable) => {}
+            other => panic!("unexpected result: {:?}", other.map(|num| num.get_value())),
+        }
+    }
+
+    proptest! {
+        // Every case synthesizes a few sha256 blocks, so keep the number of cases small.
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn create_label_circuit_matches_native(m in 1usize..8, seed in any::<u64>()) {
+            let rng = &mut XorShiftRng::seed_from_u64(seed);
+            let data: Vec<u8> = (0..32 * (1 + m)).map(|_| rng.gen()).collect();
+
+            create_label_matches_native::<PedersenHasher>(&data, m);
+            create_label_matches_native::<Sha256Hasher>(&data, m);
+        }
+    }
 }
@@ -169,6 +169,7 @@ where
         let por_pub_params = merklepor::PublicParams {
             leaves,
             private: pub_params.private,
+            bind_context: false,
         };
 
         let mut input: Vec<Fr> = Vec::new();
@@ -184,6 +185,7 @@ where
                 let por_pub_inputs = merklepor::PublicInputs {
                     commitment: comm_r,
                     challenge: node as usize,
+                    context: Vec::new(),
                 };
                 let por_inputs = PoRCompound::<H>::generate_public_inputs(
                     &por_pub_inputs,
@@ -197,6 +199,7 @@ where
             let por_pub_inputs = merklepor::PublicInputs {
                 commitment: comm_d,
                 challenge: *challenge,
+                context: Vec::new(),
             };
 
             let por_inputs =
@@ -516,15 +519,14 @@ mod tests {
     use crate::hasher::{Blake2sHasher, Hasher, PedersenHasher};
     use crate::porep::PoRep;
     use crate::proof::{NoRequirements, ProofScheme};
+    use crate::test_helper::seeded_rng;
     use crate::util::data_at_node;
 
     use ff::Field;
-    use rand::SeedableRng;
-    use rand_xorshift::XorShiftRng;
 
     #[test]
     fn drgporep_input_circuit_with_bls12_381() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let nodes = 12;
         let degree = BASE_DEGREE;
@@ -695,7 +697,7 @@ mod tests {
 
     #[test]
     fn drgporep_input_circuit_num_constraints() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         // 1 GB
         let n = (1 << 30) / 32;
@@ -739,7 +741,7 @@ mod tests {
         //     .start(log::LevelFilter::Trace)
         //     .ok();
 
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let nodes = 5;
         let degree = BASE_DEGREE;
@@ -0,0 +1,165 @@
+use std::marker::PhantomData;
+
+use bellperson::gadgets::num;
+use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+use fil_sapling_crypto::jubjub::JubjubEngine;
+
+use crate::circuit::por::PoRCircuit;
+use crate::circuit::variables::Root;
+use crate::hasher::Hasher;
+
+/// Proof that a single value is included in two merkle trees, without revealing it.
+///
+/// Useful to show that two trees, for example built with different hashers, share their leaves.
+///
+/// # Fields
+///
+/// * `params` - The params for the bls curve.
+/// * `value` - The value of the leaf, shared by both trees.
+/// * `auth_path_a` - The authentication path of the leaf in the first tree.
+/// * `root_a` - The merkle root of the first tree.
+/// * `auth_path_b` - The authentication path of the leaf in the second tree.
+/// * `root_b` - The merkle root of the second tree.
+///
+pub struct DualRootInclusion<'a, E: JubjubEngine, A: Hasher, B: Hasher> {
+    pub params: &'a E::Params,
+    pub value: Option<E::Fr>,
+    pub auth_path_a: Vec<Option<(E::Fr, bool)>>,
+    pub root_a: Root<E>,
+    pub auth_path_b: Vec<Option<(E::Fr, bool)>>,
+    pub root_b: Root<E>,
+    pub _a: PhantomData<A>,
+    pub _b: PhantomData<B>,
+}
+
+impl<'a, E: JubjubEngine, A: Hasher, B: Hasher> Circuit<E> for DualRootInclusion<'a, E, A, B> {
+    /// # Public Inputs
+    ///
+    /// This circuit expects the following public inputs.
+    ///
+    /// * [0] - packed version of the `is_right` components of the first auth_path.
+    /// * [1] - the merkle root of the first tree.
+    /// * [2] - packed version of the `is_right` components of the second auth_path.
+    /// * [3] - the merkle root of the second tree.
+    ///
+    /// The value is only allocated once, and both paths climb from that very variable.
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let value = self.value;
+        let value_num = num::AllocatedNum::alloc(cs.namespace(|| "value"), || {
+            value.ok_or_else(|| SynthesisError::AssignmentMissing)
+        })?;
+
+        PoRCircuit::<E, A>::synthesize(
+            cs.namespace(|| "tree_a"),
+            self.params,
+            Root::from_allocated::<CS>(value_num.clone()),
+            self.auth_path_a,
+            self.root_a,
+            false,
+        )?;
+
+        PoRCircuit::<E, B>::synthesize(
+            cs.namespace(|| "tree_b"),
+            self.params,
+            Root::from_allocated::<CS>(value_num),
+            self.auth_path_b,
+            self.root_b,
+            false,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bellperson::gadgets::multipack;
+    use ff::Field;
+    use paired::bls12_381::{Bls12, Fr};
+
+    use crate::circuit::test::*;
+    use crate::crypto::pedersen::JJ_PARAMS;
+    use crate::drgraph::{new_seed, BucketGraph, Graph, BASE_DEGREE};
+    use crate::fr32::{bytes_into_fr, fr_into_bytes};
+    use crate::hasher::{Blake2sHasher, PedersenHasher};
+    use crate::merkle::{MerkleProof, MerkleTreeExt};
+    use crate::test_helper::{random_data, seeded_rng};
+    use crate::util::data_at_node;
+
+    /// Synthesizes the circuit for leaf `challenge` of `data_a` and `data_b`, returning whether it
+    /// is satisfied.
+    fn synthesize_dual_root(data_a: &[u8], data_b: &[u8], challenge: usize) -> bool {
+        let leaves = data_a.len() / 32;
+
+        let graph_a =
+            BucketGraph::<PedersenHasher>::new(leaves, BASE_DEGREE, 0, new_seed()).unwrap();
+        let tree_a = graph_a.merkle_tree(data_a).unwrap();
+        let proof_a =
+            MerkleProof::<PedersenHasher>::new_from_proof(&tree_a.gen_proof(challenge).unwrap());
+
+        let graph_b =
+            BucketGraph::<Blake2sHasher>::new(leaves, BASE_DEGREE, 0, new_seed()).unwrap();
+        let tree_b = graph_b.merkle_tree(data_b).unwrap();
+        let proof_b =
+            MerkleProof::<Blake2sHasher>::new_from_proof(&tree_b.gen_proof(challenge).unwrap());
+
+        let value = bytes_into_fr::<Bls12>(data_at_node(data_a, challenge).unwrap()).unwrap();
+
+        let circuit = DualRootInclusion::<Bls12, PedersenHasher, Blake2sHasher> {
+            params: &JJ_PARAMS,
+            value: Some(value),
+            auth_path_a: proof_a.as_options(),
+            root_a: Root::Val(Some(tree_a.root().into())),
+            auth_path_b: proof_b.as_options(),
+            root_b: Root::Val(Some(tree_b.root().into())),
+            _a: PhantomData,
+            _b: PhantomData,
+        };
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        circuit
+            .synthesize(&mut cs)
+            .expect("circuit synthesis failed");
+
+        let mut expected_inputs = Vec::new();
+        for (path, root) in &[
+            (proof_a.as_pairs(), tree_a.root_fr()),
+            (proof_b.as_pairs(), tree_b.root_fr()),
+        ] {
+            let bits: Vec<bool> = path.iter().map(|(_, is_right)| *is_right).collect();
+            expected_inputs.extend(multipack::compute_multipacking::<Bls12>(&bits));
+            expected_inputs.push(*root);
+        }
+        assert_eq!(cs.num_inputs(), 5, "wrong number of inputs");
+        assert!(cs.verify(&expected_inputs), "failed to verify inputs");
+
+        cs.is_satisfied()
+    }
+
+    #[test]
+    fn test_dual_root_inclusion() {
+        let data = random_data(8, &mut seeded_rng());
+
+        for challenge in &[0, 5, 7] {
+            assert!(
+                synthesize_dual_root(&data, &data, *challenge),
+                "constraints not satisfied"
+            );
+        }
+    }
+
+    #[test]
+    fn test_dual_root_inclusion_divergent_leaves() {
+        let data_a = random_data(8, &mut seeded_rng());
+        let challenge = 5;
+
+        // Same trees, except for the challenged leaf.
+        let mut data_b = data_a.clone();
+        let other = fr_into_bytes::<Bls12>(&Fr::one());
+        data_b[challenge * 32..(challenge + 1) * 32].copy_from_slice(&other);
+
+        assert!(!synthesize_dual_root(&data_a, &data_b, challenge));
+        // The other leaves are still shared.
+        assert!(synthesize_dual_root(&data_a, &data_b, challenge - 1));
+    }
+}
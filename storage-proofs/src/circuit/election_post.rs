@@ -82,6 +82,7 @@ where
         let por_pub_params = merklepor::PublicParams {
             leaves: (pub_params.sector_size as usize / NODE_SIZE),
             private: true,
+            bind_context: false,
         };
 
         // 1. Inputs for verifying comm_r = H(comm_c || comm_q || comm_r_last)
@@ -101,6 +102,7 @@ where
                 let por_pub_inputs = merklepor::PublicInputs {
                     commitment: None,
                     challenge: challenged_leaf_start as usize + i,
+                    context: Vec::new(),
                 };
                 let por_inputs = PoRCompound::<H>::generate_public_inputs(
                     &por_pub_inputs,
@@ -350,8 +352,7 @@ mod tests {
     use std::collections::BTreeMap;
 
     use ff::Field;
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
 
     use crate::circuit::test::*;
     use crate::compound_proof;
@@ -363,10 +364,11 @@ mod tests {
     use crate::proof::{NoRequirements, ProofScheme};
     use crate::sector::SectorId;
     use crate::stacked::hash::hash3;
+    use crate::test_helper::seeded_rng;
 
     #[test]
     fn test_election_post_circuit() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let leaves = 32;
         let sector_size = leaves * 32;
@@ -496,7 +498,7 @@ mod tests {
     #[ignore] // Slow test – run only when compiled for release.
     #[test]
     fn election_post_test_compound() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let leaves = 32;
         let sector_size = leaves * 32;
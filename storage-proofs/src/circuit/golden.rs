@@ -0,0 +1,105 @@
+//! Golden constraint counts for the circuits.
+//!
+//! When a gadget changes on purpose, update the corresponding entry of `GOLDEN`, the failing test
+//! prints the new value.
+
+use ff::Field;
+use paired::bls12_381::{Bls12, Fr};
+
+use crate::circuit::por::PoRCircuit;
+use crate::circuit::test::TestConstraintSystem;
+use crate::circuit::variables::Root;
+use crate::crypto::pedersen::JJ_PARAMS;
+use crate::drgraph::{new_seed, BucketGraph, Graph, BASE_DEGREE};
+use crate::fr32::fr_into_bytes;
+use crate::hasher::{Blake2sHasher, Domain, Hasher, PedersenHasher};
+use crate::merkle::MerkleTreeExt;
+use crate::merklepor;
+use crate::proof::ProofScheme;
+use crate::test_helper::seeded_rng;
+use crate::util::data_at_node;
+
+/// `(circuit, hasher, expected number of constraints)`
+const GOLDEN: &[(&str, &str, usize)] = &[("por", "pedersen", 4125), ("por", "blake2s", 64566)];
+
+/// Synthesizes a public PoR circuit over a tree of 6 leaves.
+fn por_constraints<H: Hasher>() -> usize {
+    let rng = &mut seeded_rng();
+    let leaves = 6;
+
+    let data: Vec<u8> = (0..leaves)
+        .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
+        .collect();
+    let graph = BucketGraph::<H>::new(leaves, BASE_DEGREE, 0, new_seed()).unwrap();
+    let tree = graph.merkle_tree(data.as_slice()).unwrap();
+
+    let pub_params = merklepor::PublicParams {
+        leaves,
+        private: false,
+        bind_context: false,
+    };
+    let pub_inputs = merklepor::PublicInputs::<H::Domain> {
+        challenge: 0,
+        commitment: Some(tree.root()),
+        context: Vec::new(),
+    };
+    let priv_inputs = merklepor::PrivateInputs::<H>::new(
+        H::Domain::try_from_bytes(data_at_node(data.as_slice(), 0).unwrap()).unwrap(),
+        &tree,
+    );
+    let proof = merklepor::MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs)
+        .expect("proving failed");
+
+    let mut cs = TestConstraintSystem::<Bls12>::new();
+    PoRCircuit::<Bls12, H>::synthesize(
+        &mut cs,
+        &JJ_PARAMS,
+        Root::Val(Some(proof.data.into())),
+        proof.proof.as_options(),
+        Root::Val(Some(tree.root_fr())),
+        false,
+    )
+    .expect("circuit synthesis failed");
+    assert!(cs.is_satisfied(), "constraints not satisfied");
+
+    cs.num_constraints()
+}
+
+fn constraints(circuit: &str, hasher: &str) -> usize {
+    match (circuit, hasher) {
+        ("por", "pedersen") => por_constraints::<PedersenHasher>(),
+        ("por", "blake2s") => por_constraints::<Blake2sHasher>(),
+        _ => panic!("no circuit registered for ({}, {})", circuit, hasher),
+    }
+}
+
+#[test]
+fn test_golden_constraint_counts() {
+    let mismatches: Vec<String> = GOLDEN
+        .iter()
+        .filter_map(|&(circuit, hasher, expected)| {
+            let actual = constraints(circuit, hasher);
+            if actual == expected {
+                return None;
+            }
+
+            Some(format!(
+                "  ({:?}, {:?}): expected {}, got {} ({:+}), update the entry to ({:?}, {:?}, {})",
+                circuit,
+                hasher,
+                expected,
+                actual,
+                actual as i64 - expected as i64,
+                circuit,
+                hasher,
+                actual
+            ))
+        })
+        .collect();
+
+    assert!(
+        mismatches.is_empty(),
+        "constraint counts changed:\n{}",
+        mismatches.join("\n")
+    );
+}
@@ -1,7 +1,10 @@
 mod constraint;
+#[cfg(test)]
+mod golden;
 
 pub mod create_label;
 pub mod drgporep;
+pub mod dual_root;
 pub mod election_post;
 pub mod encode;
 pub mod multi_proof;
@@ -85,16 +85,16 @@ mod tests {
     use crate::circuit::test::TestConstraintSystem;
     use crate::crypto;
     use crate::crypto::pedersen::JJ_PARAMS;
+    use crate::test_helper::seeded_rng;
     use crate::util::bytes_into_boolean_vec;
     use bellperson::gadgets::boolean::Boolean;
     use bellperson::ConstraintSystem;
     use paired::bls12_381::Bls12;
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
 
     #[test]
     fn test_pedersen_single_input_circut() {
-        let mut rng = XorShiftRng::from_seed(crate::TEST_SEED);
+        let mut rng = seeded_rng();
 
         let cases = [(32, 689), (64, 1376)];
 
@@ -129,7 +129,7 @@ mod tests {
 
     #[test]
     fn test_pedersen_md_input_circut() {
-        let mut rng = XorShiftRng::from_seed(crate::TEST_SEED);
+        let mut rng = seeded_rng();
 
         let cases = [
             (64, 1376),   // 64 bytes
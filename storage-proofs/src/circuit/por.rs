@@ -6,14 +6,16 @@ use bellperson::{Circuit, ConstraintSystem, SynthesisError};
 use fil_sapling_crypto::jubjub::JubjubEngine;
 use paired::bls12_381::{Bls12, Fr};
 
+use crate::circuit::bench::BenchCS;
 use crate::circuit::constraint;
+use crate::circuit::test::TestConstraintSystem;
 use crate::circuit::variables::Root;
 use crate::compound_proof::{CircuitComponent, CompoundProof};
 use crate::crypto::pedersen::JJ_PARAMS;
 use crate::drgraph::graph_height;
 use crate::error::Result;
 use crate::hasher::{HashFunction, Hasher};
-use crate::merklepor::MerklePoR;
+use crate::merklepor::{self, MerklePoR};
 use crate::parameter_cache::{CacheableParameters, ParameterSetMetadata};
 use crate::proof::ProofScheme;
 
@@ -25,6 +27,7 @@ use crate::proof::ProofScheme;
 /// * `value` - The value of the leaf.
 /// * `auth_path` - The authentication path of the leaf in the tree.
 /// * `root` - The merkle root of the tree.
+/// * `context` - The hashed context the proof is bound to, if any.
 ///
 pub struct PoRCircuit<'a, E: JubjubEngine, H: Hasher> {
     params: &'a E::Params,
@@ -32,6 +35,7 @@ pub struct PoRCircuit<'a, E: JubjubEngine, H: Hasher> {
     auth_path: Vec<Option<(E::Fr, bool)>>,
     root: Root<E>,
     private: bool,
+    context: Option<Root<E>>,
     _h: PhantomData<H>,
 }
 
@@ -43,6 +47,27 @@ pub struct PoRCompound<H: Hasher> {
     _h: PhantomData<H>,
 }
 
+/// Name of the constraint enforcing that the climbed path leads to the root.
+const ROOT_CONSTRAINT: &str = "enforce root is correct";
+
+/// Explains why `cs` is unsatisfied, for debugging, or returns `None` if it is satisfied. A root
+/// mismatch of a PoR circuit is reported together with the namespace it was synthesized in, which
+/// names the round whose auth path does not lead to the root.
+pub fn diagnose_unsatisfied<E: JubjubEngine>(cs: &TestConstraintSystem<E>) -> Option<String> {
+    let path = cs.which_is_unsatisfied()?;
+
+    let diagnostic = if path == ROOT_CONSTRAINT {
+        "root mismatch".to_string()
+    } else if path.ends_with(ROOT_CONSTRAINT) {
+        let round = path[..path.len() - ROOT_CONSTRAINT.len()].trim_end_matches('/');
+        format!("root mismatch in {}", round)
+    } else {
+        format!("unsatisfied constraint: {}", path)
+    };
+
+    Some(diagnostic)
+}
+
 pub fn challenge_into_auth_path_bits(challenge: usize, leaves: usize) -> Vec<bool> {
     let height = graph_height(leaves);
     let mut bits = Vec::new();
@@ -54,6 +79,14 @@ pub fn challenge_into_auth_path_bits(challenge: usize, leaves: usize) -> Vec<boo
     bits
 }
 
+/// Returns the number of constraints of the PoR circuit for the given params.
+pub fn ppor_constraint_count<H: Hasher>(pub_params: &merklepor::PublicParams) -> Result<usize> {
+    let mut cs = BenchCS::<Bls12>::new();
+    PoRCompound::<H>::blank_circuit(pub_params).synthesize(&mut cs)?;
+
+    Ok(cs.num_constraints())
+}
+
 impl<E: JubjubEngine, C: Circuit<E>, P: ParameterSetMetadata, H: Hasher>
     CacheableParameters<E, C, P> for PoRCompound<H>
 {
@@ -82,6 +115,17 @@ where
             private == public_params.private,
             "Inputs must be consistent with public params"
         );
+        ensure!(
+            public_params.bind_context || public_inputs.context.is_empty(),
+            "Context given, but the params do not bind it"
+        );
+        let context = if public_params.bind_context {
+            Some(Root::Val(Some(merklepor::context_into_fr(
+                &public_inputs.context,
+            ))))
+        } else {
+            None
+        };
 
         Ok(PoRCircuit::<Bls12, H> {
             params: &*JJ_PARAMS,
@@ -89,6 +133,7 @@ where
             auth_path: proof.proof.as_options(),
             root,
             private,
+            context,
             _h: Default::default(),
         })
     }
@@ -102,6 +147,11 @@ where
             auth_path: vec![None; graph_height(public_params.leaves)],
             root: Root::Val(None),
             private: public_params.private,
+            context: if public_params.bind_context {
+                Some(Root::Val(None))
+            } else {
+                None
+            },
             _h: Default::default(),
         }
     }
@@ -124,6 +174,15 @@ where
             ensure!(pub_params.private, "Params must be private");
         }
 
+        if pub_params.bind_context {
+            inputs.push(merklepor::context_into_fr(&pub_inputs.context));
+        } else {
+            ensure!(
+                pub_inputs.context.is_empty(),
+                "Context given, but the params do not bind it"
+            );
+        }
+
         Ok(inputs)
     }
 }
@@ -135,6 +194,7 @@ impl<'a, E: JubjubEngine, H: Hasher> Circuit<E> for PoRCircuit<'a, E, H> {
     ///
     /// * [0] - packed version of the `is_right` components of the auth_path.
     /// * [1] - the merkle root of the tree.
+    /// * [2] - the hashed context, if bound.
     ///
     /// This circuit derives the following private inputs from its fields:
     /// * value_num - packed version of `value` as bits. (might be more than one Fr)
@@ -152,6 +212,9 @@ impl<'a, E: JubjubEngine, H: Hasher> Circuit<E> for PoRCircuit<'a, E, H> {
         {
             let value_num = value.allocated(cs.namespace(|| "value"))?;
 
+            // The path climb starts at the very variable holding the value, so the final root
+            // equality below is what ties the value to the proven inclusion. Do not re-allocate
+            // the leaf here, that would break this link.
             let mut cur = value_num;
 
             let mut auth_path_bits = Vec::with_capacity(auth_path.len());
@@ -197,12 +260,15 @@ impl<'a, E: JubjubEngine, H: Hasher> Circuit<E> for PoRCircuit<'a, E, H> {
             }
 
             // allocate input for is_right auth_path
+            // The packed bits are the little endian binary decomposition of the challenged leaf
+            // index, see `challenge_into_auth_path_bits`. Exposing them binds the proof to that
+            // position: a prover climbing a different path produces different public inputs.
             multipack::pack_into_inputs(cs.namespace(|| "path"), &auth_path_bits)?;
 
             {
                 // Validate that the root of the merkle tree that we calculated is the same as the input.
                 let rt = root.allocated(cs.namespace(|| "root_value"))?;
-                constraint::equal(cs, || "enforce root is correct", &cur, &rt);
+                constraint::equal(cs, || ROOT_CONSTRAINT, &cur, &rt);
 
                 if !self.private {
                     // Expose the root
@@ -210,6 +276,12 @@ impl<'a, E: JubjubEngine, H: Hasher> Circuit<E> for PoRCircuit<'a, E, H> {
                 }
             }
 
+            if let Some(context) = self.context {
+                // Exposing the context binds the proof to it, it is not otherwise constrained.
+                let context = context.allocated(cs.namespace(|| "context_value"))?;
+                context.inputize(cs.namespace(|| "context"))?;
+            }
+
             Ok(())
         }
     }
@@ -234,6 +306,7 @@ impl<'a, E: JubjubEngine, H: Hasher> PoRCircuit<'a, E, H> {
             auth_path,
             root,
             private,
+            context: None,
             _h: Default::default(),
         };
 
@@ -245,11 +318,11 @@ impl<'a, E: JubjubEngine, H: Hasher> PoRCircuit<'a, E, H> {
 mod tests {
     use super::*;
 
+    use std::time::Duration;
+
     use crate::proof::NoRequirements;
     use bellperson::gadgets::multipack;
     use ff::Field;
-    use rand::SeedableRng;
-    use rand_xorshift::XorShiftRng;
 
     use crate::circuit::test::*;
     use crate::compound_proof;
@@ -257,14 +330,30 @@ mod tests {
     use crate::drgraph::{new_seed, BucketGraph, Graph, BASE_DEGREE};
     use crate::fr32::{bytes_into_fr, fr_into_bytes};
     use crate::hasher::{Blake2sHasher, Domain, Hasher, PedersenHasher};
+    use crate::merkle::{MerkleProof, MerkleTree, MerkleTreeExt};
     use crate::merklepor;
     use crate::proof::ProofScheme;
+    use crate::test_helper::{random_data, seeded_rng};
     use crate::util::data_at_node;
 
+    /// A tree over `leaves` random leaves, and the leaf at `challenge`.
+    fn por_fixture<H: Hasher>(
+        leaves: usize,
+        challenge: usize,
+    ) -> (MerkleTree<H::Domain, H::Function>, H::Domain) {
+        let data = random_data(leaves, &mut seeded_rng());
+        let graph = BucketGraph::<H>::new(leaves, BASE_DEGREE, 0, new_seed()).unwrap();
+        let tree = graph.merkle_tree(data.as_slice()).unwrap();
+        let leaf =
+            H::Domain::try_from_bytes(data_at_node(data.as_slice(), challenge).unwrap()).unwrap();
+
+        (tree, leaf)
+    }
+
     #[test]
     #[ignore] // Slow test – run only when compiled for release.
     fn por_test_compound() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
         let leaves = 6;
         let data: Vec<u8> = (0..leaves)
             .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
@@ -276,12 +365,14 @@ mod tests {
             let public_inputs = merklepor::PublicInputs {
                 challenge: i,
                 commitment: Some(tree.root()),
+                context: Vec::new(),
             };
 
             let setup_params = compound_proof::SetupParams {
                 vanilla_params: merklepor::SetupParams {
                     leaves,
                     private: false,
+                    bind_context: false,
                 },
                 partitions: None,
             };
@@ -346,7 +437,7 @@ mod tests {
     }
 
     fn test_por_input_circuit_with_bls12_381<H: Hasher>(num_constraints: usize) {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let leaves = 6;
 
@@ -365,10 +456,12 @@ mod tests {
             let pub_params = merklepor::PublicParams {
                 leaves,
                 private: true,
+                bind_context: false,
             };
             let pub_inputs = merklepor::PublicInputs::<H::Domain> {
                 challenge: i,
                 commitment: Some(tree.root()),
+                context: Vec::new(),
             };
 
             let priv_inputs = merklepor::PrivateInputs::<H>::new(
@@ -397,6 +490,7 @@ mod tests {
                 auth_path: proof.proof.as_options(),
                 root: Root::Val(Some(pub_inputs.commitment.unwrap().into())),
                 private: false,
+                context: None,
                 _h: Default::default(),
             };
 
@@ -441,6 +535,316 @@ mod tests {
         }
     }
 
+    fn test_por_public_inputs_match_circuit<H: Hasher>(private: bool) {
+        let leaves = 16;
+
+        let pub_params = merklepor::PublicParams {
+            leaves,
+            private,
+            bind_context: false,
+        };
+
+        for &challenge in &[0, 7, leaves - 1] {
+            let (tree, leaf) = por_fixture::<H>(leaves, challenge);
+            let pub_inputs = merklepor::PublicInputs::<H::Domain> {
+                challenge,
+                commitment: if private { None } else { Some(tree.root()) },
+                context: Vec::new(),
+            };
+            let priv_inputs = merklepor::PrivateInputs::<H>::new(leaf, &tree);
+            let proof = merklepor::MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs)
+                .expect("proving failed");
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            PoRCompound::<H>::circuit(&pub_inputs, None, &proof, &pub_params)
+                .unwrap()
+                .synthesize(&mut cs)
+                .expect("circuit synthesis failed");
+            assert!(cs.is_satisfied(), "constraints not satisfied");
+
+            let generated =
+                PoRCompound::<H>::generate_public_inputs(&pub_inputs, &pub_params, None).unwrap();
+
+            // The first input of the constraint system is the constant `ONE`.
+            let synthesized: Vec<Fr> = cs.get_inputs()[1..].iter().map(|(v, _)| *v).collect();
+            assert_eq!(
+                synthesized, generated,
+                "inputs differ for challenge {}",
+                challenge
+            );
+        }
+    }
+
+    #[test]
+    fn test_por_public_inputs_match_circuit_pedersen() {
+        test_por_public_inputs_match_circuit::<PedersenHasher>(false);
+        test_por_public_inputs_match_circuit::<PedersenHasher>(true);
+    }
+
+    #[test]
+    fn test_por_public_inputs_match_circuit_blake2s() {
+        test_por_public_inputs_match_circuit::<Blake2sHasher>(false);
+        test_por_public_inputs_match_circuit::<Blake2sHasher>(true);
+    }
+
+    #[test]
+    fn test_por_rejects_wrong_value_pedersen() {
+        test_por_rejects_wrong_value::<PedersenHasher>();
+    }
+
+    #[test]
+    fn test_por_rejects_wrong_value_blake2s() {
+        test_por_rejects_wrong_value::<Blake2sHasher>();
+    }
+
+    fn test_por_rejects_wrong_value<H: Hasher>() {
+        let leaves = 8;
+        let (tree, leaf) = por_fixture::<H>(leaves, 3);
+
+        let pub_params = merklepor::PublicParams::new(leaves);
+        let pub_inputs = merklepor::PublicInputs::<H::Domain> {
+            challenge: 3,
+            commitment: Some(tree.root()),
+            context: Vec::new(),
+        };
+        let priv_inputs = merklepor::PrivateInputs::<H>::new(leaf, &tree);
+        let proof = merklepor::MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs)
+            .expect("proving failed");
+
+        // A value that is not the leaf the auth path was generated for.
+        let mut wrong_value: Fr = proof.data.into();
+        wrong_value.add_assign(&Fr::one());
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let por = PoRCircuit::<Bls12, H> {
+            params: &JJ_PARAMS,
+            value: Root::Val(Some(wrong_value)),
+            auth_path: proof.proof.as_options(),
+            root: Root::Val(Some(pub_inputs.commitment.unwrap().into())),
+            private: false,
+            context: None,
+            _h: Default::default(),
+        };
+
+        por.synthesize(&mut cs).expect("circuit synthesis failed");
+        assert!(
+            !cs.is_satisfied(),
+            "wrong value must not satisfy the circuit"
+        );
+        assert_eq!(cs.which_is_unsatisfied(), Some("enforce root is correct"));
+    }
+
+    #[test]
+    fn test_por_binds_challenge_pedersen() {
+        test_por_binds_challenge::<PedersenHasher>();
+    }
+
+    #[test]
+    fn test_por_binds_challenge_blake2s() {
+        test_por_binds_challenge::<Blake2sHasher>();
+    }
+
+    fn test_por_binds_challenge<H: Hasher>() {
+        let leaves = 8;
+
+        let pub_params = merklepor::PublicParams::new(leaves);
+        let prove = |challenge: usize| {
+            let (tree, leaf) = por_fixture::<H>(leaves, challenge);
+            let pub_inputs = merklepor::PublicInputs::<H::Domain> {
+                challenge,
+                commitment: Some(tree.root()),
+                context: Vec::new(),
+            };
+            let priv_inputs = merklepor::PrivateInputs::<H>::new(leaf, &tree);
+            let proof = merklepor::MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs)
+                .expect("proving failed");
+
+            (pub_inputs, proof)
+        };
+
+        // A valid proof for leaf 3 does not verify against the inputs for leaf 5.
+        let (pub_inputs, proof) = prove(3);
+        let (other_inputs, _) = prove(5);
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        PoRCompound::<H>::circuit(&pub_inputs, None, &proof, &pub_params)
+            .unwrap()
+            .synthesize(&mut cs)
+            .expect("circuit synthesis failed");
+        assert!(cs.is_satisfied(), "constraints not satisfied");
+
+        let inputs =
+            PoRCompound::<H>::generate_public_inputs(&pub_inputs, &pub_params, None).unwrap();
+        let other =
+            PoRCompound::<H>::generate_public_inputs(&other_inputs, &pub_params, None).unwrap();
+        assert!(cs.verify(&inputs));
+        assert!(!cs.verify(&other), "proof must be bound to its challenge");
+
+        // Flipping a direction bit, to claim another position with the same siblings, does not
+        // reconstruct the root.
+        let mut auth_path = proof.proof.as_options();
+        auth_path[1] = auth_path[1].map(|(sibling, is_right)| (sibling, !is_right));
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        PoRCircuit::<Bls12, H> {
+            params: &JJ_PARAMS,
+            value: Root::Val(Some(proof.data.into())),
+            auth_path,
+            root: Root::Val(Some(pub_inputs.commitment.unwrap().into())),
+            private: false,
+            context: None,
+            _h: Default::default(),
+        }
+        .synthesize(&mut cs)
+        .expect("circuit synthesis failed");
+        assert!(!cs.is_satisfied(), "mismatched bits must not satisfy");
+        assert_eq!(cs.which_is_unsatisfied(), Some("enforce root is correct"));
+    }
+
+    #[test]
+    fn test_por_binds_context_pedersen() {
+        test_por_binds_context::<PedersenHasher>();
+    }
+
+    #[test]
+    fn test_por_binds_context_blake2s() {
+        test_por_binds_context::<Blake2sHasher>();
+    }
+
+    fn test_por_binds_context<H: Hasher>() {
+        let leaves = 8;
+        let (tree, leaf) = por_fixture::<H>(leaves, 3);
+
+        let pub_params = merklepor::PublicParams {
+            bind_context: true,
+            ..merklepor::PublicParams::new(leaves)
+        };
+        let inputs_for = |context: &[u8]| merklepor::PublicInputs::<H::Domain> {
+            challenge: 3,
+            commitment: Some(tree.root()),
+            context: context.to_vec(),
+        };
+
+        let pub_inputs = inputs_for(b"first context");
+        let priv_inputs = merklepor::PrivateInputs::<H>::new(leaf, &tree);
+        let proof = merklepor::MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs)
+            .expect("proving failed");
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        PoRCompound::<H>::circuit(&pub_inputs, None, &proof, &pub_params)
+            .unwrap()
+            .synthesize(&mut cs)
+            .expect("circuit synthesis failed");
+        assert!(cs.is_satisfied(), "constraints not satisfied");
+
+        let blank = PoRCompound::<H>::blank_circuit(&pub_params);
+        let mut blank_cs = BenchCS::<Bls12>::new();
+        blank.synthesize(&mut blank_cs).unwrap();
+        assert_eq!(cs.num_inputs(), blank_cs.num_inputs());
+
+        let inputs =
+            PoRCompound::<H>::generate_public_inputs(&pub_inputs, &pub_params, None).unwrap();
+        assert_eq!(cs.num_inputs(), inputs.len() + 1);
+        assert!(cs.verify(&inputs));
+
+        // The same proof is not valid in another context, nor without one.
+        for other in &[&b"second context"[..], &[]] {
+            let other_inputs =
+                PoRCompound::<H>::generate_public_inputs(&inputs_for(other), &pub_params, None)
+                    .unwrap();
+            assert!(
+                !cs.verify(&other_inputs),
+                "proof must be bound to its context"
+            );
+        }
+
+        // Params which do not bind a context reject one.
+        let unbound = merklepor::PublicParams::new(leaves);
+        assert!(PoRCompound::<H>::generate_public_inputs(&pub_inputs, &unbound, None).is_err());
+        assert!(PoRCompound::<H>::circuit(&pub_inputs, None, &proof, &unbound).is_err());
+    }
+
+    #[test]
+    fn test_diagnose_unsatisfied() {
+        let (tree, _) = por_fixture::<PedersenHasher>(8, 0);
+
+        // Synthesizes one PoR per challenge, corrupting the auth path of challenge `bad`.
+        let synthesize = |bad: Option<usize>| {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            for (round, &challenge) in [1, 4, 6].iter().enumerate() {
+                let proof = MerkleProof::<PedersenHasher>::new_from_proof(
+                    &tree.gen_proof(challenge).unwrap(),
+                );
+                let mut auth_path = proof.as_options();
+                if bad == Some(round) {
+                    auth_path[0] = Some((Fr::one(), auth_path[0].unwrap().1));
+                }
+
+                PoRCircuit::<Bls12, PedersenHasher>::synthesize(
+                    cs.namespace(|| format!("challenge {}", round)),
+                    &JJ_PARAMS,
+                    Root::Val(Some((*proof.leaf()).into())),
+                    auth_path,
+                    Root::Val(Some(tree.root().into())),
+                    false,
+                )
+                .expect("circuit synthesis failed");
+            }
+
+            cs
+        };
+
+        assert_eq!(diagnose_unsatisfied(&synthesize(None)), None);
+        assert_eq!(
+            diagnose_unsatisfied(&synthesize(Some(1))),
+            Some("root mismatch in challenge 1".to_string())
+        );
+        assert_eq!(
+            diagnose_unsatisfied(&synthesize(Some(2))),
+            Some("root mismatch in challenge 2".to_string())
+        );
+    }
+
+    fn test_por_estimate_prove_cost<H: Hasher>(private: bool) {
+        let leaves = 6;
+        let (tree, leaf) = por_fixture::<H>(leaves, 2);
+
+        let pub_params = merklepor::PublicParams {
+            leaves,
+            private,
+            bind_context: false,
+        };
+        let pub_inputs = merklepor::PublicInputs::<H::Domain> {
+            challenge: 2,
+            commitment: if private { None } else { Some(tree.root()) },
+            context: Vec::new(),
+        };
+        let priv_inputs = merklepor::PrivateInputs::<H>::new(leaf, &tree);
+
+        let proof = merklepor::MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs)
+            .expect("proving failed");
+        let circuit = PoRCompound::<H>::circuit(&pub_inputs, None, &proof, &pub_params).unwrap();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        circuit
+            .synthesize(&mut cs)
+            .expect("circuit synthesis failed");
+
+        let cost = Duration::from_nanos(3);
+        let estimate = merklepor::MerklePoR::<H>::estimate_prove_cost(&pub_params, cost).unwrap();
+        assert_eq!(estimate.constraints, cs.num_constraints());
+        assert_eq!(estimate.est_duration, cost * cs.num_constraints() as u32);
+    }
+
+    #[test]
+    fn test_por_estimate_prove_cost_pedersen() {
+        test_por_estimate_prove_cost::<PedersenHasher>(false);
+        test_por_estimate_prove_cost::<PedersenHasher>(true);
+    }
+
+    #[test]
+    fn test_por_estimate_prove_cost_blake2s() {
+        test_por_estimate_prove_cost::<Blake2sHasher>(false);
+    }
+
     #[ignore] // Slow test – run only when compiled for release.
     #[test]
     fn test_private_por_compound_pedersen() {
@@ -454,7 +858,7 @@ mod tests {
     }
 
     fn private_por_test_compound<H: Hasher>() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
         let leaves = 6;
         let data: Vec<u8> = (0..leaves)
             .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
@@ -466,12 +870,14 @@ mod tests {
             let public_inputs = merklepor::PublicInputs {
                 challenge: i,
                 commitment: None,
+                context: Vec::new(),
             };
 
             let setup_params = compound_proof::SetupParams {
                 vanilla_params: merklepor::SetupParams {
                     leaves,
                     private: true,
+                    bind_context: false,
                 },
                 partitions: None,
             };
@@ -523,7 +929,7 @@ mod tests {
 
     #[test]
     fn test_private_por_input_circuit_with_bls12_381() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let leaves = 6;
 
@@ -543,10 +949,12 @@ mod tests {
             let pub_params = merklepor::PublicParams {
                 leaves,
                 private: true,
+                bind_context: false,
             };
             let pub_inputs = merklepor::PublicInputs {
                 challenge: i,
                 commitment: None,
+                context: Vec::new(),
             };
 
             let priv_inputs = merklepor::PrivateInputs::<PedersenHasher>::new(
@@ -580,8 +988,9 @@ mod tests {
                 params: &JJ_PARAMS,
                 value: Root::Val(Some(proof.data.into())),
                 auth_path: proof.proof.as_options(),
-                root: Root::Val(Some(tree.root().into())),
+                root: Root::Val(Some(tree.root_fr())),
                 private: true,
+                context: None,
                 _h: Default::default(),
             };
 
@@ -71,6 +71,7 @@ where
         let por_pub_params = merklepor::PublicParams {
             leaves: (pub_params.sector_size as usize / NODE_SIZE),
             private: true,
+            bind_context: false,
         };
 
         ensure!(
@@ -84,6 +85,7 @@ where
             let por_pub_inputs = merklepor::PublicInputs {
                 commitment: None,
                 challenge: challenge.leaf as usize,
+                context: Vec::new(),
             };
             let por_inputs =
                 PoRCompound::<H>::generate_public_inputs(&por_pub_inputs, &por_pub_params, None)?;
@@ -274,8 +276,7 @@ mod tests {
     use std::collections::BTreeMap;
 
     use ff::Field;
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
 
     use crate::circuit::test::*;
     use crate::compound_proof;
@@ -287,10 +288,11 @@ mod tests {
     use crate::rational_post::{self, derive_challenges, RationalPoSt};
     use crate::sector::OrderedSectorSet;
     use crate::stacked::hash::hash2;
+    use crate::test_helper::seeded_rng;
 
     #[test]
     fn test_rational_post_circuit_with_bls12_381() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let leaves = 32;
         let sector_size = leaves * 32;
@@ -421,7 +423,7 @@ mod tests {
     #[ignore] // Slow test – run only when compiled for release.
     #[test]
     fn rational_post_test_compound() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let leaves = 32;
         let sector_size = leaves * 32;
@@ -237,6 +237,7 @@ fn generate_inclusion_inputs<H: Hasher>(
     let pub_inputs = merklepor::PublicInputs::<H::Domain> {
         challenge: c,
         commitment: None,
+        context: Vec::new(),
     };
 
     PoRCompound::<H>::generate_public_inputs(&pub_inputs, por_params, k)
@@ -265,12 +266,14 @@ impl<'a, H: 'static + Hasher, G: 'static + Hasher>
         let window_por_params = merklepor::MerklePoR::<H>::setup(&merklepor::SetupParams {
             leaves: window_graph.size(),
             private: true,
+            bind_context: false,
         })
         .expect("setup failed");
 
         let wrapper_por_params = merklepor::MerklePoR::<H>::setup(&merklepor::SetupParams {
             leaves: wrapper_graph.size(),
             private: true,
+            bind_context: false,
         })
         .expect("setup failed");
 
@@ -433,10 +436,10 @@ mod tests {
     use crate::stacked::{
         ChallengeRequirements, PrivateInputs, PublicInputs, SetupParams, StackedConfig, EXP_DEGREE,
     };
+    use crate::test_helper::seeded_rng;
 
     use ff::Field;
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
 
     #[test]
     fn stacked_input_circuit() {
@@ -450,7 +453,7 @@ mod tests {
         let num_layers = 2;
         let config = StackedConfig::new(num_layers, 2, 3).unwrap();
 
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let replica_id: Fr = Fr::random(rng);
         let data: Vec<u8> = (0..nodes)
@@ -630,7 +633,7 @@ mod tests {
         let config = StackedConfig::new(num_layers, 3, 2).unwrap();
         let partition_count = 1;
 
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let replica_id: Fr = Fr::random(rng);
         let data: Vec<u8> = (0..nodes)
@@ -95,18 +95,17 @@ mod tests {
     use bellperson::gadgets::boolean::Boolean;
     use bellperson::ConstraintSystem;
     use paired::bls12_381::{Bls12, Fr};
-    use rand::SeedableRng;
-    use rand_xorshift::XorShiftRng;
 
     use crate::circuit::test::TestConstraintSystem;
     use crate::crypto::pedersen::JJ_PARAMS;
     use crate::fr32::fr_into_bytes;
     use crate::stacked_old::hash::hash2 as vanilla_hash2;
+    use crate::test_helper::seeded_rng;
     use crate::util::bytes_into_boolean_vec;
 
     #[test]
     fn test_hash2_circuit() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         for _ in 0..10 {
             let mut cs = TestConstraintSystem::<Bls12>::new();
@@ -220,12 +220,14 @@ impl<'a, H: 'static + Hasher, G: 'static + Hasher>
         let por_params = merklepor::MerklePoR::<H>::setup(&merklepor::SetupParams {
             leaves: graph.size(),
             private: true,
+            bind_context: false,
         })?;
 
         let generate_inclusion_inputs = |c: usize| {
             let pub_inputs = merklepor::PublicInputs::<H::Domain> {
                 challenge: c,
                 commitment: None,
+                context: Vec::new(),
             };
 
             PoRCompound::<H>::generate_public_inputs(&pub_inputs, &por_params, k)
@@ -332,10 +334,10 @@ mod tests {
         ChallengeRequirements, LayerChallenges, PrivateInputs, PublicInputs, SetupParams,
         EXP_DEGREE,
     };
+    use crate::test_helper::seeded_rng;
 
     use ff::Field;
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
 
     #[test]
     fn stacked_input_circuit_with_bls12_381() {
@@ -345,7 +347,7 @@ mod tests {
         let num_layers = 2;
         let layer_challenges = LayerChallenges::new(num_layers, 1);
 
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let replica_id: Fr = Fr::random(rng);
         let data: Vec<u8> = (0..nodes)
@@ -503,7 +505,7 @@ mod tests {
         let layer_challenges = LayerChallenges::new(num_layers, 3);
         let partition_count = 1;
 
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let replica_id: Fr = Fr::random(rng);
         let data: Vec<u8> = (0..nodes)
@@ -2,6 +2,8 @@ use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 
+#[cfg(debug_assertions)]
+use bellperson::Circuit;
 use bellperson::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
 use blake2s_simd::State as Blake2s;
 use byteorder::{BigEndian, ByteOrder};
@@ -277,6 +279,10 @@ impl<E: Engine> TestConstraintSystem<E> {
         self.inputs.len()
     }
 
+    pub fn num_aux(&self) -> usize {
+        self.aux.len()
+    }
+
     pub fn get_input(&mut self, index: usize, path: &str) -> E::Fr {
         let (assignment, name) = self.inputs[index].clone();
 
@@ -412,6 +418,47 @@ impl<E: Engine> ConstraintSystem<E> for TestConstraintSystem<E> {
     }
 }
 
+/// Describes how the public inputs allocated by a circuit diverge from the expected ones.
+#[cfg(debug_assertions)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputMismatch {
+    /// The circuit allocated a different number of public inputs (excluding `ONE`).
+    Count { expected: usize, actual: usize },
+    /// The first expected input (0-indexed, excluding `ONE`) that differs, and the name of the
+    /// variable the circuit allocated at that position.
+    Value { index: usize, name: String },
+}
+
+/// Synthesizes `circuit` and compares the public inputs it allocates against `expected_inputs`,
+/// element by element, in the form they are passed to the verifier (i.e. without `ONE`).
+/// Returns the first mismatch, if any. Useful to debug proofs which fail to verify because the
+/// public inputs were generated in the wrong order.
+#[cfg(debug_assertions)]
+pub fn debug_verify_inputs<E: Engine, C: Circuit<E>>(
+    circuit: C,
+    expected_inputs: &[E::Fr],
+) -> Result<Option<InputMismatch>, SynthesisError> {
+    let mut cs = TestConstraintSystem::<E>::new();
+    circuit.synthesize(&mut cs)?;
+
+    let actual = &cs.get_inputs()[1..];
+    if actual.len() != expected_inputs.len() {
+        return Ok(Some(InputMismatch::Count {
+            expected: expected_inputs.len(),
+            actual: actual.len(),
+        }));
+    }
+
+    Ok(actual
+        .iter()
+        .zip(expected_inputs.iter())
+        .position(|((a, _), b)| a != b)
+        .map(|index| InputMismatch::Value {
+            index,
+            name: actual[index].1.clone(),
+        }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -476,4 +523,53 @@ mod tests {
 
         assert!(cs.get("test1/test2/hehe") == Fr::one());
     }
+
+    #[cfg(debug_assertions)]
+    struct InputsCircuit(Vec<paired::bls12_381::Fr>);
+
+    #[cfg(debug_assertions)]
+    impl Circuit<paired::bls12_381::Bls12> for InputsCircuit {
+        fn synthesize<CS: ConstraintSystem<paired::bls12_381::Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            for (i, val) in self.0.into_iter().enumerate() {
+                cs.alloc_input(|| format!("input {}", i), || Ok(val))?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_debug_verify_inputs() {
+        use paired::bls12_381::Fr;
+
+        let inputs: Vec<Fr> = (1..4)
+            .map(|i| Fr::from_str(&i.to_string()).unwrap())
+            .collect();
+
+        assert_eq!(
+            debug_verify_inputs(InputsCircuit(inputs.clone()), &inputs).unwrap(),
+            None
+        );
+
+        let permuted = vec![inputs[0], inputs[2], inputs[1]];
+        assert_eq!(
+            debug_verify_inputs(InputsCircuit(inputs.clone()), &permuted).unwrap(),
+            Some(InputMismatch::Value {
+                index: 1,
+                name: "input 1".into()
+            })
+        );
+
+        assert_eq!(
+            debug_verify_inputs(InputsCircuit(inputs.clone()), &inputs[..2]).unwrap(),
+            Some(InputMismatch::Count {
+                expected: 2,
+                actual: 3
+            })
+        );
+    }
 }
@@ -160,12 +160,13 @@ impl UInt64 {
 mod test {
     use super::*;
 
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
+
+    use crate::test_helper::seeded_rng;
 
     #[test]
     fn test_uint64_from_bits_be() {
-        let mut rng = XorShiftRng::from_seed(crate::TEST_SEED);
+        let mut rng = seeded_rng();
 
         for _ in 0..1000 {
             let v = (0..64)
@@ -197,7 +198,7 @@ mod test {
 
     #[test]
     fn test_uint64_from_bits() {
-        let mut rng = XorShiftRng::from_seed(crate::TEST_SEED);
+        let mut rng = seeded_rng();
 
         for _ in 0..1000 {
             let v = (0..64)
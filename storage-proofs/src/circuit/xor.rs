@@ -32,16 +32,16 @@ mod tests {
     use super::xor;
     use crate::circuit::test::TestConstraintSystem;
     use crate::crypto;
+    use crate::test_helper::seeded_rng;
     use crate::util::{bits_to_bytes, bytes_into_boolean_vec};
     use bellperson::gadgets::boolean::Boolean;
     use bellperson::ConstraintSystem;
     use paired::bls12_381::Bls12;
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
 
     #[test]
     fn test_xor_input_circut() {
-        let mut rng = XorShiftRng::from_seed(crate::TEST_SEED);
+        let mut rng = seeded_rng();
 
         for i in 0..10 {
             let mut cs = TestConstraintSystem::<Bls12>::new();
@@ -6,6 +6,7 @@ use fil_sapling_crypto::jubjub::JubjubEngine;
 use log::info;
 use rand::{rngs::OsRng, RngCore};
 
+use crate::cancel::CancellationToken;
 use crate::circuit::multi_proof::MultiProof;
 use crate::error::Result;
 use crate::parameter_cache::{CacheableParameters, ParameterSetMetadata};
@@ -71,6 +72,27 @@ where
         priv_in: &S::PrivateInputs,
         groth_params: &'b groth16::Parameters<E>,
     ) -> Result<MultiProof<'b, E>>
+    where
+        E::Params: Sync,
+    {
+        Self::prove_cancellable(
+            pub_params,
+            pub_in,
+            priv_in,
+            groth_params,
+            &CancellationToken::new(),
+        )
+    }
+
+    /// Like prove, but checks `cancel` before the vanilla proofs and before each partition's
+    /// circuit proof, returning `Error::Cancelled` as soon as it is set.
+    fn prove_cancellable<'b>(
+        pub_params: &PublicParams<'a, S>,
+        pub_in: &S::PublicInputs,
+        priv_in: &S::PrivateInputs,
+        groth_params: &'b groth16::Parameters<E>,
+        cancel: &CancellationToken,
+    ) -> Result<MultiProof<'b, E>>
     where
         E::Params: Sync,
     {
@@ -80,6 +102,7 @@ where
         // This will always run at least once, since there cannot be zero partitions.
         ensure!(partition_count > 0, "There must be partitions");
 
+        cancel.check()?;
         info!("vanilla_proof:start");
         let vanilla_proofs =
             S::prove_all_partitions(&pub_params.vanilla_params, &pub_in, priv_in, partitions)?;
@@ -101,6 +124,7 @@ where
             vanilla_proofs
                 .par_iter()
                 .map(|vanilla_proof| {
+                    cancel.check()?;
                     Self::circuit_proof(
                         pub_in,
                         &vanilla_proof,
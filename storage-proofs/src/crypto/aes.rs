@@ -27,12 +27,13 @@ pub fn decode(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
+
+    use crate::test_helper::seeded_rng;
 
     #[test]
     fn test_aes() {
-        let mut rng = XorShiftRng::from_seed(crate::TEST_SEED);
+        let mut rng = seeded_rng();
 
         for i in 0..10 {
             let key: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
@@ -1,20 +1,57 @@
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use ff::PrimeField;
 use paired::bls12_381::Fr;
 use sha2::{Digest, Sha256};
 
 use crate::fr32::bytes_into_fr_repr_safe;
 
+/// Parameters of the key derivation function, for experimenting with other field encodings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfConfig {
+    /// How many of the most significant bits of the digest are cleared. At least 2 are needed to
+    /// stay in `Fr`, which is also the default.
+    pub mask_high_bits: u8,
+}
+
+impl Default for KdfConfig {
+    fn default() -> Self {
+        KdfConfig { mask_high_bits: 2 }
+    }
+}
+
+impl KdfConfig {
+    /// How many of the least significant bits of the digest are kept.
+    pub fn kept_bits(&self) -> usize {
+        256 - self.mask_high_bits as usize
+    }
+}
+
 /// Key derivation function, based on pedersen hashing.
-pub fn create_label(data: &[u8], _m: usize) -> Result<Fr> {
-    let hash = Sha256::digest(data);
+pub fn create_label(data: &[u8], m: usize) -> Result<Fr> {
+    create_label_with(data, m, &KdfConfig::default())
+}
+
+/// Like `create_label`, but clears as many high bits as `config` asks for.
+pub fn create_label_with(data: &[u8], _m: usize, config: &KdfConfig) -> Result<Fr> {
+    ensure!(
+        config.mask_high_bits >= 2,
+        "at least 2 high bits must be masked, got {}",
+        config.mask_high_bits
+    );
+
+    let mut hash = Sha256::digest(data);
+    // The digest is read as little endian, so the high bits are at the end.
+    for bit in config.kept_bits()..256 {
+        hash[bit / 8] &= !(1 << (bit % 8));
+    }
+
     Ok(Fr::from_repr(bytes_into_fr_repr_safe(hash.as_ref()))?)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::create_label;
-    use ff::PrimeField;
+    use super::*;
+    use ff::PrimeFieldRepr;
     use paired::bls12_381::{Fr, FrRepr};
 
     #[test]
@@ -34,4 +71,36 @@ mod tests {
         let res = create_label(&data, m).unwrap();
         assert_eq!(res, expected);
     }
+
+    #[test]
+    fn create_label_mask_widths() {
+        // The digest of this input has bits set in every masked range below.
+        let data = vec![1u8; 64];
+
+        let default = create_label(&data, 1).unwrap();
+        assert_eq!(
+            create_label_with(&data, 1, &KdfConfig::default()).unwrap(),
+            default
+        );
+
+        let mut seen = vec![default];
+        for mask_high_bits in &[3, 8, 64] {
+            let config = KdfConfig {
+                mask_high_bits: *mask_high_bits,
+            };
+            let label = create_label_with(&data, 1, &config).unwrap();
+
+            // The masked bits are clear.
+            let repr = label.into_repr();
+            assert!(repr.num_bits() as usize <= config.kept_bits());
+            assert!(
+                !seen.contains(&label),
+                "mask width {} made no difference",
+                mask_high_bits
+            );
+            seen.push(label);
+        }
+
+        assert!(create_label_with(&data, 1, &KdfConfig { mask_high_bits: 1 }).is_err());
+    }
 }
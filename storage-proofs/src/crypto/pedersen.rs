@@ -1,13 +1,14 @@
 use anyhow::{ensure, Context};
-use ff::PrimeFieldRepr;
-use fil_sapling_crypto::jubjub::JubjubBls12;
+use ff::{PrimeField, PrimeFieldRepr};
+use fil_sapling_crypto::jubjub::{JubjubBls12, JubjubEngine};
 use fil_sapling_crypto::pedersen_hash::Personalization;
 use lazy_static::lazy_static;
 use paired::bls12_381::{Bls12, Fr, FrRepr};
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::fr32::bytes_into_frs;
 use crate::settings;
+use crate::util::NODE_SIZE;
 
 lazy_static! {
     pub static ref JJ_PARAMS: JubjubBls12 = JubjubBls12::new_with_window_size(
@@ -21,6 +22,12 @@ lazy_static! {
 pub const PEDERSEN_BLOCK_SIZE: usize = 256;
 pub const PEDERSEN_BLOCK_BYTES: usize = PEDERSEN_BLOCK_SIZE / 8;
 
+// A merkle tree node is exactly one pedersen block, the hashers and circuits rely on this when
+// splitting data into nodes. `assert!` is not available in constants on our toolchain, so a false
+// condition is turned into an underflowing array length, which fails the build.
+const _: [(); (PEDERSEN_BLOCK_BYTES == NODE_SIZE) as usize - 1] = [];
+const _: [(); (PEDERSEN_BLOCK_SIZE == 8 * NODE_SIZE) as usize - 1] = [];
+
 pub fn pedersen(data: &[u8]) -> Fr {
     pedersen_bits(Bits::new(data))
 }
@@ -42,43 +49,232 @@ pub fn pedersen_md_no_padding(data: &[u8]) -> Fr {
     pedersen_md_no_padding_bits(Bits::new(data))
 }
 
-pub fn pedersen_md_no_padding_bits<T: AsRef<[u8]>, S: Iterator<Item = T>>(
-    mut data: Bits<T, S>,
+/// Like `pedersen_md_no_padding`, but hashes every block with the given personalization, so that
+/// digests over different kinds of data can be domain separated.
+pub fn pedersen_md_no_padding_with(data: &[u8], personalization: Personalization) -> Fr {
+    pedersen_md_no_padding_bits_with(Bits::new(data), personalization)
+}
+
+/// Like `pedersen_md_no_padding`, but validates that `data` is at least two blocks long and a
+/// multiple of the block size, instead of silently hashing malformed input.
+pub fn try_pedersen_md_no_padding(data: &[u8]) -> Result<Fr> {
+    ensure!(
+        data.len() >= 2 * PEDERSEN_BLOCK_BYTES,
+        Error::InputTooShort {
+            got: data.len(),
+            min: 2 * PEDERSEN_BLOCK_BYTES,
+        }
+    );
+    ensure!(
+        data.len() % PEDERSEN_BLOCK_BYTES == 0,
+        Error::InputNotBlockAligned {
+            got: data.len(),
+            block: PEDERSEN_BLOCK_BYTES,
+        }
+    );
+
+    Ok(pedersen_md_no_padding(data))
+}
+
+pub fn pedersen_md_no_padding_bits<T: AsRef<[u8]>, S: Iterator<Item = T>>(data: Bits<T, S>) -> Fr {
+    pedersen_md_no_padding_bits_with(data, Personalization::None)
+}
+
+fn pedersen_md_no_padding_bits_with<T: AsRef<[u8]>, S: Iterator<Item = T>>(
+    data: Bits<T, S>,
+    personalization: Personalization,
 ) -> Fr {
     let mut cur = Vec::with_capacity(PEDERSEN_BLOCK_SIZE);
 
+    pedersen_md_no_padding_into(data, personalization, &mut cur)
+}
+
+/// `pedersen_md_no_padding` with `cur` as scratch space for the intermediate digests.
+fn pedersen_md_no_padding_into<T: AsRef<[u8]>, S: Iterator<Item = T>>(
+    data: Bits<T, S>,
+    personalization: Personalization,
+    cur: &mut Vec<u8>,
+) -> Fr {
+    pedersen_md_blocks(data, &Personalized(personalization), cur)
+}
+
+/// A compression function the Merkle-Damgard loop in `pedersen_md_blocks` can be run over.
+trait MdCompression {
+    type Output;
+
+    fn compress<I: IntoIterator<Item = bool>>(&self, bits: I) -> Self::Output;
+
+    /// Appends the bytes of `out` that are chained into the next block to `cur`.
+    fn write_chaining_value(out: &Self::Output, cur: &mut Vec<u8>);
+}
+
+/// `pedersen_compression_with` under a fixed personalization.
+struct Personalized(Personalization);
+
+impl MdCompression for Personalized {
+    type Output = Fr;
+
+    fn compress<I: IntoIterator<Item = bool>>(&self, bits: I) -> Fr {
+        pedersen_compression_with(bits, self.0)
+    }
+
+    fn write_chaining_value(out: &Fr, cur: &mut Vec<u8>) {
+        out.into_repr()
+            .write_le(cur)
+            .expect("failed to write result hash");
+    }
+}
+
+/// `pedersen_compression_xy`, chaining only the x-coordinate.
+struct PointCompression;
+
+impl MdCompression for PointCompression {
+    type Output = (Fr, Fr);
+
+    fn compress<I: IntoIterator<Item = bool>>(&self, bits: I) -> (Fr, Fr) {
+        pedersen_compression_xy(bits)
+    }
+
+    fn write_chaining_value(out: &(Fr, Fr), cur: &mut Vec<u8>) {
+        Personalized::write_chaining_value(&out.0, cur)
+    }
+}
+
+/// `pedersen_compression_generic` over the given params.
+struct GenericCompression<'a, E: JubjubEngine>(&'a E::Params);
+
+impl<'a, E: JubjubEngine> MdCompression for GenericCompression<'a, E> {
+    type Output = E::Fr;
+
+    fn compress<I: IntoIterator<Item = bool>>(&self, bits: I) -> E::Fr {
+        pedersen_compression_generic::<E, _>(bits, self.0)
+    }
+
+    fn write_chaining_value(out: &E::Fr, cur: &mut Vec<u8>) {
+        out.into_repr()
+            .write_le(cur)
+            .expect("failed to write result hash");
+    }
+}
+
+/// The Merkle-Damgard loop shared by all `pedersen_md_*` variants: compresses the first two blocks,
+/// then each following block together with the chaining value of the previous digest. `cur` is
+/// scratch space for the chaining value, it is cleared first, so it can be reused across calls.
+fn pedersen_md_blocks<C: MdCompression, T: AsRef<[u8]>, S: Iterator<Item = T>>(
+    mut data: Bits<T, S>,
+    compression: &C,
+    cur: &mut Vec<u8>,
+) -> C::Output {
     // hash the first two blocks
-    let first = pedersen_compression_bits(data.ref_take(2 * PEDERSEN_BLOCK_SIZE));
-    first
-        .write_le(&mut cur)
-        .expect("failed to write result hash");
+    let mut out = compression.compress(data.ref_take(2 * PEDERSEN_BLOCK_SIZE));
 
     while !data.is_done() {
+        cur.truncate(0);
+        C::write_chaining_value(&out, cur);
+
         let r = data.ref_take(PEDERSEN_BLOCK_SIZE);
-        let x = pedersen_compression_bits(Bits::new(&cur).chain(r));
+        out = compression.compress(Bits::new(&*cur).chain(r));
+    }
 
-        cur.truncate(0);
-        x.write_le(&mut cur).expect("failed to write result hash");
+    out
+}
+
+/// Computes `pedersen_md_no_padding` for many inputs in a row, reusing the buffer for the
+/// intermediate digests instead of allocating it on every call.
+#[derive(Debug, Clone)]
+pub struct PedersenMdHasher {
+    cur: Vec<u8>,
+}
+
+impl Default for PedersenMdHasher {
+    fn default() -> Self {
+        PedersenMdHasher {
+            cur: Vec::with_capacity(PEDERSEN_BLOCK_SIZE),
+        }
+    }
+}
+
+impl PedersenMdHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Same as `pedersen_md_no_padding(data)`, no state is carried over from previous calls.
+    pub fn hash(&mut self, data: &[u8]) -> Fr {
+        pedersen_md_no_padding_into(Bits::new(data), Personalization::None, &mut self.cur)
     }
+}
+
+/// Same construction as `pedersen_md_no_padding`, but returns both coordinates of the resulting
+/// curve point.
+///
+/// The x-coordinate alone only determines the point up to the sign of y. This is safe whenever the
+/// result is used as a hash digest (as everywhere in this crate), but protocols that need to commit
+/// to the point itself must use this variant.
+pub fn pedersen_md_point(data: &[u8]) -> (Fr, Fr) {
+    let mut cur = Vec::with_capacity(PEDERSEN_BLOCK_SIZE);
 
-    let frs = bytes_into_frs::<Bls12>(&cur).expect("pedersen must generate valid fr elements");
-    assert_eq!(frs.len(), 1);
-    frs[0]
+    pedersen_md_blocks(Bits::new(data), &PointCompression, &mut cur)
 }
 
 fn pedersen_compression_bits<T>(bits: T) -> FrRepr
+where
+    T: IntoIterator<Item = bool>,
+{
+    pedersen_compression_with(bits, Personalization::None).into()
+}
+
+/// The pedersen compression function under the given personalization, returning the x-coordinate
+/// of the resulting point.
+pub fn pedersen_compression_with<T>(bits: T, personalization: Personalization) -> Fr
+where
+    T: IntoIterator<Item = bool>,
+{
+    pedersen_compression_xy_with(bits, personalization).0
+}
+
+fn pedersen_compression_xy<T>(bits: T) -> (Fr, Fr)
+where
+    T: IntoIterator<Item = bool>,
+{
+    pedersen_compression_xy_with(bits, Personalization::None)
+}
+
+fn pedersen_compression_xy_with<T>(bits: T, personalization: Personalization) -> (Fr, Fr)
 where
     T: IntoIterator<Item = bool>,
 {
     let digest = if cfg!(target_arch = "x86_64") {
         use fil_sapling_crypto::pedersen_hash::pedersen_hash_bls12_381_with_precomp;
-        pedersen_hash_bls12_381_with_precomp::<_>(Personalization::None, bits, &JJ_PARAMS)
+        pedersen_hash_bls12_381_with_precomp::<_>(personalization, bits, &JJ_PARAMS)
     } else {
         use fil_sapling_crypto::pedersen_hash::pedersen_hash;
-        pedersen_hash::<Bls12, _>(Personalization::None, bits, &JJ_PARAMS)
+        pedersen_hash::<Bls12, _>(personalization, bits, &JJ_PARAMS)
     };
 
-    digest.into_xy().0.into()
+    digest.into_xy()
+}
+
+/// Engine generic version of `pedersen_md_no_padding`, using the passed in params instead of
+/// `JJ_PARAMS`. Prefer the BLS12-381 specific functions where possible, as they make use of
+/// precomputed tables.
+pub fn pedersen_md_no_padding_generic<E: JubjubEngine>(data: &[u8], params: &E::Params) -> E::Fr {
+    let mut cur = Vec::with_capacity(PEDERSEN_BLOCK_SIZE);
+
+    pedersen_md_blocks(Bits::new(data), &GenericCompression::<E>(params), &mut cur)
+}
+
+/// Engine generic version of the pedersen compression function, returning the x-coordinate of the
+/// resulting point.
+pub fn pedersen_compression_generic<E: JubjubEngine, T: IntoIterator<Item = bool>>(
+    bits: T,
+    params: &E::Params,
+) -> E::Fr {
+    use fil_sapling_crypto::pedersen_hash::pedersen_hash;
+
+    pedersen_hash::<E, _>(Personalization::None, bits, params)
+        .into_xy()
+        .0
 }
 
 #[derive(Debug, Clone)]
@@ -295,12 +491,12 @@ impl<T: AsRef<[u8]>, S: Iterator<Item = T>> Iterator for Bits<T, S> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_helper::seeded_rng;
     use crate::util::bytes_into_bits;
     use bitvec::{self, BitVec};
     use ff::Field;
     use paired::bls12_381::Fr;
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
 
     #[test]
     fn test_bit_vec_le() {
@@ -333,7 +529,7 @@ mod tests {
 
     #[test]
     fn test_pedersen_md_no_padding() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         for i in 2..5 {
             let x: Vec<u8> = (0..i * 32).map(|_| rng.gen()).collect();
@@ -342,6 +538,109 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pedersen_personalization() {
+        let rng = &mut seeded_rng();
+        let x: Vec<u8> = (0..3 * PEDERSEN_BLOCK_BYTES).map(|_| rng.gen()).collect();
+
+        assert_eq!(
+            pedersen_md_no_padding_with(&x, Personalization::None),
+            pedersen_md_no_padding(&x)
+        );
+        assert_ne!(
+            pedersen_md_no_padding_with(&x, Personalization::NoteCommitment),
+            pedersen_md_no_padding_with(&x, Personalization::None)
+        );
+
+        assert_ne!(
+            pedersen_compression_with(Bits::new(&x), Personalization::MerkleTree(0)),
+            pedersen_compression_with(Bits::new(&x), Personalization::None)
+        );
+    }
+
+    #[test]
+    fn test_try_pedersen_md_no_padding() {
+        let rng = &mut seeded_rng();
+
+        let x: Vec<u8> = (0..3 * PEDERSEN_BLOCK_BYTES).map(|_| rng.gen()).collect();
+        assert_eq!(
+            try_pedersen_md_no_padding(&x).unwrap(),
+            pedersen_md_no_padding(&x)
+        );
+
+        for len in &[0, PEDERSEN_BLOCK_BYTES] {
+            match try_pedersen_md_no_padding(&x[..*len])
+                .unwrap_err()
+                .downcast::<Error>()
+            {
+                Ok(Error::InputTooShort { got, min }) => {
+                    assert_eq!(got, *len);
+                    assert_eq!(min, 2 * PEDERSEN_BLOCK_BYTES);
+                }
+                res => panic!("unexpected result: {:?}", res),
+            }
+        }
+
+        let len = 2 * PEDERSEN_BLOCK_BYTES + 1;
+        match try_pedersen_md_no_padding(&x[..len])
+            .unwrap_err()
+            .downcast::<Error>()
+        {
+            Ok(Error::InputNotBlockAligned { got, block }) => {
+                assert_eq!(got, len);
+                assert_eq!(block, PEDERSEN_BLOCK_BYTES);
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_pedersen_md_hasher() {
+        let rng = &mut seeded_rng();
+        let mut hasher = PedersenMdHasher::new();
+
+        // Varying lengths, so a longer input is followed by a shorter one.
+        for i in &[4, 2, 3, 2, 5, 2] {
+            let x: Vec<u8> = (0..i * 32).map(|_| rng.gen()).collect();
+
+            assert_eq!(hasher.hash(&x), pedersen_md_no_padding(&x));
+            // Hashing the same input again gives the same result.
+            assert_eq!(hasher.hash(&x), pedersen_md_no_padding(&x));
+        }
+    }
+
+    #[test]
+    fn test_pedersen_md_point() {
+        let rng = &mut seeded_rng();
+
+        for i in 2..5 {
+            let x: Vec<u8> = (0..i * 32).map(|_| rng.gen()).collect();
+            let (px, py) = pedersen_md_point(x.as_slice());
+
+            assert_eq!(px, pedersen_md_no_padding(x.as_slice()));
+            assert_ne!(py, Fr::zero());
+        }
+    }
+
+    #[test]
+    fn test_pedersen_generic() {
+        let rng = &mut seeded_rng();
+
+        for i in 2..5 {
+            let x: Vec<u8> = (0..i * 32).map(|_| rng.gen()).collect();
+
+            assert_eq!(
+                pedersen_md_no_padding_generic::<Bls12>(x.as_slice(), &JJ_PARAMS),
+                pedersen_md_no_padding(x.as_slice())
+            );
+            assert_eq!(
+                pedersen_compression_generic::<Bls12, _>(Bits::new(x.as_slice()), &JJ_PARAMS)
+                    .into_repr(),
+                pedersen_compression_bits(Bits::new(x.as_slice()))
+            );
+        }
+    }
+
     #[test]
     fn test_bits_collect() {
         let bytes = b"hello";
@@ -385,7 +684,7 @@ mod tests {
 
     #[test]
     fn test_pedersen_hasher_update() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         for _ in 2..5 {
             let x: Vec<Vec<u8>> = (0..5)
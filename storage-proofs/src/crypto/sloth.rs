@@ -2,6 +2,8 @@ use ff::Field;
 use paired::Engine;
 
 /// Sloth based encoding.
+///
+/// A zero key is not special cased: encoding with it is the identity, as is decoding.
 #[inline]
 pub fn encode<E: Engine>(key: &E::Fr, plaintext: &E::Fr) -> E::Fr {
     let mut ciphertext = *plaintext;
@@ -25,6 +27,9 @@ mod tests {
     use super::*;
     use ff::PrimeField;
     use paired::bls12_381::{Bls12, Fr, FrRepr};
+
+    use crate::hasher::{Blake2sHasher, Domain, Hasher, PedersenHasher, Sha256Hasher};
+    use crate::test_helper::seeded_rng;
     use proptest::{prop_compose, proptest, proptest_helper};
 
     // the modulus from `bls12_381::Fr`
@@ -57,6 +62,75 @@ mod tests {
         assert_ne!(plaintext, decrypted);
     }
 
+    #[test]
+    fn sloth_bls_12_zero_key() {
+        let key = Fr::zero();
+        let plaintext = Fr::from_str("123456789").unwrap();
+        let ciphertext = encode::<Bls12>(&key, &plaintext);
+        assert_eq!(ciphertext, plaintext);
+        assert_eq!(decode::<Bls12>(&key, &ciphertext), plaintext);
+    }
+
+    fn sloth_zero_key<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let key = H::Domain::default();
+        let plaintext = H::Domain::random(rng);
+
+        let ciphertext = H::sloth_encode(&key, &plaintext).unwrap();
+        assert_eq!(ciphertext, plaintext);
+        assert_eq!(H::sloth_decode(&key, &ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn sloth_zero_key_pedersen() {
+        sloth_zero_key::<PedersenHasher>();
+    }
+
+    #[test]
+    fn sloth_zero_key_sha256() {
+        sloth_zero_key::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn sloth_zero_key_blake2s() {
+        sloth_zero_key::<Blake2sHasher>();
+    }
+
+    fn sloth_domain_matches_fr<H: Hasher>() {
+        let rng = &mut seeded_rng();
+
+        for _ in 0..10 {
+            let key = H::Domain::random(rng);
+            let plaintext = H::Domain::random(rng);
+
+            let ciphertext = encode::<Bls12>(&key.into(), &plaintext.into());
+            let ciphertext_domain: H::Domain = ciphertext.into();
+            assert_eq!(
+                H::sloth_encode(&key, &plaintext).unwrap(),
+                ciphertext_domain
+            );
+
+            let decoded: H::Domain = decode::<Bls12>(&key.into(), &ciphertext).into();
+            assert_eq!(H::sloth_decode(&key, &ciphertext_domain).unwrap(), decoded);
+            assert_eq!(decoded, plaintext);
+        }
+    }
+
+    #[test]
+    fn sloth_domain_matches_fr_pedersen() {
+        sloth_domain_matches_fr::<PedersenHasher>();
+    }
+
+    #[test]
+    fn sloth_domain_matches_fr_sha256() {
+        sloth_domain_matches_fr::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn sloth_domain_matches_fr_blake2s() {
+        sloth_domain_matches_fr::<Blake2sHasher>();
+    }
+
     prop_compose! {
         fn arb_fr()(a in 0..MODULUS[0], b in 0..MODULUS[1], c in 0..MODULUS[2], d in 0..MODULUS[3]) -> Fr {
             Fr::from_repr(FrRepr([a, b, c, d])).unwrap()
@@ -25,12 +25,13 @@ fn xor(key: &[u8], input: &[u8]) -> Result<Vec<u8>> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
+
+    use crate::test_helper::seeded_rng;
 
     #[test]
     fn test_xor() {
-        let mut rng = XorShiftRng::from_seed(crate::TEST_SEED);
+        let mut rng = seeded_rng();
 
         for i in 0..10 {
             let key: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
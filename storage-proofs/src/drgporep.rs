@@ -7,8 +7,8 @@ use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+use crate::cancel::CancellationToken;
 use crate::drgraph::Graph;
-use crate::encode;
 use crate::error::Result;
 use crate::fr32::bytes_into_fr_repr_safe;
 use crate::hasher::{Domain, Hasher};
@@ -97,7 +97,7 @@ where
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DataProof<H: Hasher> {
     #[serde(bound(
         serialize = "MerkleProof<H>: Serialize",
@@ -406,7 +406,7 @@ where
                 bytes_into_fr_repr_safe(hash.as_ref()).into()
             };
 
-            let unsealed = encode::decode(key, proof.replica_nodes[i].data);
+            let unsealed = decode_node::<H>(&key, &proof.replica_nodes[i].data)?;
 
             if unsealed != proof.nodes[i].data {
                 return Ok(false);
@@ -443,26 +443,7 @@ where
             None => pp.graph.merkle_tree(data)?,
         };
 
-        let graph = &pp.graph;
-        // encode(&pp.graph, replica_id, data, None)?;
-        // Because a node always follows all of its parents in the data,
-        // the nodes are by definition already topologically sorted.
-        // Therefore, if we simply traverse the data in order, encoding each node in place,
-        // we can always get each parent's encodings with a simple lookup --
-        // since we will already have encoded the parent earlier in the traversal.
-
-        let mut parents = vec![0; graph.degree()];
-        for node in 0..graph.size() {
-            graph.parents(node, &mut parents)?;
-            let key = graph.create_key(replica_id, node, &parents, data, None)?;
-            let start = data_at_node_offset(node);
-            let end = start + NODE_SIZE;
-
-            let node_data = H::Domain::try_from_bytes(&data[start..end])?;
-            let encoded = H::sloth_encode(key.as_ref(), &node_data)?;
-
-            encoded.write_bytes(&mut data[start..end])?;
-        }
+        encode(&pp.graph, replica_id, data)?;
 
         let comm_d = tree_d.root();
         let tree_r = pp.graph.merkle_tree(data)?;
@@ -494,6 +475,63 @@ where
     }
 }
 
+/// Encodes all nodes of `data` in place, the inverse of `decode`.
+pub fn encode<H, G>(graph: &G, replica_id: &H::Domain, data: &mut [u8]) -> Result<()>
+where
+    H: Hasher,
+    G::Key: AsRef<H::Domain>,
+    G: Graph<H>,
+{
+    encode_cancellable(graph, replica_id, data, &CancellationToken::new())
+}
+
+/// Like `encode`, but checks `cancel` before every node and returns `Error::Cancelled` as soon as
+/// it is set, leaving `data` partially encoded.
+pub fn encode_cancellable<H, G>(
+    graph: &G,
+    replica_id: &H::Domain,
+    data: &mut [u8],
+    cancel: &CancellationToken,
+) -> Result<()>
+where
+    H: Hasher,
+    G::Key: AsRef<H::Domain>,
+    G: Graph<H>,
+{
+    graph.check_data_len(data)?;
+
+    // Because a node always follows all of its parents in the data,
+    // the nodes are by definition already topologically sorted.
+    // Therefore, if we simply traverse the data in order, encoding each node in place,
+    // we can always get each parent's encodings with a simple lookup --
+    // since we will already have encoded the parent earlier in the traversal.
+    let mut parents = vec![0; graph.degree()];
+    for node in 0..graph.size() {
+        cancel.check()?;
+        graph.parents(node, &mut parents)?;
+        let key = graph.create_key(replica_id, node, &parents, data, None)?;
+        let start = data_at_node_offset(node);
+        let end = start + NODE_SIZE;
+
+        let node_data = H::Domain::try_from_bytes(&data[start..end])?;
+        let encoded = encode_node::<H>(key.as_ref(), &node_data)?;
+
+        encoded.write_bytes(&mut data[start..end])?;
+    }
+
+    Ok(())
+}
+
+/// Encodes the data of a single node with its key, using the sloth encoding of `H`.
+pub fn encode_node<H: Hasher>(key: &H::Domain, node_data: &H::Domain) -> Result<H::Domain> {
+    H::sloth_encode(key, node_data)
+}
+
+/// Inverse of `encode_node`.
+pub fn decode_node<H: Hasher>(key: &H::Domain, encoded: &H::Domain) -> Result<H::Domain> {
+    H::sloth_decode(key, encoded)
+}
+
 pub fn decode<'a, H, G>(
     graph: &'a G,
     replica_id: &'a H::Domain,
@@ -535,7 +573,7 @@ where
     let key = graph.create_key(replica_id, v, &parents, &data, exp_parents_data)?;
     let node_data = H::Domain::try_from_bytes(&data_at_node(data, v)?)?;
 
-    Ok(encode::decode(*key.as_ref(), node_data))
+    decode_node::<H>(key.as_ref(), &node_data)
 }
 
 pub fn decode_domain_block<H>(
@@ -550,7 +588,7 @@ where
 {
     let key = create_key_from_tree::<H>(replica_id, node, parents, tree)?;
 
-    Ok(encode::decode(key, node_data))
+    decode_node::<H>(&key, &node_data)
 }
 
 /// Creates the encoding key from a `MerkleTree`.
@@ -586,15 +624,15 @@ mod tests {
     use memmap::MmapMut;
     use memmap::MmapOptions;
     use paired::bls12_381::{Bls12, Fr};
-    use rand::SeedableRng;
-    use rand_xorshift::XorShiftRng;
     use std::fs::File;
     use std::io::Write;
     use tempfile;
 
     use crate::drgraph::{new_seed, BucketGraph, BASE_DEGREE};
+    use crate::error::Error;
     use crate::fr32::fr_into_bytes;
     use crate::hasher::{Blake2sHasher, PedersenHasher, Sha256Hasher};
+    use crate::test_helper::seeded_rng;
     use crate::util::data_at_node;
 
     pub fn file_backed_mmap_from(data: &[u8]) -> MmapMut {
@@ -610,8 +648,57 @@ mod tests {
         }
     }
 
+    fn encode_decode_node<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let key = H::Domain::random(rng);
+        let data: Vec<H::Domain> = (0..8).map(|_| H::Domain::random(rng)).collect();
+
+        for node_data in &data {
+            let encoded = encode_node::<H>(&key, node_data).unwrap();
+            assert_ne!(&encoded, node_data);
+            assert_eq!(&decode_node::<H>(&key, &encoded).unwrap(), node_data);
+        }
+    }
+
+    #[test]
+    fn encode_decode_node_pedersen() {
+        encode_decode_node::<PedersenHasher>();
+    }
+
+    #[test]
+    fn encode_decode_node_sha256() {
+        encode_decode_node::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn encode_decode_node_blake2s() {
+        encode_decode_node::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn encode_cancelled_leaves_data_untouched() {
+        let rng = &mut seeded_rng();
+        let nodes = 8;
+        let graph = BucketGraph::<PedersenHasher>::new(nodes, BASE_DEGREE, 0, new_seed()).unwrap();
+        let replica_id: <PedersenHasher as Hasher>::Domain = Domain::random(rng);
+        let original: Vec<u8> = (0..nodes)
+            .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
+            .collect();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let mut data = original.clone();
+        match encode_cancellable(&graph, &replica_id, &mut data, &cancel)
+            .map_err(|err| err.downcast::<Error>())
+        {
+            Err(Ok(Error::Cancelled)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(data, original);
+    }
+
     fn test_extract_all<H: Hasher>() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let replica_id: H::Domain = H::Domain::random(rng);
         let data = vec![2u8; 32 * 3];
@@ -684,7 +771,7 @@ mod tests {
     }
 
     fn test_extract<H: Hasher>() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let replica_id: H::Domain = H::Domain::random(rng);
         let nodes = 3;
@@ -775,7 +862,7 @@ mod tests {
 
         // The loop is here in case we need to retry because of an edge case in the test design.
         loop {
-            let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+            let rng = &mut seeded_rng();
             let degree = BASE_DEGREE;
             let expansion_degree = 0;
             let seed = new_seed();
@@ -1,23 +1,31 @@
 use std::cmp;
+use std::collections::{HashSet, VecDeque};
+use std::io::{Read, Write};
 use std::marker::PhantomData;
+use std::path::Path;
 
 use anyhow::ensure;
-use rand::{rngs::OsRng, Rng, SeedableRng};
+use log::warn;
+use rand::{rngs::OsRng, Rng, RngCore, SeedableRng};
 use rand_chacha::ChaChaRng;
 use sha2::{Digest, Sha256};
 
 use crate::error::*;
 use crate::fr32::bytes_into_fr_repr_safe;
 use crate::hasher::pedersen::PedersenHasher;
-use crate::hasher::Hasher;
-use crate::merkle::{create_merkle_tree, MerkleTree};
+use crate::hasher::{Domain, Hasher};
+use crate::merkle::{
+    create_merkle_tree_with_options, load_verified_merkle_tree, store_merkle_tree,
+    MerkleBuildOptions, MerkleTree,
+};
 use crate::parameter_cache::ParameterSetMetadata;
-use crate::util::{data_at_node_offset, NODE_SIZE};
+use crate::util::{data_at_node, data_at_node_offset, NODE_SIZE};
 
 /// The default hasher currently in use.
 pub type DefaultTreeHasher = PedersenHasher;
 
-pub const PARALLEL_MERKLE: bool = true;
+/// Whether merkle trees are built in parallel, can be turned off with the `serial-only` feature.
+pub const PARALLEL_MERKLE: bool = !cfg!(feature = "serial-only");
 
 /// The base degree used for all DRG graphs. One degree from this value is used to ensure that a
 /// given node always has its immediate predecessor as a parent, thus ensuring unique topological
@@ -33,9 +41,146 @@ pub trait Graph<H: Hasher>: ::std::fmt::Debug + Clone + PartialEq + Eq {
         self.size() * NODE_SIZE
     }
 
+    /// Returns the estimated size of the merkle tree over all nodes, i.e. of its `2 * size - 1`
+    /// domain elements.
+    fn tree_size_estimate(&self) -> usize {
+        (2 * self.size()).saturating_sub(1) * H::Domain::SIZE
+    }
+
+    /// Returns a rough estimate of the memory needed to replicate the graph: the data itself, its
+    /// merkle tree and the parents buffer.
+    fn replication_memory_estimate(&self) -> usize {
+        self.expected_size()
+            + self.tree_size_estimate()
+            + self.degree() * std::mem::size_of::<usize>()
+    }
+
+    /// Checks that `data` holds exactly one node for every node of the graph.
+    fn check_data_len(&self, data: &[u8]) -> Result<()> {
+        ensure!(
+            data.len() == self.expected_size(),
+            Error::InvalidMerkleTreeArgs(data.len(), NODE_SIZE, self.size())
+        );
+
+        Ok(())
+    }
+
+    /// Returns the data of each of `nodes`, as slices into `data`, which holds all nodes of the
+    /// graph.
+    fn nodes_data<'a>(&self, data: &'a [u8], nodes: &[usize]) -> Result<Vec<&'a [u8]>> {
+        self.check_data_len(data)?;
+
+        nodes
+            .iter()
+            .map(|&node| {
+                ensure!(
+                    node < self.size(),
+                    Error::OutOfBounds(node, self.size() - 1)
+                );
+                data_at_node(data, node)
+            })
+            .collect()
+    }
+
     /// Builds a merkle tree based on the given data.
     fn merkle_tree<'a>(&self, data: &'a [u8]) -> Result<MerkleTree<H::Domain, H::Function>> {
-        create_merkle_tree::<H>(None, self.size(), data)
+        self.merkle_tree_with_options(data, MerkleBuildOptions::default())
+    }
+
+    /// Like `merkle_tree`, but built as controlled by `options`.
+    fn merkle_tree_with_options<'a>(
+        &self,
+        data: &'a [u8],
+        options: MerkleBuildOptions,
+    ) -> Result<MerkleTree<H::Domain, H::Function>> {
+        self.check_data_len(data)?;
+        create_merkle_tree_with_options::<H>(None, self.size(), data, options)
+    }
+
+    /// Like `merkle_tree`, but also returns the leaves parsed from `data`, for callers that need
+    /// them afterwards and would otherwise parse `data` a second time.
+    fn merkle_tree_with_leaves<'a>(
+        &self,
+        data: &'a [u8],
+    ) -> Result<(MerkleTree<H::Domain, H::Function>, Vec<H::Domain>)> {
+        let tree = self.merkle_tree(data)?;
+        let leaves = (0..tree.leafs())
+            .map(|i| tree.read_at(i))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok((tree, leaves))
+    }
+
+    /// Like `merkle_tree`, but if `reject_all_zero` is set, refuses to build a tree over data that
+    /// is all zeros, which usually means the data was never written.
+    fn merkle_tree_checked<'a>(
+        &self,
+        data: &'a [u8],
+        reject_all_zero: bool,
+    ) -> Result<MerkleTree<H::Domain, H::Function>> {
+        ensure!(
+            !reject_all_zero || data.iter().any(|b| *b != 0),
+            Error::SuspiciousAllZeroData
+        );
+
+        self.merkle_tree(data)
+    }
+
+    /// Returns the key naming the on-disk cache of the merkle tree over `data`. It combines the
+    /// `identifier` of the graph, its size and a hash of `data`, so different graphs or different
+    /// data never share a cache file. Hashing `data` is far cheaper than building the tree, but
+    /// still reads all of it.
+    fn tree_cache_key(&self, data: &[u8]) -> String
+    where
+        Self: ParameterSetMetadata,
+    {
+        let identifier = self.identifier();
+
+        let mut hasher = Sha256::new();
+        hasher.input(&(identifier.len() as u64).to_le_bytes());
+        hasher.input(identifier.as_bytes());
+        hasher.input(&(self.size() as u64).to_le_bytes());
+        hasher.input(Sha256::digest(data));
+
+        format!(
+            "tree-{}-{}",
+            self.size(),
+            hex::encode(&hasher.result()[..16])
+        )
+    }
+
+    /// Like `merkle_tree`, but stores the tree in `cache_dir`, in a file named by
+    /// `tree_cache_key`, and loads it from there instead if it was built before. A cached tree is
+    /// verified with `load_verified_merkle_tree` before it is used, and rebuilt if that fails, so a
+    /// partially written or corrupted cache file is never returned as the tree.
+    fn merkle_tree_cached(
+        &self,
+        data: &[u8],
+        cache_dir: &Path,
+    ) -> Result<MerkleTree<H::Domain, H::Function>>
+    where
+        Self: ParameterSetMetadata,
+    {
+        self.check_data_len(data)?;
+        ensure!(
+            self.size() > 0,
+            Error::InvalidMerkleTreeArgs(data.len(), NODE_SIZE, self.size())
+        );
+
+        let path = cache_dir.join(self.tree_cache_key(data));
+        // A missing or unusable cache file is not an error, the tree is rebuilt instead.
+        if path.exists() {
+            if let Ok(tree) = load_verified_merkle_tree::<H, _>(&path) {
+                if tree.leafs() == self.size() {
+                    return Ok(tree);
+                }
+            }
+        }
+
+        let tree = self.merkle_tree(data)?;
+        store_merkle_tree::<H, _>(&tree, &path)?;
+
+        Ok(tree)
     }
 
     /// Returns the merkle tree depth.
@@ -43,6 +188,12 @@ pub trait Graph<H: Hasher>: ::std::fmt::Debug + Clone + PartialEq + Eq {
         graph_height(self.size()) as u64
     }
 
+    /// Returns the number of leaves of the merkle tree over the graph. The tree is a full binary
+    /// tree, so a size which is not a power of two is padded up to the next one.
+    fn padded_leaf_count(&self) -> usize {
+        1 << self.merkle_tree_depth()
+    }
+
     /// Returns a sorted list of all parents of this node. The parents may be repeated.
     ///
     /// If a node doesn't have any parents, then this vector needs to return a vector where
@@ -53,12 +204,158 @@ pub trait Graph<H: Hasher>: ::std::fmt::Debug + Clone + PartialEq + Eq {
     /// reasons, so that the vector can be allocated outside this call.
     fn parents(&self, node: usize, parents: &mut [u32]) -> Result<()>;
 
+    /// Returns true if `node` is a base node, i.e. a node whose parents are not sampled from the
+    /// graph. By default this detects the self reference convention described on `parents`.
+    fn is_base_node(&self, node: usize) -> bool {
+        let mut parents = vec![0; self.degree()];
+        self.parents(node, &mut parents).is_ok() && parents[0] as usize == node
+    }
+
+    /// Fills `out` with the distinct parents of `node`, sorted ascending. Unlike `parents`, which
+    /// may repeat a parent, the result holds at most `degree()` elements.
+    fn distinct_parents(&self, node: usize, out: &mut Vec<usize>) -> Result<()> {
+        let mut parents = vec![0; self.degree()];
+        self.parents(node, &mut parents)?;
+
+        out.clear();
+        out.extend(parents.iter().map(|p| *p as usize));
+        out.sort_unstable();
+        out.dedup();
+
+        Ok(())
+    }
+
+    /// Returns the ancestors of `node` up to `max_depth` levels, i.e. the distinct parents of
+    /// `node`, their distinct parents, and so on. The result grows quickly with the depth and can
+    /// contain a large part of the graph, so keep `max_depth` small for deep graphs.
+    fn ancestors(&self, node: usize, max_depth: usize) -> Result<HashSet<usize>> {
+        let mut ancestors = HashSet::new();
+        let mut frontier = vec![node];
+        let mut parents = Vec::with_capacity(self.degree());
+
+        for _ in 0..max_depth {
+            let mut next = Vec::new();
+            for node in frontier {
+                self.distinct_parents(node, &mut parents)?;
+                for parent in &parents {
+                    if ancestors.insert(*parent) {
+                        next.push(*parent);
+                    }
+                }
+            }
+
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+
+        Ok(ancestors)
+    }
+
+    /// Returns true if every parent of a node precedes it, so that nodes can be processed in
+    /// index order.
+    fn forward(&self) -> bool {
+        true
+    }
+
+    /// Returns an order in which the nodes can be replicated, with every node after its parents.
+    /// For forward graphs this is the index order, otherwise a topological sort is computed,
+    /// failing if the graph contains a cycle. Self references of base nodes are ignored.
+    fn replication_order(&self) -> Result<Vec<usize>> {
+        if self.forward() {
+            return Ok((0..self.size()).collect());
+        }
+
+        let mut children = vec![Vec::new(); self.size()];
+        let mut missing_parents = vec![0; self.size()];
+        let mut parents = Vec::with_capacity(self.degree());
+        for node in 0..self.size() {
+            self.distinct_parents(node, &mut parents)?;
+            for parent in parents.iter().filter(|p| **p != node) {
+                children[*parent].push(node);
+                missing_parents[node] += 1;
+            }
+        }
+
+        let mut ready: VecDeque<usize> = (0..self.size())
+            .filter(|node| missing_parents[*node] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(self.size());
+        while let Some(node) = ready.pop_front() {
+            order.push(node);
+            for child in &children[node] {
+                missing_parents[*child] -= 1;
+                if missing_parents[*child] == 0 {
+                    ready.push_back(*child);
+                }
+            }
+        }
+
+        ensure!(order.len() == self.size(), Error::CyclicGraph);
+
+        Ok(order)
+    }
+
+    /// Writes the parents of all nodes to `w` in a compact binary format, intended for external
+    /// tooling: the number of nodes and the degree, followed by the `size * degree` parents, all
+    /// encoded as LEB128 varints. Use `read_adjacency` to read it back.
+    fn write_adjacency<W: Write>(&self, w: &mut W) -> Result<()> {
+        write_varint(w, self.size() as u64)?;
+        write_varint(w, self.degree() as u64)?;
+
+        let mut parents = vec![0; self.degree()];
+        for node in 0..self.size() {
+            self.parents(node, &mut parents)?;
+            for parent in &parents {
+                write_varint(w, u64::from(*parent))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Samples `count` challenged nodes from `rng`, distributed according to `weight`. Fails if the
+    /// graph has no nodes to challenge.
+    fn sample_challenges<R: Rng>(
+        &self,
+        rng: &mut R,
+        count: usize,
+        weight: ChallengeWeight,
+    ) -> Result<Vec<usize>> {
+        let size = self.size();
+        ensure!(size > 0, "cannot sample challenges from an empty graph");
+
+        Ok((0..count)
+            .map(|_| match weight {
+                ChallengeWeight::Uniform => rng.gen_range(0, size),
+                ChallengeWeight::DepthBiased => {
+                    cmp::max(rng.gen_range(0, size), rng.gen_range(0, size))
+                }
+            })
+            .collect())
+    }
+
     /// Returns the size of the graph (number of nodes).
     fn size(&self) -> usize;
 
     /// Returns the number of parents of each node in the graph.
+    ///
+    /// This must equal the number of entries `parents` fills, callers size the `parents` buffer
+    /// with it. Graphs with expansion parents have to count them as well.
     fn degree(&self) -> usize;
 
+    /// Returns how many of the `degree` parents are base (DRG) parents, which `parents` fills in
+    /// first.
+    fn base_degree(&self) -> usize {
+        self.degree() - self.expansion_degree()
+    }
+
+    /// Returns how many of the `degree` parents are expansion parents, following the base ones.
+    fn expansion_degree(&self) -> usize {
+        0
+    }
+
     fn new(
         nodes: usize,
         base_degree: usize,
@@ -79,8 +376,216 @@ pub trait Graph<H: Hasher>: ::std::fmt::Debug + Clone + PartialEq + Eq {
     ) -> Result<Self::Key>;
 }
 
+/// How `Graph::sample_challenges` distributes challenges over the nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeWeight {
+    /// Every node is equally likely.
+    Uniform,
+    /// The likelihood of a node grows linearly with its index, favoring deeper nodes, which have
+    /// more ancestors.
+    DepthBiased,
+}
+
+/// The parents of all nodes of a graph, as written by `Graph::write_adjacency`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adjacency {
+    size: usize,
+    degree: usize,
+    parents: Vec<u32>,
+}
+
+impl Adjacency {
+    /// Returns the number of nodes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of parents of each node.
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    /// Returns the parents of `node`.
+    pub fn parents(&self, node: usize) -> Result<&[u32]> {
+        ensure!(node < self.size, Error::OutOfBounds(node, self.size - 1));
+
+        let start = node * self.degree;
+        Ok(&self.parents[start..start + self.degree])
+    }
+}
+
+/// Reads an adjacency written by `Graph::write_adjacency`, checking that all parents are nodes
+/// of the graph.
+pub fn read_adjacency<R: Read>(r: &mut R) -> Result<Adjacency> {
+    let size = read_varint(r)? as usize;
+    let degree = read_varint(r)? as usize;
+    ensure!(size > 0, Error::MalformedInput);
+    ensure!(size.checked_mul(degree).is_some(), Error::MalformedInput);
+
+    // The header is untrusted, so the parents are not preallocated from it.
+    let mut parents = Vec::new();
+    for _ in 0..size * degree {
+        let parent = read_varint(r)? as usize;
+        ensure!(parent < size, Error::OutOfBounds(parent, size - 1));
+        parents.push(parent as u32);
+    }
+
+    Ok(Adjacency {
+        size,
+        degree,
+        parents,
+    })
+}
+
+fn write_varint<W: Write>(w: &mut W, mut value: u64) -> Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            w.write_all(&[byte])?;
+            return Ok(());
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint<R: Read>(r: &mut R) -> Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8];
+        r.read_exact(&mut byte)?;
+
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(Error::MalformedInput.into())
+}
+
 pub fn graph_height(size: usize) -> usize {
-    (size as f64).log2().ceil() as usize
+    graph_height_arity(size, 2)
+}
+
+/// Returns the height of a merkle tree with the given `arity` over `size` leaves, i.e.
+/// `ceil(log_arity(size))`.
+pub fn graph_height_arity(size: usize, arity: usize) -> usize {
+    assert!(arity >= 2, "arity must be at least 2");
+
+    let mut height = 0;
+    let mut width = 1;
+    while width < size {
+        width = width.saturating_mul(arity);
+        height += 1;
+    }
+
+    height
+}
+
+/// How `BucketGraph` derives the randomness used to sample the parents of a node.
+#[derive(Clone, Debug, PartialEq, Eq, Copy)]
+pub enum ParentSampler {
+    /// A `ChaChaRng`, seeded with the graph seed followed by the node index. The default.
+    ChaCha,
+    /// The concatenated blake2s hashes of `PARENT_SAMPLER_TAG | seed | node | counter` for
+    /// `counter = 0, 1, ..`, read as little endian `u64`s, which is easy to reproduce without a
+    /// ChaCha implementation. Every draw of a value below `n` is one `u64` modulo `n`.
+    Blake2s,
+}
+
+impl Default for ParentSampler {
+    fn default() -> Self {
+        ParentSampler::ChaCha
+    }
+}
+
+/// Domain separation tag of the `ParentSampler::Blake2s` hashes.
+pub const PARENT_SAMPLER_TAG: &[u8] = b"filecoin-drg-parents";
+
+/// The two random draws `BucketGraph::sample_parents` makes for every parent.
+trait ParentDraws {
+    /// Draws the bucket of a parent of the meta node `meta_node`, below `log2(meta_node)`.
+    fn bucket(&mut self, meta_node: usize) -> usize;
+
+    /// Draws the distance of a parent, in `low..high`.
+    fn distance(&mut self, low: usize, high: usize) -> usize;
+}
+
+impl ParentDraws for ChaChaRng {
+    fn bucket(&mut self, meta_node: usize) -> usize {
+        let logi = (meta_node as f32).log2().floor() as usize;
+        self.gen::<usize>() % logi
+    }
+
+    fn distance(&mut self, low: usize, high: usize) -> usize {
+        self.gen_range(low, high)
+    }
+}
+
+/// The random byte stream of `ParentSampler::Blake2s` for a single node.
+struct Blake2sParentRng {
+    seed: [u8; 28],
+    node: u32,
+    counter: u64,
+    block: [u8; 32],
+    pos: usize,
+}
+
+impl Blake2sParentRng {
+    fn new(seed: [u8; 28], node: usize) -> Self {
+        Blake2sParentRng {
+            seed,
+            node: node as u32,
+            counter: 0,
+            block: [0; 32],
+            pos: 32,
+        }
+    }
+
+    fn refill(&mut self) {
+        let hash = blake2s_simd::State::new()
+            .update(PARENT_SAMPLER_TAG)
+            .update(&self.seed)
+            .update(&self.node.to_le_bytes())
+            .update(&self.counter.to_le_bytes())
+            .finalize();
+
+        self.block.copy_from_slice(hash.as_bytes());
+        self.counter += 1;
+        self.pos = 0;
+    }
+
+    /// Reads the next 8 bytes of the stream as a little endian `u64`. A block holds exactly 4 of
+    /// them, so no value spans two blocks.
+    fn next_u64(&mut self) -> u64 {
+        if self.pos == self.block.len() {
+            self.refill();
+        }
+
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.block[self.pos..self.pos + 8]);
+        self.pos += 8;
+        u64::from_le_bytes(bytes)
+    }
+
+    /// Draws a value below `n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+impl ParentDraws for Blake2sParentRng {
+    fn bucket(&mut self, meta_node: usize) -> usize {
+        // floor(log2(meta_node)), computed on integers.
+        let logi = 63 - (meta_node as u64).leading_zeros() as usize;
+        self.below(logi)
+    }
+
+    fn distance(&mut self, low: usize, high: usize) -> usize {
+        low + self.below(high - low)
+    }
 }
 
 /// Bucket sampling algorithm.
@@ -89,17 +594,25 @@ pub struct BucketGraph<H: Hasher> {
     nodes: usize,
     base_degree: usize,
     seed: [u8; 28],
+    sampler: ParentSampler,
     _h: PhantomData<H>,
 }
 
 impl<H: Hasher> ParameterSetMetadata for BucketGraph<H> {
     fn identifier(&self) -> String {
-        // NOTE: Seed is not included because it does not influence parameter generation.
+        // NOTE: Seed is not included because it does not influence parameter generation. The
+        // default sampler is left out, so existing identifiers stay valid.
+        let sampler = match self.sampler {
+            ParentSampler::ChaCha => "",
+            ParentSampler::Blake2s => "; parent_sampler: blake2s",
+        };
+
         format!(
-            "drgraph::BucketGraph{{size: {}; degree: {}; hasher: {}}}",
+            "drgraph::BucketGraph{{size: {}; degree: {}; hasher: {}{}}}",
             self.nodes,
             self.degree(),
             H::name(),
+            sampler,
         )
     }
 
@@ -137,6 +650,12 @@ impl<H: Hasher> Graph<H> for BucketGraph<H> {
     #[inline]
     fn parents(&self, node: usize, parents: &mut [u32]) -> Result<()> {
         let m = self.degree();
+        debug_assert!(
+            parents.len() >= m,
+            "parents buffer of {} entries is smaller than the degree {}",
+            parents.len(),
+            m
+        );
 
         match node {
             // There are special cases for the first and second node: the first node self
@@ -149,45 +668,29 @@ impl<H: Hasher> Graph<H> for BucketGraph<H> {
                 }
                 Ok(())
             }
-            _ => {
-                // The degree `m` minus 1; the degree without the immediate predecessor node.
-                let m_prime = m - 1;
-
-                // seed = self.seed | node
-                let mut seed = [0u8; 32];
-                seed[..28].copy_from_slice(&self.seed);
-                seed[28..].copy_from_slice(&(node as u32).to_le_bytes());
-                let mut rng = ChaChaRng::from_seed(seed);
-
-                for (k, parent) in parents.iter_mut().take(m_prime).enumerate() {
-                    // Iterate over `m_prime` number of meta nodes for the i-th real node. Simulate
-                    // the edges that we would add from previous graph nodes. If any edge is added
-                    // from a meta node of j-th real node then add edge (j,i).
-                    let logi = ((node * m_prime) as f32).log2().floor() as usize;
-                    let j = rng.gen::<usize>() % logi;
-                    let jj = cmp::min(node * m_prime + k, 1 << (j + 1));
-                    let back_dist = rng.gen_range(cmp::max(jj >> 1, 2), jj + 1);
-                    let out = (node * m_prime + k - back_dist) / m_prime;
-
-                    // remove self references and replace with reference to previous node
-                    if out == node {
-                        *parent = (node - 1) as u32;
-                    } else {
-                        ensure!(
-                            out <= node,
-                            "Parent node must be smaller than current node."
-                        );
-                        *parent = out as u32;
-                    }
-                }
+            _ => match self.sampler {
+                ParentSampler::ChaCha => {
+                    // seed = self.seed | node
+                    let mut seed = [0u8; 32];
+                    seed[..28].copy_from_slice(&self.seed);
+                    seed[28..].copy_from_slice(&(node as u32).to_le_bytes());
 
-                // Add the immediate predecessor as a parent to ensure unique topological ordering.
-                parents[m_prime] = (node - 1) as u32;
-                Ok(())
-            }
+                    self.sample_parents(node, parents, &mut ChaChaRng::from_seed(seed))
+                }
+                ParentSampler::Blake2s => {
+                    let rng = &mut Blake2sParentRng::new(self.seed, node);
+                    self.sample_parents(node, parents, rng)
+                }
+            },
         }
     }
 
+    /// The first two nodes are special cased in `parents` and never get sampled parents.
+    #[inline]
+    fn is_base_node(&self, node: usize) -> bool {
+        node < 2
+    }
+
     #[inline]
     fn size(&self) -> usize {
         self.nodes
@@ -208,6 +711,25 @@ impl<H: Hasher> Graph<H> for BucketGraph<H> {
         base_degree: usize,
         expansion_degree: usize,
         seed: [u8; 28],
+    ) -> Result<Self> {
+        Self::new_with_sampler(
+            nodes,
+            base_degree,
+            expansion_degree,
+            seed,
+            ParentSampler::default(),
+        )
+    }
+}
+
+impl<H: Hasher> BucketGraph<H> {
+    /// Like `Graph::new`, but with an explicit parent sampler instead of the default one.
+    pub fn new_with_sampler(
+        nodes: usize,
+        base_degree: usize,
+        expansion_degree: usize,
+        seed: [u8; 28],
+        sampler: ParentSampler,
     ) -> Result<Self> {
         if !cfg!(feature = "unchecked-degrees") {
             ensure!(base_degree == BASE_DEGREE, "Base degree is wrong.");
@@ -215,17 +737,199 @@ impl<H: Hasher> Graph<H> for BucketGraph<H> {
 
         ensure!(expansion_degree == 0, "Expension degree must be zero.");
 
+        if !nodes.is_power_of_two() {
+            warn!(
+                "graph size {} is not a power of two, its merkle tree is padded to {} leaves",
+                nodes,
+                nodes.next_power_of_two()
+            );
+        }
+
         Ok(BucketGraph {
             nodes,
             base_degree,
             seed,
+            sampler,
+            _h: PhantomData,
+        })
+    }
+
+    /// Returns the parent sampler of the graph.
+    pub fn sampler(&self) -> ParentSampler {
+        self.sampler
+    }
+
+    /// Samples the parents of `node`, which must be at least 2, drawing all randomness from `rng`.
+    fn sample_parents<R: ParentDraws>(
+        &self,
+        node: usize,
+        parents: &mut [u32],
+        rng: &mut R,
+    ) -> Result<()> {
+        let m = self.degree();
+        // The degree `m` minus 1; the degree without the immediate predecessor node.
+        let m_prime = m - 1;
+
+        // Parents are stored as `u32`, and the meta node indices below go up to
+        // `(node + 1) * m_prime`. Refuse nodes for which either would wrap, instead of silently
+        // sampling wrong parents.
+        let overflow = || Error::ParentOverflow { node };
+        ensure!(node - 1 <= u32::max_value() as usize, overflow());
+        let meta_node = node.checked_mul(m_prime).ok_or_else(overflow)?;
+        meta_node.checked_add(m_prime).ok_or_else(overflow)?;
+
+        for (k, parent) in parents.iter_mut().take(m_prime).enumerate() {
+            // Iterate over `m_prime` number of meta nodes for the i-th real node. Simulate
+            // the edges that we would add from previous graph nodes. If any edge is added
+            // from a meta node of j-th real node then add edge (j,i).
+            let j = rng.bucket(meta_node);
+            let jj = cmp::min(meta_node + k, 1 << (j + 1));
+            let back_dist = rng.distance(cmp::max(jj >> 1, 2), jj + 1);
+            let out = (meta_node + k)
+                .checked_sub(back_dist)
+                .ok_or_else(overflow)?
+                / m_prime;
+
+            // remove self references and replace with reference to previous node
+            if out == node {
+                *parent = (node - 1) as u32;
+            } else {
+                ensure!(
+                    out <= node,
+                    "Parent node must be smaller than current node."
+                );
+                *parent = out as u32;
+            }
+        }
+
+        // Add the immediate predecessor as a parent to ensure unique topological ordering.
+        parents[m_prime] = (node - 1) as u32;
+        Ok(())
+    }
+}
+
+/// A `Graph` wrapper, which computes the parents of all nodes of the wrapped graph once and serves
+/// `parents` from that cache afterwards. Useful when the same graph is traversed repeatedly, e.g.
+/// once per layer during replication.
+///
+/// The cache holds `size * degree` parents of 4 bytes each, so it uses `size * degree * 4` bytes
+/// of memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedGraph<H: Hasher, G: Graph<H>> {
+    graph: G,
+    parents: Vec<u32>,
+    _h: PhantomData<H>,
+}
+
+impl<H: Hasher, G: Graph<H>> CachedGraph<H, G> {
+    /// Wraps the given graph, precomputing the parents of all its nodes.
+    pub fn from_graph(graph: G) -> Result<Self> {
+        let degree = graph.degree();
+        let mut parents = vec![0; graph.size() * degree];
+
+        for (node, node_parents) in parents.chunks_mut(degree).enumerate() {
+            graph.parents(node, node_parents)?;
+        }
+
+        Ok(CachedGraph {
+            graph,
+            parents,
             _h: PhantomData,
         })
     }
+
+    /// Returns the wrapped graph.
+    pub fn inner(&self) -> &G {
+        &self.graph
+    }
+}
+
+impl<H: Hasher, G: Graph<H>> Graph<H> for CachedGraph<H, G> {
+    type Key = G::Key;
+
+    fn create_key(
+        &self,
+        id: &H::Domain,
+        node: usize,
+        parents: &[u32],
+        parents_data: &[u8],
+        exp_parents_data: Option<&[u8]>,
+    ) -> Result<Self::Key> {
+        self.graph
+            .create_key(id, node, parents, parents_data, exp_parents_data)
+    }
+
+    #[inline]
+    fn parents(&self, node: usize, parents: &mut [u32]) -> Result<()> {
+        let degree = self.degree();
+        ensure!(
+            node < self.size(),
+            Error::OutOfBounds(node, self.size() - 1)
+        );
+        ensure!(
+            parents.len() >= degree,
+            Error::OutOfBounds(degree, parents.len())
+        );
+
+        let start = node * degree;
+        parents[..degree].copy_from_slice(&self.parents[start..start + degree]);
+
+        Ok(())
+    }
+
+    #[inline]
+    fn is_base_node(&self, node: usize) -> bool {
+        self.graph.is_base_node(node)
+    }
+
+    #[inline]
+    fn forward(&self) -> bool {
+        self.graph.forward()
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        self.graph.size()
+    }
+
+    #[inline]
+    fn degree(&self) -> usize {
+        self.graph.degree()
+    }
+
+    #[inline]
+    fn expansion_degree(&self) -> usize {
+        self.graph.expansion_degree()
+    }
+
+    fn seed(&self) -> [u8; 28] {
+        self.graph.seed()
+    }
+
+    fn new(
+        nodes: usize,
+        base_degree: usize,
+        expansion_degree: usize,
+        seed: [u8; 28],
+    ) -> Result<Self> {
+        Self::from_graph(G::new(nodes, base_degree, expansion_degree, seed)?)
+    }
 }
 
+/// Generates a new random graph seed, panicking if the OS random number generator is unavailable.
+/// Prefer `try_new_seed` in library code.
 pub fn new_seed() -> [u8; 28] {
-    OsRng.gen()
+    try_new_seed().expect("failed to generate seed")
+}
+
+/// Generates a new random graph seed from the OS random number generator.
+pub fn try_new_seed() -> Result<[u8; 28]> {
+    let mut seed = [0u8; 28];
+    OsRng.try_fill_bytes(&mut seed).map_err(|err| {
+        Error::Unclassified(format!("OS random number generator failed: {}", err))
+    })?;
+
+    Ok(seed)
 }
 
 #[cfg(test)]
@@ -233,17 +937,15 @@ mod tests {
     use super::*;
 
     use memmap::MmapMut;
-    use memmap::MmapOptions;
 
     use crate::drgraph::new_seed;
     use crate::hasher::{Blake2sHasher, PedersenHasher, Sha256Hasher};
+    use crate::test_helper::seeded_rng;
+    use crate::util::anon_mmap;
 
     // Create and return an object of MmapMut backed by in-memory copy of data.
     pub fn mmap_from(data: &[u8]) -> MmapMut {
-        let mut mm = MmapOptions::new()
-            .len(data.len())
-            .map_anon()
-            .expect("Failed to create memory map");
+        let mut mm = anon_mmap(data.len()).expect("Failed to create memory map");
         mm.copy_from_slice(data);
         mm
     }
@@ -300,8 +1002,710 @@ mod tests {
         graph_bucket::<PedersenHasher>();
     }
 
-    fn gen_proof<H: Hasher>() {
-        let g = BucketGraph::<H>::new(5, BASE_DEGREE, 0, new_seed()).unwrap();
+    fn base_nodes<H: Hasher>() {
+        let g = BucketGraph::<H>::new(200, BASE_DEGREE, 0, new_seed()).unwrap();
+
+        assert!(g.is_base_node(0));
+        assert!(g.is_base_node(1));
+        for node in 2..g.size() {
+            assert!(!g.is_base_node(node), "node {} is not a base node", node);
+        }
+
+        let cached = CachedGraph::from_graph(g).unwrap();
+        assert!(cached.is_base_node(0));
+        assert!(cached.is_base_node(1));
+        assert!(!cached.is_base_node(2));
+    }
+
+    #[test]
+    fn base_nodes_sha256() {
+        base_nodes::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn base_nodes_blake2s() {
+        base_nodes::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn base_nodes_pedersen() {
+        base_nodes::<PedersenHasher>();
+    }
+
+    fn distinct_parents<H: Hasher>() {
+        let g = BucketGraph::<H>::new(200, BASE_DEGREE, 0, new_seed()).unwrap();
+        let mut parents = vec![0; g.degree()];
+        let mut distinct = Vec::new();
+
+        // The first two nodes only reference node 0, `degree()` times.
+        for node in 0..2 {
+            g.distinct_parents(node, &mut distinct).unwrap();
+            assert_eq!(distinct, vec![0]);
+        }
+
+        for node in (2..g.size()).step_by(7) {
+            g.parents(node, &mut parents).unwrap();
+            let mut expected: Vec<usize> = parents.iter().map(|p| *p as usize).collect();
+            expected.sort();
+            expected.dedup();
+
+            g.distinct_parents(node, &mut distinct).unwrap();
+            assert!(distinct.len() <= g.degree());
+            assert_eq!(distinct, expected);
+        }
+    }
+
+    #[test]
+    fn distinct_parents_sha256() {
+        distinct_parents::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn distinct_parents_blake2s() {
+        distinct_parents::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn distinct_parents_pedersen() {
+        distinct_parents::<PedersenHasher>();
+    }
+
+    fn ancestors<H: Hasher>() {
+        let g = BucketGraph::<H>::new(200, BASE_DEGREE, 0, new_seed()).unwrap();
+        let mut distinct = Vec::new();
+
+        for node in (0..g.size()).step_by(13) {
+            g.distinct_parents(node, &mut distinct).unwrap();
+            let expected: HashSet<usize> = distinct.iter().cloned().collect();
+            assert_eq!(g.ancestors(node, 1).unwrap(), expected);
+
+            let mut previous = HashSet::new();
+            for depth in 0..5 {
+                let ancestors = g.ancestors(node, depth).unwrap();
+                assert!(ancestors.is_superset(&previous));
+                assert!(ancestors.iter().all(|a| *a <= node));
+                previous = ancestors;
+            }
+        }
+
+        assert!(g.ancestors(199, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn ancestors_sha256() {
+        ancestors::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn ancestors_blake2s() {
+        ancestors::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn ancestors_pedersen() {
+        ancestors::<PedersenHasher>();
+    }
+
+    #[test]
+    fn nodes_data() {
+        let g = BucketGraph::<PedersenHasher>::new(16, BASE_DEGREE, 0, new_seed()).unwrap();
+        let data: Vec<u8> = (0..g.expected_size()).map(|i| i as u8).collect();
+
+        let nodes = [0, 3, 3, 15];
+        let nodes_data = g.nodes_data(&data, &nodes).unwrap();
+        assert_eq!(nodes_data.len(), nodes.len());
+        for (node, node_data) in nodes.iter().zip(nodes_data) {
+            assert_eq!(node_data.len(), NODE_SIZE);
+            assert_eq!(node_data, data_at_node(&data, *node).unwrap());
+        }
+
+        assert!(g.nodes_data(&data, &[]).unwrap().is_empty());
+        assert!(g.nodes_data(&data, &[0, 16]).is_err());
+        assert!(g.nodes_data(&data[..NODE_SIZE], &[0]).is_err());
+    }
+
+    fn replication_memory_estimate<H: 'static + Hasher>() {
+        let small = BucketGraph::<H>::new(16, BASE_DEGREE, 0, new_seed()).unwrap();
+        let large = BucketGraph::<H>::new(64, BASE_DEGREE, 0, new_seed()).unwrap();
+
+        for g in &[&small, &large] {
+            assert_eq!(g.tree_size_estimate(), (2 * g.size() - 1) * NODE_SIZE);
+            assert!(g.replication_memory_estimate() >= g.expected_size());
+        }
+        assert!(large.replication_memory_estimate() > 3 * small.replication_memory_estimate());
+    }
+
+    #[test]
+    fn replication_memory_estimate_sha256() {
+        replication_memory_estimate::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn replication_memory_estimate_blake2s() {
+        replication_memory_estimate::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn replication_memory_estimate_pedersen() {
+        replication_memory_estimate::<PedersenHasher>();
+    }
+
+    #[test]
+    fn padded_leaf_count() {
+        let g = BucketGraph::<PedersenHasher>::new(200, BASE_DEGREE, 0, new_seed()).unwrap();
+        assert_eq!(g.padded_leaf_count(), 256);
+        assert_eq!(g.merkle_tree_depth(), 8);
+
+        // No padding needed.
+        let g = BucketGraph::<PedersenHasher>::new(256, BASE_DEGREE, 0, new_seed()).unwrap();
+        assert_eq!(g.padded_leaf_count(), g.size());
+        assert_eq!(g.merkle_tree_depth(), 8);
+    }
+
+    #[test]
+    fn check_data_len() {
+        let g = BucketGraph::<PedersenHasher>::new(16, BASE_DEGREE, 0, new_seed()).unwrap();
+
+        assert!(g.check_data_len(&vec![0u8; g.expected_size()]).is_ok());
+        for len in &[
+            0,
+            NODE_SIZE,
+            g.expected_size() - 1,
+            g.expected_size() + NODE_SIZE,
+        ] {
+            let err = g.check_data_len(&vec![0u8; *len]).unwrap_err();
+            match err.downcast::<Error>() {
+                Ok(Error::InvalidMerkleTreeArgs(actual, NODE_SIZE, 16)) => assert_eq!(actual, *len),
+                other => panic!("unexpected error: {:?}", other),
+            }
+        }
+
+        assert!(g.merkle_tree(&vec![0u8; g.expected_size() + 1]).is_err());
+    }
+
+    /// A graph whose edges point backwards: node `i` depends on `i + 1` and `i + 2`, and the last
+    /// node is the base node. If `cyclic` is set, the last node also depends on the first.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct BackwardGraph<H: Hasher> {
+        nodes: usize,
+        cyclic: bool,
+        _h: PhantomData<H>,
+    }
+
+    impl<H: Hasher> Graph<H> for BackwardGraph<H> {
+        type Key = H::Domain;
+
+        fn parents(&self, node: usize, parents: &mut [u32]) -> Result<()> {
+            for (i, parent) in parents.iter_mut().enumerate() {
+                *parent = cmp::min(node + 1 + i, self.nodes - 1) as u32;
+            }
+            if self.cyclic && node == self.nodes - 1 {
+                parents[0] = 0;
+            }
+
+            Ok(())
+        }
+
+        fn forward(&self) -> bool {
+            false
+        }
+
+        fn size(&self) -> usize {
+            self.nodes
+        }
+
+        fn degree(&self) -> usize {
+            2
+        }
+
+        fn new(nodes: usize, _: usize, _: usize, _: [u8; 28]) -> Result<Self> {
+            Ok(BackwardGraph {
+                nodes,
+                cyclic: false,
+                _h: PhantomData,
+            })
+        }
+
+        fn seed(&self) -> [u8; 28] {
+            [0; 28]
+        }
+
+        fn create_key(
+            &self,
+            _id: &H::Domain,
+            _node: usize,
+            _parents: &[u32],
+            _parents_data: &[u8],
+            _exp_parents_data: Option<&[u8]>,
+        ) -> Result<Self::Key> {
+            unimplemented!("not needed to order the nodes")
+        }
+    }
+
+    fn replication_order<H: Hasher>() {
+        let g = BucketGraph::<H>::new(200, BASE_DEGREE, 0, new_seed()).unwrap();
+        assert_eq!(
+            g.replication_order().unwrap(),
+            (0..g.size()).collect::<Vec<_>>()
+        );
+
+        let g = BackwardGraph::<H>::new(50, 0, 0, [0; 28]).unwrap();
+        let order = g.replication_order().unwrap();
+        assert_eq!(order[0], g.size() - 1);
+
+        let mut position = vec![None; g.size()];
+        for (i, node) in order.iter().enumerate() {
+            assert!(position[*node].is_none(), "node {} is repeated", node);
+            position[*node] = Some(i);
+        }
+
+        let mut parents = Vec::new();
+        for node in 0..g.size() {
+            g.distinct_parents(node, &mut parents).unwrap();
+            for parent in parents.iter().filter(|p| **p != node) {
+                assert!(position[*parent] < position[node]);
+            }
+        }
+
+        let cached = CachedGraph::from_graph(g.clone()).unwrap();
+        assert!(!cached.forward());
+        assert_eq!(cached.replication_order().unwrap(), order);
+
+        let cyclic = BackwardGraph::<H> { cyclic: true, ..g };
+        match cyclic.replication_order() {
+            Err(err) => match err.downcast::<Error>() {
+                Ok(Error::CyclicGraph) => {}
+                other => panic!("unexpected error: {:?}", other),
+            },
+            Ok(order) => panic!("ordered a cyclic graph: {:?}", order),
+        }
+    }
+
+    #[test]
+    fn replication_order_sha256() {
+        replication_order::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn replication_order_blake2s() {
+        replication_order::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn replication_order_pedersen() {
+        replication_order::<PedersenHasher>();
+    }
+
+    fn adjacency<H: Hasher>() {
+        let g = BucketGraph::<H>::new(200, BASE_DEGREE, 0, new_seed()).unwrap();
+
+        let mut buf = Vec::new();
+        g.write_adjacency(&mut buf).unwrap();
+        let adjacency = read_adjacency(&mut buf.as_slice()).unwrap();
+
+        assert_eq!(adjacency.size(), g.size());
+        assert_eq!(adjacency.degree(), g.degree());
+
+        let mut parents = vec![0; g.degree()];
+        for node in 0..g.size() {
+            g.parents(node, &mut parents).unwrap();
+            assert_eq!(adjacency.parents(node).unwrap(), &parents[..]);
+        }
+        assert!(adjacency.parents(g.size()).is_err());
+
+        // truncated input
+        assert!(read_adjacency(&mut &buf[..buf.len() - 1]).is_err());
+
+        // a header claiming more parents than fit in memory, or in a usize
+        for &(size, degree) in &[(u64::max_value(), 1), (1 << 40, 1 << 40)] {
+            let mut header = Vec::new();
+            write_varint(&mut header, size).unwrap();
+            write_varint(&mut header, degree).unwrap();
+            assert!(read_adjacency(&mut header.as_slice()).is_err());
+        }
+    }
+
+    #[test]
+    fn adjacency_sha256() {
+        adjacency::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn adjacency_blake2s() {
+        adjacency::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn adjacency_pedersen() {
+        adjacency::<PedersenHasher>();
+    }
+
+    /// Checks that `parents` fills exactly `degree()` entries of a buffer of that size.
+    fn parents_fill_degree<H: Hasher, G: Graph<H>>(g: &G) {
+        assert_eq!(g.base_degree() + g.expansion_degree(), g.degree());
+
+        for node in 0..g.size() {
+            let mut parents = vec![u32::max_value(); g.degree()];
+            g.parents(node, &mut parents).unwrap();
+
+            assert!(
+                parents.iter().all(|p| (*p as usize) < g.size()),
+                "node {} has unfilled or out of range parents: {:?}",
+                node,
+                parents
+            );
+        }
+    }
+
+    fn degree_consistent<H: 'static + Hasher>() {
+        let nodes = 64;
+
+        let bucket = BucketGraph::<H>::new(nodes, BASE_DEGREE, 0, new_seed()).unwrap();
+        assert_eq!(bucket.degree(), BASE_DEGREE);
+        assert_eq!(bucket.expansion_degree(), 0);
+        parents_fill_degree(&bucket);
+
+        let cached = CachedGraph::<H, BucketGraph<H>>::from_graph(bucket).unwrap();
+        parents_fill_degree(&cached);
+
+        let backward = BackwardGraph::<H>::new(nodes, 0, 0, new_seed()).unwrap();
+        parents_fill_degree(&backward);
+
+        let stacked = crate::stacked::StackedBucketGraph::<H>::new(
+            nodes,
+            BASE_DEGREE,
+            crate::stacked::EXP_DEGREE,
+            new_seed(),
+        )
+        .unwrap();
+        assert_eq!(stacked.degree(), BASE_DEGREE + crate::stacked::EXP_DEGREE);
+        assert_eq!(
+            Graph::<H>::expansion_degree(&stacked),
+            crate::stacked::EXP_DEGREE
+        );
+        parents_fill_degree(&stacked);
+
+        let stacked_old = crate::stacked_old::StackedBucketGraph::<H>::new(
+            nodes,
+            BASE_DEGREE,
+            crate::stacked_old::EXP_DEGREE,
+            new_seed(),
+        )
+        .unwrap();
+        assert_eq!(
+            stacked_old.degree(),
+            BASE_DEGREE + crate::stacked_old::EXP_DEGREE
+        );
+        parents_fill_degree(&stacked_old);
+    }
+
+    #[test]
+    fn degree_consistent_sha256() {
+        degree_consistent::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn degree_consistent_blake2s() {
+        degree_consistent::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn degree_consistent_pedersen() {
+        degree_consistent::<PedersenHasher>();
+    }
+
+    fn parent_samplers<H: Hasher>() {
+        let nodes = 128;
+        let seed = new_seed();
+
+        let graph = |sampler| {
+            BucketGraph::<H>::new_with_sampler(nodes, BASE_DEGREE, 0, seed, sampler).unwrap()
+        };
+        let all_parents = |g: &BucketGraph<H>| {
+            let mut all = Vec::with_capacity(nodes * g.degree());
+            let mut parents = vec![0; g.degree()];
+            for node in 0..nodes {
+                g.parents(node, &mut parents).unwrap();
+                for parent in &parents {
+                    assert!(
+                        (*parent as usize) < node || node < 2,
+                        "invalid parent {} of node {}",
+                        parent,
+                        node
+                    );
+                }
+                all.extend_from_slice(&parents);
+            }
+            all
+        };
+
+        let chacha = graph(ParentSampler::ChaCha);
+        let blake2s = graph(ParentSampler::Blake2s);
+
+        // ChaCha stays the default.
+        assert_eq!(
+            chacha,
+            BucketGraph::<H>::new(nodes, BASE_DEGREE, 0, seed).unwrap()
+        );
+
+        // Both are deterministic.
+        assert_eq!(
+            all_parents(&chacha),
+            all_parents(&graph(ParentSampler::ChaCha))
+        );
+        assert_eq!(
+            all_parents(&blake2s),
+            all_parents(&graph(ParentSampler::Blake2s))
+        );
+
+        // But sample different parents, and are told apart by their identifiers.
+        assert_ne!(all_parents(&chacha), all_parents(&blake2s));
+        assert_ne!(chacha.identifier(), blake2s.identifier());
+        assert_eq!(
+            chacha.identifier(),
+            format!(
+                "drgraph::BucketGraph{{size: {}; degree: {}; hasher: {}}}",
+                nodes,
+                BASE_DEGREE,
+                H::name()
+            )
+        );
+    }
+
+    #[test]
+    fn parent_samplers_sha256() {
+        parent_samplers::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn parent_samplers_blake2s() {
+        parent_samplers::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn parent_samplers_pedersen() {
+        parent_samplers::<PedersenHasher>();
+    }
+
+    #[test]
+    fn blake2s_parent_sampler_golden() {
+        // Computed independently from the description of `ParentSampler::Blake2s`. These must
+        // not change, or graphs sampled with blake2s change.
+        let golden: &[(usize, [u32; BASE_DEGREE])] = &[
+            (0, [0, 0, 0, 0, 0, 0]),
+            (2, [0, 1, 1, 1, 1, 1]),
+            (3, [2, 2, 2, 2, 2, 2]),
+            (10, [6, 9, 9, 6, 9, 9]),
+            (100, [95, 98, 57, 99, 99, 99]),
+            (1000, [956, 999, 838, 385, 999, 999]),
+        ];
+
+        let g = BucketGraph::<Sha256Hasher>::new_with_sampler(
+            1001,
+            BASE_DEGREE,
+            0,
+            [7; 28],
+            ParentSampler::Blake2s,
+        )
+        .unwrap();
+        let mut parents = [0; BASE_DEGREE];
+        for (node, expected) in golden {
+            g.parents(*node, &mut parents).unwrap();
+            assert_eq!(&parents, expected, "parents of node {}", node);
+        }
+    }
+
+    fn parents_overflow<H: Hasher>() {
+        let m_prime = BASE_DEGREE - 1;
+        let assert_overflow = |g: &BucketGraph<H>, node: usize| {
+            let mut parents = vec![0; g.degree()];
+            match g.parents(node, &mut parents) {
+                Ok(_) => panic!("node {} must not get parents: {:?}", node, parents),
+                Err(err) => match err.downcast::<Error>() {
+                    Ok(Error::ParentOverflow { node: n }) => assert_eq!(n, node),
+                    other => panic!("unexpected error: {:?}", other),
+                },
+            }
+        };
+
+        // The graph is never materialized, so its size does not matter.
+        let g = BucketGraph::<H>::new(usize::max_value(), BASE_DEGREE, 0, new_seed()).unwrap();
+
+        // The parents do not fit into `u32`.
+        assert_overflow(&g, u32::max_value() as usize + 2);
+        // Neither does the meta node index `node * m_prime`.
+        assert_overflow(&g, usize::max_value() / m_prime + 1);
+
+        // The largest node whose parents are representable still works.
+        let node = u32::max_value() as usize + 1;
+        let mut parents = vec![0; g.degree()];
+        g.parents(node, &mut parents).unwrap();
+        assert_eq!(parents[m_prime], u32::max_value());
+    }
+
+    #[test]
+    fn parents_overflow_sha256() {
+        parents_overflow::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn parents_overflow_blake2s() {
+        parents_overflow::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn parents_overflow_pedersen() {
+        parents_overflow::<PedersenHasher>();
+    }
+
+    fn merkle_tree_checked<H: Hasher>() {
+        let g = BucketGraph::<H>::new(16, BASE_DEGREE, 0, new_seed()).unwrap();
+        let zeros = vec![0u8; g.expected_size()];
+
+        match g.merkle_tree_checked(&zeros, true) {
+            Err(err) => match err.downcast::<Error>() {
+                Ok(Error::SuspiciousAllZeroData) => {}
+                res => panic!("unexpected error: {:?}", res),
+            },
+            Ok(_) => panic!("all-zero data must be rejected"),
+        }
+        assert!(g.merkle_tree_checked(&zeros, false).is_ok());
+
+        let mut data = zeros.clone();
+        data[0] = 1;
+        assert!(g.merkle_tree_checked(&data, true).is_ok());
+    }
+
+    fn merkle_tree_with_leaves<H: Hasher>() {
+        let g = BucketGraph::<H>::new(16, BASE_DEGREE, 0, new_seed()).unwrap();
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = (0..g.size())
+            .flat_map(|_| H::Domain::random(&mut rng).into_bytes())
+            .collect();
+
+        let (tree, leaves) = g.merkle_tree_with_leaves(&data).unwrap();
+        assert_eq!(tree.root(), g.merkle_tree(&data).unwrap().root());
+        assert_eq!(leaves.len(), g.size());
+
+        let rebuilt: MerkleTree<H::Domain, H::Function> =
+            MerkleTree::try_from_iter(leaves.into_iter().map(Ok)).unwrap();
+        assert_eq!(rebuilt.root(), tree.root());
+
+        assert!(g.merkle_tree_with_leaves(&data[1..]).is_err());
+    }
+
+    #[test]
+    fn merkle_tree_with_leaves_sha256() {
+        merkle_tree_with_leaves::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn merkle_tree_with_leaves_blake2s() {
+        merkle_tree_with_leaves::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn merkle_tree_with_leaves_pedersen() {
+        merkle_tree_with_leaves::<PedersenHasher>();
+    }
+
+    #[test]
+    fn merkle_tree_checked_sha256() {
+        merkle_tree_checked::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn merkle_tree_checked_blake2s() {
+        merkle_tree_checked::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn merkle_tree_checked_pedersen() {
+        merkle_tree_checked::<PedersenHasher>();
+    }
+
+    #[test]
+    fn test_sample_challenges() {
+        let g = BucketGraph::<PedersenHasher>::new(1024, BASE_DEGREE, 0, new_seed()).unwrap();
+        let count = 10_000;
+
+        let rng = &mut seeded_rng();
+        let uniform = g
+            .sample_challenges(rng, count, ChallengeWeight::Uniform)
+            .unwrap();
+        assert_eq!(uniform.len(), count);
+        assert!(uniform.iter().all(|&c| c < g.size()));
+
+        let rng = &mut seeded_rng();
+        let expected: Vec<usize> = (0..count).map(|_| rng.gen_range(0, g.size())).collect();
+        assert_eq!(uniform, expected);
+
+        let biased = g
+            .sample_challenges(rng, count, ChallengeWeight::DepthBiased)
+            .unwrap();
+        assert!(biased.iter().all(|&c| c < g.size()));
+
+        // The expected mean is 1/2 of the size for uniform and 2/3 for depth biased sampling.
+        let mean = |c: &[usize]| c.iter().sum::<usize>() as f64 / c.len() as f64;
+        let size = g.size() as f64;
+        assert!((mean(&uniform) / size - 0.5).abs() < 0.05);
+        assert!(mean(&biased) / size > 0.6);
+
+        let empty = BucketGraph::<PedersenHasher>::new(0, BASE_DEGREE, 0, new_seed()).unwrap();
+        assert!(empty
+            .sample_challenges(rng, 1, ChallengeWeight::Uniform)
+            .is_err());
+    }
+
+    #[test]
+    fn test_try_new_seed() {
+        let a = try_new_seed().unwrap();
+        let b = try_new_seed().unwrap();
+
+        assert_ne!(a, b, "seeds must be random");
+    }
+
+    fn assert_graph<H: Hasher, G: Graph<H>>(_: &G) {}
+
+    fn cached_graph<H: Hasher>() {
+        let degree = BASE_DEGREE;
+        let g = BucketGraph::<H>::new(200, degree, 0, new_seed()).unwrap();
+        let cached = CachedGraph::from_graph(g.clone()).unwrap();
+
+        assert_graph::<H, _>(&cached);
+        assert_eq!(cached.size(), g.size());
+        assert_eq!(cached.degree(), g.degree());
+
+        for node in 0..g.size() {
+            let mut expected = vec![0; degree];
+            g.parents(node, &mut expected).unwrap();
+            let mut actual = vec![0; degree];
+            cached.parents(node, &mut actual).unwrap();
+
+            assert_eq!(expected, actual, "different parents for node {}", node);
+        }
+
+        let mut parents = vec![0; degree];
+        assert!(cached.parents(g.size(), &mut parents).is_err());
+    }
+
+    #[test]
+    fn cached_graph_sha256() {
+        cached_graph::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn cached_graph_blake2s() {
+        cached_graph::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn cached_graph_pedersen() {
+        cached_graph::<PedersenHasher>();
+    }
+
+    fn gen_proof<H: Hasher>() {
+        let g = BucketGraph::<H>::new(5, BASE_DEGREE, 0, new_seed()).unwrap();
         let data = vec![2u8; NODE_SIZE * 5];
 
         let mmapped = &mmap_from(&data);
@@ -325,4 +1729,108 @@ mod tests {
     fn gen_proof_blake2s() {
         gen_proof::<Blake2sHasher>();
     }
+
+    fn tree_cache_key<H: Hasher>() {
+        let seed = new_seed();
+        let g = BucketGraph::<H>::new(16, BASE_DEGREE, 0, seed).unwrap();
+        let data = vec![2u8; NODE_SIZE * 16];
+        let key = g.tree_cache_key(&data);
+
+        assert_eq!(key, g.tree_cache_key(&data));
+        assert_eq!(key, g.clone().tree_cache_key(&data));
+
+        let mut other_data = data.clone();
+        other_data[0] = 3;
+        assert_ne!(key, g.tree_cache_key(&other_data));
+
+        let bigger = BucketGraph::<H>::new(32, BASE_DEGREE, 0, seed).unwrap();
+        let other_sampler =
+            BucketGraph::<H>::new_with_sampler(16, BASE_DEGREE, 0, seed, ParentSampler::Blake2s)
+                .unwrap();
+        let other_hasher = BucketGraph::<Blake2sHasher>::new(16, BASE_DEGREE, 0, seed).unwrap();
+        assert_ne!(key, bigger.tree_cache_key(&vec![2u8; NODE_SIZE * 32]));
+        assert_ne!(key, other_sampler.tree_cache_key(&data));
+        if H::name() != Blake2sHasher::name() {
+            assert_ne!(key, other_hasher.tree_cache_key(&data));
+        }
+
+        // The cached tree is reused, and matches the one built in memory.
+        let dir = tempfile::tempdir().unwrap();
+        let tree = g.merkle_tree_cached(&data, dir.path()).unwrap();
+        assert_eq!(tree.root(), g.merkle_tree(&data).unwrap().root());
+        let cached_files = std::fs::read_dir(dir.path()).unwrap().count();
+        assert!(cached_files > 0, "no cache file written");
+
+        let reloaded = g.merkle_tree_cached(&data, dir.path()).unwrap();
+        assert_eq!(reloaded.root(), tree.root());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), cached_files);
+    }
+
+    fn merkle_tree_cached_rebuilds_bad_cache<H: Hasher>() {
+        let g = BucketGraph::<H>::new(16, BASE_DEGREE, 0, new_seed()).unwrap();
+        let data = vec![2u8; NODE_SIZE * 16];
+        let root = g.merkle_tree(&data).unwrap().root();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(g.tree_cache_key(&data));
+        g.merkle_tree_cached(&data, dir.path()).unwrap();
+        let cached = std::fs::read(&path).unwrap();
+
+        // A truncated cache file, as left behind by a crashed build.
+        std::fs::write(&path, &cached[..cached.len() / 2]).unwrap();
+        assert_eq!(
+            g.merkle_tree_cached(&data, dir.path()).unwrap().root(),
+            root
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), cached);
+
+        // A cache file with a corrupted node.
+        let mut corrupted = cached.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
+        std::fs::write(&path, &corrupted).unwrap();
+        assert_eq!(
+            g.merkle_tree_cached(&data, dir.path()).unwrap().root(),
+            root
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), cached);
+    }
+
+    #[test]
+    fn merkle_tree_cached_rebuilds_bad_cache_pedersen() {
+        merkle_tree_cached_rebuilds_bad_cache::<PedersenHasher>();
+    }
+
+    #[test]
+    fn merkle_tree_cached_rebuilds_bad_cache_sha256() {
+        merkle_tree_cached_rebuilds_bad_cache::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn merkle_tree_cached_rebuilds_bad_cache_blake2s() {
+        merkle_tree_cached_rebuilds_bad_cache::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn merkle_tree_cached_empty_graph() {
+        let g = BucketGraph::<PedersenHasher>::new(0, BASE_DEGREE, 0, new_seed()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(g.merkle_tree_cached(&[], dir.path()).is_err());
+    }
+
+    #[test]
+    fn tree_cache_key_pedersen() {
+        tree_cache_key::<PedersenHasher>();
+    }
+
+    #[test]
+    fn tree_cache_key_sha256() {
+        tree_cache_key::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn tree_cache_key_blake2s() {
+        tree_cache_key::<Blake2sHasher>();
+    }
 }
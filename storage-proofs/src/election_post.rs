@@ -9,6 +9,7 @@ use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+use crate::challenge_domain::ChallengeDomain;
 use crate::crypto::pedersen::{pedersen_md_no_padding_bits, Bits};
 use crate::drgraph::graph_height;
 use crate::error::{Error, Result};
@@ -245,6 +246,7 @@ pub fn generate_sector_challenge(
     sectors: &OrderedSectorSet,
 ) -> Result<SectorId> {
     let mut hasher = Sha256::new();
+    hasher.input(ChallengeDomain::PoSt.tag());
     hasher.input(&randomness[..]);
     hasher.input(&n.to_le_bytes()[..]);
     let hash = hasher.result();
@@ -295,6 +297,7 @@ pub fn generate_leaf_challenge(
     );
 
     let mut hasher = Sha256::new();
+    hasher.input(ChallengeDomain::PoSt.tag());
     hasher.input(&randomness[..]);
     hasher.input(&sector_challenge_index.to_le_bytes()[..]);
     hasher.input(&leaf_challenge_index.to_le_bytes()[..]);
@@ -423,15 +426,15 @@ mod tests {
     use super::*;
     use ff::Field;
     use paired::bls12_381::{Bls12, Fr};
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
 
     use crate::drgraph::{new_seed, BucketGraph, Graph, BASE_DEGREE};
     use crate::fr32::fr_into_bytes;
     use crate::hasher::{Blake2sHasher, PedersenHasher, Sha256Hasher};
+    use crate::test_helper::seeded_rng;
 
     fn test_election_post<H: Hasher>() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let leaves = 32;
         let sector_size = leaves * 32;
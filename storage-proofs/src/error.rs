@@ -13,6 +13,22 @@ pub enum Error {
     BadPieceCommitment,
     #[error("Out of bounds access {} > {}", _0, _1)]
     OutOfBounds(usize, usize),
+    #[error("challenge {challenge} out of range for {leaves} leaves")]
+    ChallengeOutOfRange { challenge: usize, leaves: usize },
+    #[error("proof path has {got} elements, the tree requires {expected}")]
+    ProofPathLengthMismatch { expected: usize, got: usize },
+    #[error("invalid base64 encoded domain element: {}", _0)]
+    InvalidBase64(String),
+    #[error("invalid hex encoded domain element: {}", _0)]
+    InvalidHex(String),
+    #[error("invalid sector id: {}", _0)]
+    InvalidSectorId(String),
+    #[error("graph contains a cycle")]
+    CyclicGraph,
+    #[error("parents of node {node} overflow the graph index range")]
+    ParentOverflow { node: usize },
+    #[error("unsupported merkle tree arity {}", _0)]
+    UnsupportedArity(usize),
     #[error("mismatch of data, node_size and nodes {} != {} * {}", _0, _1, _2)]
     InvalidMerkleTreeArgs(usize, usize, usize),
     #[error("{}", _0)]
@@ -21,14 +37,34 @@ pub enum Error {
     Io(#[from] ::std::io::Error),
     #[error("tree root and commitment do not match")]
     InvalidCommitment,
+    #[error("replica was created with a different replica id")]
+    ReplicaIdMismatch,
+    #[error("proof of partition {} is invalid", _0)]
+    InvalidPartitionProof(usize),
+    #[error("operation was cancelled")]
+    Cancelled,
+    #[error("commitment is not a canonical field element")]
+    NonCanonicalCommitment,
     #[error("malformed input")]
     MalformedInput,
     #[error("malformed merkle tree")]
     MalformedMerkleTree,
     #[error("invalid input size")]
     InvalidInputSize,
+    #[error("input of {got} bytes is too short, at least {min} bytes are required")]
+    InputTooShort { got: usize, min: usize },
+    #[error("input of {got} bytes is not a multiple of the block size {block}")]
+    InputNotBlockAligned { got: usize, block: usize },
+    #[error("merkle tree store is corrupted")]
+    TreeCorrupted,
+    #[error("merkle tree store was built with {found}, expected {expected}")]
+    HasherMismatch { expected: String, found: String },
+    #[error("refusing to build a merkle tree over all-zero data")]
+    SuspiciousAllZeroData,
     #[error("merkle tree generation error: {}", _0)]
     MerkleTreeGenerationError(String),
+    #[error("pieces take {total} bytes, but the sector only holds {capacity} bytes")]
+    PiecesExceedSectorSize { total: u64, capacity: u64 },
     #[error("Cannot (yet) generate inclusion proof for unaligned piece.")]
     UnalignedPiece,
     #[error("{}", _0)]
@@ -1,6 +1,6 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::stderr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use bellperson::groth16::*;
@@ -10,12 +10,12 @@ use fil_sapling_crypto::jubjub::{JubjubBls12, JubjubEngine};
 use log::info;
 use paired::bls12_381::Bls12;
 use pbr::ProgressBar;
-use rand::{Rng, SeedableRng};
-use rand_xorshift::XorShiftRng;
+use rand::Rng;
 
 use crate::circuit::bench::BenchCS;
 use crate::circuit::test::TestConstraintSystem;
 use crate::crypto::pedersen::JJ_PARAMS;
+use crate::test_helper::seeded_rng;
 
 pub fn prettyb(num: usize) -> String {
     let num = num as f64;
@@ -38,15 +38,50 @@ pub fn prettyb(num: usize) -> String {
     format!("{}{} {}", negative, pretty_bytes, unit)
 }
 
-/// Generate a unique cache path, based on the inputs.
-fn get_cache_path(name: &str, data_size: usize, challenge_count: usize, m: usize) -> String {
-    format!(
-        "/tmp/filecoin-proofs-cache-{}-{}-{}-{}",
+/// Generate a unique cache path in `out_dir`, based on the inputs.
+fn get_cache_path(
+    out_dir: &Path,
+    name: &str,
+    data_size: usize,
+    challenge_count: usize,
+    m: usize,
+) -> PathBuf {
+    out_dir.join(format!(
+        "filecoin-proofs-cache-{}-{}-{}-{}",
         name.to_ascii_lowercase(),
         data_size,
         challenge_count,
         m,
-    )
+    ))
+}
+
+/// The path the last proof is written to, next to the cached parameters.
+fn get_proof_path(cache_path: &Path) -> PathBuf {
+    let mut file_name = cache_path
+        .file_name()
+        .expect("cache path has a file name")
+        .to_os_string();
+    file_name.push("-proof");
+
+    cache_path.with_file_name(file_name)
+}
+
+/// Where the groth parameters and proofs generated by an example end up.
+#[derive(Debug, Clone)]
+pub struct OutputOpts {
+    /// The directory the artifacts are written to, the system temp dir by default.
+    pub out_dir: PathBuf,
+    /// Remove the artifacts once the example is done, instead of keeping them as a cache.
+    pub clean: bool,
+}
+
+impl Default for OutputOpts {
+    fn default() -> Self {
+        OutputOpts {
+            out_dir: std::env::temp_dir(),
+            clean: false,
+        }
+    }
 }
 
 /// The available circuit types for benchmarking.
@@ -57,11 +92,51 @@ pub enum CSType {
     Circuit,
 }
 
+/// Time spent in the phases of groth parameter generation.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ParamsTimings {
+    /// Synthesizing the circuit.
+    pub synthesis: Duration,
+    /// Computing the parameters from the synthesized circuit (FFTs and multiexponentiations).
+    pub parameters: Duration,
+}
+
+/// The size of a synthesized circuit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitCounts {
+    pub constraints: usize,
+    /// Public inputs, including the constant one input.
+    pub inputs: usize,
+    pub aux: usize,
+}
+
+/// Synthesizes `circuit` on a `BenchCS` and returns its size. Missing assignments are fine, so
+/// this works on the same circuits as parameter generation.
+pub fn count_circuit<C: Circuit<Bls12>>(circuit: C) -> CircuitCounts {
+    let mut cs = BenchCS::<Bls12>::new();
+    circuit
+        .synthesize(&mut cs)
+        .expect("failed to synthesize circuit");
+
+    CircuitCounts {
+        constraints: cs.num_constraints(),
+        inputs: cs.num_inputs(),
+        aux: cs.num_aux(),
+    }
+}
+
 /// A trait that makes it easy to implement "Examples". These are really tunable benchmarking CLI tools.
 pub trait Example<'a, C: Circuit<Bls12>>: Default {
     /// The actual work.
-    fn work_groth(&mut self, typ: CSType, data_size: usize, challenge_count: usize, m: usize) {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+    fn work_groth(
+        &mut self,
+        typ: CSType,
+        data_size: usize,
+        challenge_count: usize,
+        m: usize,
+        out: &OutputOpts,
+    ) {
+        let rng = &mut seeded_rng();
 
         let leaves = data_size / 32;
         let tree_depth = (leaves as f64).log2().ceil() as usize;
@@ -78,15 +153,25 @@ pub trait Example<'a, C: Circuit<Bls12>>: Default {
         let name = Self::name();
 
         // caching
-        let p = get_cache_path(&name, data_size, challenge_count, m);
-        let cache_path = Path::new(&p);
+        info!("out_dir: {:?}", out.out_dir);
+        fs::create_dir_all(&out.out_dir).expect("failed to create out dir");
+        let cache_path = &get_cache_path(&out.out_dir, &name, data_size, challenge_count, m);
         let groth_params: Parameters<Bls12> = if cache_path.exists() {
             info!("reading groth params from cache: {:?}", cache_path);
             let f = File::open(&cache_path).expect("failed to read cache");
             Parameters::read(&f, false).expect("failed to read cached params")
         } else {
             info!("generating new groth params");
-            let p = self.generate_groth_params(rng, &JJ_PARAMS, tree_depth, challenge_count, m);
+            let (p, timings) = self.generate_groth_params_timed(
+                rng,
+                &JJ_PARAMS,
+                tree_depth,
+                challenge_count,
+                leaves,
+                m,
+            );
+            info!("params_synthesis_time: {:?}", timings.synthesis);
+            info!("params_computation_time: {:?}", timings.parameters);
             info!("writing params to cache: {:?}", cache_path);
 
             let mut f = File::create(&cache_path).expect("faild to open cache file");
@@ -154,10 +239,20 @@ pub trait Example<'a, C: Circuit<Bls12>>: Default {
         info!("avg_proving_time: {:?} seconds", proving_avg);
         info!("avg_verifying_time: {:?} seconds", verifying_avg);
         info!("params_generation_time: {:?}", param_duration);
+
+        let proof_path = get_proof_path(cache_path);
+        info!("writing last proof to: {:?}", proof_path);
+        fs::write(&proof_path, &proof_vec).expect("failed to write proof");
+
+        if out.clean {
+            info!("removing generated params and proof");
+            fs::remove_file(cache_path).expect("failed to remove cached params");
+            fs::remove_file(&proof_path).expect("failed to remove proof");
+        }
     }
 
     fn work_bench(&mut self, typ: CSType, data_size: usize, challenge_count: usize, m: usize) {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let leaves = data_size / 32;
         let tree_depth = (leaves as f64).log2().ceil() as usize;
@@ -202,7 +297,7 @@ pub trait Example<'a, C: Circuit<Bls12>>: Default {
     }
 
     fn work_circuit(&mut self, typ: CSType, data_size: usize, challenge_count: usize, m: usize) {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let leaves = data_size / 32;
         let tree_depth = (leaves as f64).log2().ceil() as usize;
@@ -245,6 +340,17 @@ pub trait Example<'a, C: Circuit<Bls12>>: Default {
                     .default_value("6")
                     .takes_value(true),
             )
+            .arg(
+                Arg::with_name("out-dir")
+                    .long("out-dir")
+                    .help("Where to write generated params and proofs, defaults to the temp dir")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("clean")
+                    .long("clean")
+                    .help("Remove the generated params and proofs when done"),
+            )
             .subcommand(
                 SubCommand::with_name("groth")
                     .about("execute circuits using groth constraint system"),
@@ -260,7 +366,7 @@ pub trait Example<'a, C: Circuit<Bls12>>: Default {
     fn main() {
         let mut instance = Self::default();
 
-        let (data_size, challenge_count, m, typ) = {
+        let (data_size, challenge_count, m, typ, out) = {
             let matches = instance.clap();
 
             let data_size = value_t!(matches, "size", usize)
@@ -275,12 +381,17 @@ pub trait Example<'a, C: Circuit<Bls12>>: Default {
                 Some("circuit") => CSType::Circuit,
                 _ => panic!("please select a valid subcommand"),
             };
+            let mut out = OutputOpts::default();
+            if let Some(out_dir) = matches.value_of("out-dir") {
+                out.out_dir = PathBuf::from(out_dir);
+            }
+            out.clean = matches.is_present("clean");
 
-            (data_size, challenge_count, m, typ)
+            (data_size, challenge_count, m, typ, out)
         };
 
         match typ {
-            CSType::Groth => instance.work_groth(typ, data_size, challenge_count, m),
+            CSType::Groth => instance.work_groth(typ, data_size, challenge_count, m, &out),
             CSType::Bench => instance.work_bench(typ, data_size, challenge_count, m),
             CSType::Circuit => instance.work_circuit(typ, data_size, challenge_count, m),
         }
@@ -299,6 +410,42 @@ pub trait Example<'a, C: Circuit<Bls12>>: Default {
         _: usize,
     ) -> Parameters<Bls12>;
 
+    /// Like `generate_groth_params`, but also reports how the time is split between synthesizing
+    /// the circuit and computing the parameters. Synthesis is measured separately on a `BenchCS`
+    /// and subtracted from the total, which makes it an estimate, but good enough to tell whether
+    /// the circuit or the crypto dominates. The size of the circuit is logged before the
+    /// parameters are computed, so unexpectedly large circuits are noticed early.
+    #[allow(clippy::too_many_arguments)]
+    fn generate_groth_params_timed<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        engine_params: &'a <Bls12 as JubjubEngine>::Params,
+        tree_depth: usize,
+        challenge_count: usize,
+        leaves: usize,
+        m: usize,
+    ) -> (Parameters<Bls12>, ParamsTimings) {
+        let start = Instant::now();
+        let c = self.create_circuit(rng, engine_params, tree_depth, challenge_count, leaves, m);
+        let counts = count_circuit(c);
+        let synthesis = start.elapsed();
+
+        info!("circuit_constraints: {}", counts.constraints);
+        info!("circuit_inputs: {}", counts.inputs);
+        info!("circuit_aux: {}", counts.aux);
+
+        let start = Instant::now();
+        let params = self.generate_groth_params(rng, engine_params, tree_depth, challenge_count, m);
+        let total = start.elapsed();
+
+        let timings = ParamsTimings {
+            synthesis,
+            parameters: total.checked_sub(synthesis).unwrap_or_default(),
+        };
+
+        (params, timings)
+    }
+
     /// How many samples should be taken when proofing and verifying
     fn samples() -> usize;
 
@@ -351,3 +498,133 @@ pub trait Example<'a, C: Circuit<Bls12>>: Default {
         cs.num_constraints()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bellperson::{ConstraintSystem, SynthesisError};
+    use ff::Field;
+    use paired::bls12_381::Fr;
+
+    /// Proves knowledge of the square root of the public input.
+    struct Square {
+        x: Option<Fr>,
+    }
+
+    impl Circuit<Bls12> for Square {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), SynthesisError> {
+            let x_val = self.x;
+            let x = cs.alloc(|| "x", || x_val.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(
+                || "y",
+                || {
+                    let mut y = x_val.ok_or(SynthesisError::AssignmentMissing)?;
+                    y.square();
+                    Ok(y)
+                },
+            )?;
+            cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct SquareExample;
+
+    impl<'a> Example<'a, Square> for SquareExample {
+        fn name() -> String {
+            "Square".into()
+        }
+
+        fn generate_groth_params<R: Rng>(
+            &mut self,
+            rng: &mut R,
+            _: &'a <Bls12 as JubjubEngine>::Params,
+            _: usize,
+            _: usize,
+            _: usize,
+        ) -> Parameters<Bls12> {
+            generate_random_parameters(Square { x: None }, rng).expect("failed to generate params")
+        }
+
+        fn samples() -> usize {
+            1
+        }
+
+        fn create_circuit<R: Rng>(
+            &mut self,
+            rng: &mut R,
+            _: &'a <Bls12 as JubjubEngine>::Params,
+            _: usize,
+            _: usize,
+            _: usize,
+            _: usize,
+        ) -> Square {
+            Square {
+                x: Some(Fr::random(rng)),
+            }
+        }
+
+        fn verify_proof(
+            &mut self,
+            _: &Proof<Bls12>,
+            _: &PreparedVerifyingKey<Bls12>,
+        ) -> Option<bool> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_work_groth_out_dir() {
+        let out_dir = tempfile::tempdir().unwrap();
+        let cache_path = get_cache_path(out_dir.path(), "Square", 32, 1, 6);
+        let proof_path = get_proof_path(&cache_path);
+
+        let mut out = OutputOpts {
+            out_dir: out_dir.path().to_path_buf(),
+            clean: false,
+        };
+        SquareExample.work_groth(CSType::Groth, 32, 1, 6, &out);
+        assert!(cache_path.is_file(), "params not written to out dir");
+        assert!(proof_path.is_file(), "proof not written to out dir");
+
+        out.clean = true;
+        SquareExample.work_groth(CSType::Groth, 32, 1, 6, &out);
+        assert!(!cache_path.exists(), "params not cleaned up");
+        assert!(!proof_path.exists(), "proof not cleaned up");
+        assert_eq!(fs::read_dir(out_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_generate_groth_params_timed() {
+        let rng = &mut seeded_rng();
+
+        let (_, timings) = SquareExample.generate_groth_params_timed(rng, &JJ_PARAMS, 0, 1, 1, 6);
+
+        assert!(timings.synthesis > Duration::new(0, 0));
+        assert!(timings.parameters > Duration::new(0, 0));
+    }
+
+    #[test]
+    fn test_count_circuit_matches_test_cs() {
+        let rng = &mut seeded_rng();
+        let x = Some(Fr::random(rng));
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        Square { x }.synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+
+        let counts = count_circuit(Square { x });
+        assert_eq!(counts.constraints, cs.num_constraints());
+        assert_eq!(counts.inputs, cs.num_inputs());
+        assert_eq!(counts.aux, cs.num_aux());
+
+        // Parameter generation synthesizes without assignments, which must not change the size.
+        assert_eq!(count_circuit(Square { x: None }), counts);
+    }
+}
@@ -1,3 +1,4 @@
+use std::convert::TryFrom;
 use std::fmt;
 use std::hash::Hasher as StdHasher;
 
@@ -13,7 +14,7 @@ use paired::bls12_381::{Bls12, Fr, FrRepr};
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
 
-use super::{Domain, HashFunction, Hasher};
+use super::{node_preimage, Domain, HashFunction, Hasher};
 use crate::crypto::sloth;
 use crate::error::*;
 
@@ -24,6 +25,8 @@ impl Hasher for Blake2sHasher {
     type Domain = Blake2sDomain;
     type Function = Blake2sFunction;
 
+    const NODE_HASH_CONSTRAINTS: usize = 21_006;
+
     fn name() -> String {
         "Blake2sHasher".into()
     }
@@ -101,8 +104,33 @@ impl AsRef<Blake2sDomain> for Blake2sDomain {
 
 impl Blake2sDomain {
     pub fn trim_to_fr32(&mut self) {
+        // Conversions into `Fr` do not mask again and rely on this having been applied.
         // strip last two bits, to ensure result is in Fr.
         self.0[31] &= 0b0011_1111;
+        debug_assert!(self.is_field_safe(), "trimmed value is not in Fr");
+    }
+
+    /// Returns true if the bytes, read as little endian, are a valid element of `Fr`.
+    pub fn is_field_safe(&self) -> bool {
+        let mut repr = FrRepr::default();
+        repr.read_le(&self.0[..]).is_ok() && Fr::from_repr(repr).is_ok()
+    }
+
+    /// Converts a field element into its big endian byte representation. The default conversion
+    /// (`From<Fr>`) is little endian, this is only intended for interop with external systems.
+    pub fn from_fr_be(val: Fr) -> Self {
+        let mut res = Self::default();
+        val.into_repr().write_be(&mut res.0[0..32]).unwrap();
+
+        res
+    }
+
+    /// Interprets the bytes as a big endian field element, the inverse of `from_fr_be`.
+    pub fn to_fr_be(&self) -> Result<Fr> {
+        let mut res = FrRepr::default();
+        res.read_be(&self.0[0..32])?;
+
+        Fr::from_repr(res).map_err(|_| Error::BadFrBytes.into())
     }
 }
 
@@ -138,7 +166,7 @@ impl From<FrRepr> for Blake2sDomain {
 
 impl Element for Blake2sDomain {
     fn byte_len() -> usize {
-        32
+        Self::SIZE
     }
 
     fn from_slice(bytes: &[u8]) -> Self {
@@ -153,6 +181,7 @@ impl Element for Blake2sDomain {
     }
 }
 
+/// Does not mask the value, it must already be in `Fr` (see `trim_to_fr32`).
 impl From<Blake2sDomain> for Fr {
     fn from(val: Blake2sDomain) -> Self {
         let mut res = FrRepr::default();
@@ -163,6 +192,8 @@ impl From<Blake2sDomain> for Fr {
 }
 
 impl Domain for Blake2sDomain {
+    const SIZE: usize = 32;
+
     fn serialize(&self) -> Vec<u8> {
         self.0.to_vec()
     }
@@ -173,18 +204,18 @@ impl Domain for Blake2sDomain {
 
     fn try_from_bytes(raw: &[u8]) -> Result<Self> {
         ensure!(
-            raw.len() == 32 && u32::from(raw[31]) <= Fr::NUM_BITS,
+            raw.len() == Self::SIZE && u32::from(raw[Self::SIZE - 1]) <= Fr::NUM_BITS,
             Error::InvalidInputSize
         );
 
         let mut res = Blake2sDomain::default();
-        res.0.copy_from_slice(&raw[0..32]);
+        res.0.copy_from_slice(&raw[0..Self::SIZE]);
         Ok(res)
     }
 
     fn write_bytes(&self, dest: &mut [u8]) -> Result<()> {
-        ensure!(dest.len() >= 32, Error::InvalidInputSize);
-        dest[0..32].copy_from_slice(&self.0[..]);
+        ensure!(dest.len() >= Self::SIZE, Error::InvalidInputSize);
+        dest[0..Self::SIZE].copy_from_slice(&self.0[..]);
         Ok(())
     }
 
@@ -206,12 +237,55 @@ impl Into<Blake2sDomain> for Blake2sHash {
 
 impl HashFunction<Blake2sDomain> for Blake2sFunction {
     fn hash(data: &[u8]) -> Blake2sDomain {
-        Blake2s::new()
+        let mut res = Blake2sDomain::default();
+        Self::hash_into(data, &mut res);
+        res
+    }
+
+    fn hash_into(data: &[u8], out: &mut Blake2sDomain) {
+        let hashed = Blake2s::new()
             .hash_length(32)
             .to_state()
             .update(data)
-            .finalize()
-            .into()
+            .finalize();
+        out.0.copy_from_slice(hashed.as_ref());
+        out.trim_to_fr32();
+    }
+
+    /// With the `simd` feature, hashes up to `blake2s_simd::many::degree()` inputs at once, using
+    /// AVX2 or SSE4.1 if the CPU supports them. The digests are identical to those of `hash`.
+    #[cfg(feature = "simd")]
+    fn hash_many(inputs: &[&[u8]]) -> Vec<Blake2sDomain> {
+        use blake2s_simd::many::{hash_many, HashManyJob};
+
+        let mut params = Blake2s::new();
+        params.hash_length(32);
+
+        let mut jobs: Vec<HashManyJob> = inputs
+            .iter()
+            .map(|input| HashManyJob::new(&params, input))
+            .collect();
+        hash_many(jobs.iter_mut());
+
+        jobs.iter()
+            .map(|job| {
+                let mut res = Blake2sDomain::default();
+                res.0.copy_from_slice(job.to_hash().as_bytes());
+                res.trim_to_fr32();
+                res
+            })
+            .collect()
+    }
+
+    /// The node preimages go through `hash_many`, which gives the same digests as `node`.
+    fn node_many(pairs: &[(Blake2sDomain, Blake2sDomain)], height: usize) -> Vec<Blake2sDomain> {
+        let preimages: Vec<Vec<u8>> = pairs
+            .iter()
+            .map(|(left, right)| node_preimage(left, right, height))
+            .collect();
+        let inputs: Vec<&[u8]> = preimages.iter().map(Vec::as_slice).collect();
+
+        Self::hash_many(&inputs)
     }
 
     fn hash_leaf_circuit<E: JubjubEngine, CS: ConstraintSystem<E>>(
@@ -221,6 +295,7 @@ impl HashFunction<Blake2sDomain> for Blake2sFunction {
         _height: usize,
         params: &E::Params,
     ) -> std::result::Result<num::AllocatedNum<E>, SynthesisError> {
+        // Must match the byte layout of `node_preimage`.
         let mut preimage: Vec<boolean::Boolean> = vec![];
 
         preimage.extend_from_slice(left);
@@ -276,9 +351,8 @@ impl Algorithm<Blake2sDomain> for Blake2sFunction {
         leaf
     }
 
-    fn node(&mut self, left: Blake2sDomain, right: Blake2sDomain, _height: usize) -> Blake2sDomain {
-        left.hash(self);
-        right.hash(self);
+    fn node(&mut self, left: Blake2sDomain, right: Blake2sDomain, height: usize) -> Blake2sDomain {
+        self.write(&node_preimage(&left, &right, height));
         self.hash()
     }
 }
@@ -296,3 +370,179 @@ impl From<Blake2sDomain> for [u8; 32] {
         val.0
     }
 }
+
+impl From<&[u8; 32]> for Blake2sDomain {
+    #[inline]
+    fn from(val: &[u8; 32]) -> Self {
+        Blake2sDomain(*val)
+    }
+}
+
+impl TryFrom<&[u8]> for Blake2sDomain {
+    type Error = anyhow::Error;
+
+    /// Same as `Domain::try_from_bytes`.
+    fn try_from(raw: &[u8]) -> Result<Self> {
+        Self::try_from_bytes(raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fr32::fr_into_bytes;
+    use crate::test_helper::seeded_rng;
+
+    #[test]
+    fn std_conversions() {
+        let bytes = [7u8; 32];
+        let domain = Blake2sDomain::from(&bytes);
+        assert_eq!(domain, Blake2sDomain::from(bytes));
+        assert_eq!(Blake2sDomain::try_from(&bytes[..]).unwrap(), domain);
+
+        for len in &[0, 31, 33] {
+            let raw = vec![7u8; *len];
+            match Blake2sDomain::try_from(&raw[..]).map_err(|err| err.downcast::<Error>()) {
+                Err(Ok(Error::InvalidInputSize)) => {}
+                other => panic!("unexpected result for {} bytes: {:?}", len, other),
+            }
+        }
+    }
+
+    #[test]
+    fn from_fr_matches_fr_into_bytes() {
+        let rng = &mut seeded_rng();
+
+        // Commitments are computed both through domains and through `fr_into_bytes`, so the two
+        // serializations must agree.
+        for _ in 0..100 {
+            let fr = Fr::random(rng);
+            assert_eq!(
+                &Blake2sDomain::from(fr).0[..],
+                &fr_into_bytes::<Bls12>(&fr)[..]
+            );
+        }
+    }
+
+    #[test]
+    fn fr_endianness() {
+        let fr = Fr::from_str("1234").unwrap();
+
+        let le = Blake2sDomain::from(fr);
+        let be = Blake2sDomain::from_fr_be(fr);
+        assert_ne!(le, be);
+
+        let mut reversed = le.0;
+        reversed.reverse();
+        assert_eq!(reversed, be.0);
+
+        assert_eq!(Fr::from(le), fr);
+        assert_eq!(be.to_fr_be().unwrap(), fr);
+    }
+
+    #[test]
+    fn test_node_preimage() {
+        let rng = &mut seeded_rng();
+
+        for height in 0..4 {
+            let left = Blake2sDomain::random(rng);
+            let right = Blake2sDomain::random(rng);
+
+            let node = Blake2sFunction::default().node(left, right, height);
+            let expected = <Blake2sFunction as HashFunction<Blake2sDomain>>::hash(&node_preimage(
+                &left, &right, height,
+            ));
+
+            assert_eq!(node, expected);
+        }
+    }
+
+    #[test]
+    fn test_hash_into() {
+        let rng = &mut seeded_rng();
+        let mut out = Blake2sDomain::default();
+
+        for len in &[0, 1, 32, 64, 100] {
+            let mut data = vec![0u8; *len];
+            rng.fill_bytes(&mut data);
+
+            <Blake2sFunction as HashFunction<Blake2sDomain>>::hash_into(&data, &mut out);
+            assert_eq!(
+                out,
+                <Blake2sFunction as HashFunction<Blake2sDomain>>::hash(&data)
+            );
+        }
+    }
+
+    #[test]
+    fn test_hash_many_matches_hash() {
+        let rng = &mut seeded_rng();
+
+        // More inputs than the widest SIMD implementation hashes at once, of varying lengths
+        // around the block size.
+        let inputs: Vec<Vec<u8>> = [0, 1, 32, 63, 64, 65, 100, 128, 1000]
+            .iter()
+            .cycle()
+            .take(20)
+            .map(|len| {
+                let mut data = vec![0u8; *len];
+                rng.fill_bytes(&mut data);
+                data
+            })
+            .collect();
+        let inputs: Vec<&[u8]> = inputs.iter().map(|input| &input[..]).collect();
+
+        let expected: Vec<Blake2sDomain> = inputs
+            .iter()
+            .map(|input| <Blake2sFunction as HashFunction<Blake2sDomain>>::hash(input))
+            .collect();
+        assert_eq!(
+            <Blake2sFunction as HashFunction<Blake2sDomain>>::hash_many(&inputs),
+            expected
+        );
+
+        assert!(<Blake2sFunction as HashFunction<Blake2sDomain>>::hash_many(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_node_many_matches_node() {
+        let rng = &mut seeded_rng();
+        let pairs: Vec<(Blake2sDomain, Blake2sDomain)> = (0..20)
+            .map(|_| (Blake2sDomain::random(rng), Blake2sDomain::random(rng)))
+            .collect();
+
+        let expected: Vec<Blake2sDomain> = pairs
+            .iter()
+            .map(|(left, right)| Blake2sFunction::default().node(*left, *right, 3))
+            .collect();
+        assert_eq!(
+            <Blake2sFunction as HashFunction<Blake2sDomain>>::node_many(&pairs, 3),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_hash_is_field_safe() {
+        let rng = &mut seeded_rng();
+
+        for _ in 0..100 {
+            let mut data = vec![0u8; 64];
+            rng.fill_bytes(&mut data);
+
+            let out = <Blake2sFunction as HashFunction<Blake2sDomain>>::hash(&data);
+            assert!(out.is_field_safe());
+
+            let fr: Fr = out.into();
+            assert_eq!(Blake2sDomain::from(fr), out);
+        }
+
+        assert!(!Blake2sDomain([0xff; 32]).is_field_safe());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_into_fr_does_not_mask() {
+        let _: Fr = Blake2sDomain([0xff; 32]).into();
+    }
+}
@@ -4,8 +4,18 @@ pub mod sha256;
 
 mod types;
 
-pub use self::types::{Domain, HashFunction, Hasher};
+pub use self::types::{
+    accumulate, hash_bytes_into_domain, node_preimage, Domain, HashFunction, Hasher,
+};
 
 pub use self::blake2s::Blake2sHasher;
 pub use self::pedersen::PedersenHasher;
 pub use self::sha256::Sha256Hasher;
+
+use crate::util::NODE_SIZE;
+
+// Every domain element is stored as exactly one merkle tree node, see `crypto::pedersen` for the
+// idiom.
+const _: [(); (<blake2s::Blake2sDomain as Domain>::SIZE == NODE_SIZE) as usize - 1] = [];
+const _: [(); (<pedersen::PedersenDomain as Domain>::SIZE == NODE_SIZE) as usize - 1] = [];
+const _: [(); (<sha256::Sha256Domain as Domain>::SIZE == NODE_SIZE) as usize - 1] = [];
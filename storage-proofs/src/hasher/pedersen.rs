@@ -24,6 +24,8 @@ impl Hasher for PedersenHasher {
     type Domain = PedersenDomain;
     type Function = PedersenFunction;
 
+    const NODE_HASH_CONSTRAINTS: usize = 859;
+
     fn name() -> String {
         "PedersenHasher".into()
     }
@@ -143,23 +145,25 @@ fn as_ref<'a>(src: &'a [u64; 4]) -> &'a [u8] {
 }
 
 impl Domain for PedersenDomain {
+    const SIZE: usize = 32;
+
     // QUESTION: When, if ever, should serialize and into_bytes return different results?
     // The definitions here at least are equivalent.
     fn serialize(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(PedersenDomain::byte_len());
+        let mut bytes = Vec::with_capacity(Self::SIZE);
         self.0.write_le(&mut bytes).unwrap();
         bytes
     }
 
     fn into_bytes(&self) -> Vec<u8> {
-        let mut out = Vec::with_capacity(PedersenDomain::byte_len());
+        let mut out = Vec::with_capacity(Self::SIZE);
         self.0.write_le(&mut out).unwrap();
 
         out
     }
 
     fn try_from_bytes(raw: &[u8]) -> Result<Self> {
-        ensure!(raw.len() == PedersenDomain::byte_len(), Error::BadFrBytes);
+        ensure!(raw.len() == Self::SIZE, Error::BadFrBytes);
         let mut res: FrRepr = Default::default();
         res.read_le(raw)?;
 
@@ -167,7 +171,8 @@ impl Domain for PedersenDomain {
     }
 
     fn write_bytes(&self, dest: &mut [u8]) -> Result<()> {
-        self.0.write_le(dest)?;
+        ensure!(dest.len() >= Self::SIZE, Error::InvalidInputSize);
+        self.0.write_le(&mut dest[0..Self::SIZE])?;
         Ok(())
     }
 
@@ -179,7 +184,7 @@ impl Domain for PedersenDomain {
 
 impl Element for PedersenDomain {
     fn byte_len() -> usize {
-        32
+        Self::SIZE
     }
 
     fn from_slice(bytes: &[u8]) -> Self {
@@ -259,24 +264,34 @@ impl LightAlgorithm<PedersenDomain> for PedersenFunction {
         right: PedersenDomain,
         _height: usize,
     ) -> PedersenDomain {
-        let node_bits = NodeBits::new(&(left.0).0[..], &(right.0).0[..]);
-
-        let digest = if cfg!(target_arch = "x86_64") {
-            use fil_sapling_crypto::pedersen_hash::pedersen_hash_bls12_381_with_precomp;
-            pedersen_hash_bls12_381_with_precomp::<_>(
-                Personalization::None,
-                node_bits,
-                &pedersen::JJ_PARAMS,
-            )
-        } else {
-            use fil_sapling_crypto::pedersen_hash::pedersen_hash;
-            pedersen_hash::<Bls12, _>(Personalization::None, node_bits, &pedersen::JJ_PARAMS)
-        };
-
-        digest.into_xy().0.into()
+        hash_node(&left.0, &right.0).into()
     }
 }
 
+/// Computes the parent of `left` and `right` in a merkle tree, exactly as
+/// `PedersenFunction::node` does, without requiring a tree or hasher state.
+pub fn pedersen_node(left: Fr, right: Fr, _height: usize) -> Fr {
+    hash_node(&left.into_repr(), &right.into_repr())
+}
+
+fn hash_node(left: &FrRepr, right: &FrRepr) -> Fr {
+    let node_bits = NodeBits::new(&left.0[..], &right.0[..]);
+
+    let digest = if cfg!(target_arch = "x86_64") {
+        use fil_sapling_crypto::pedersen_hash::pedersen_hash_bls12_381_with_precomp;
+        pedersen_hash_bls12_381_with_precomp::<_>(
+            Personalization::None,
+            node_bits,
+            &pedersen::JJ_PARAMS,
+        )
+    } else {
+        use fil_sapling_crypto::pedersen_hash::pedersen_hash;
+        pedersen_hash::<Bls12, _>(Personalization::None, node_bits, &pedersen::JJ_PARAMS)
+    };
+
+    digest.into_xy().0
+}
+
 /// Helper to iterate over a pair of `Fr`.
 struct NodeBits<'a> {
     // 256 bits
@@ -348,6 +363,7 @@ mod tests {
     use merkletree::hash::Hashable;
 
     use crate::merkle::MerkleTree;
+    use crate::test_helper::seeded_rng;
 
     #[test]
     fn test_path() {
@@ -416,6 +432,23 @@ mod tests {
         assert_eq!(t.read_at(6).unwrap(), root);
     }
 
+    #[test]
+    fn test_pedersen_node() {
+        let rng = &mut seeded_rng();
+        let leaves: Vec<Fr> = (0..4).map(|_| Fr::random(rng)).collect();
+
+        let t = MerkleTree::<PedersenDomain, PedersenFunction>::try_from_iter(
+            leaves.iter().map(|l| Ok(PedersenDomain::from(*l))),
+        )
+        .unwrap();
+
+        let i1 = pedersen_node(leaves[0], leaves[1], 0);
+        let i2 = pedersen_node(leaves[2], leaves[3], 0);
+        let root = pedersen_node(i1, i2, 1);
+
+        assert_eq!(t.root(), root.into());
+    }
+
     #[test]
     fn test_as_ref() {
         let cases: Vec<[u64; 4]> = vec![
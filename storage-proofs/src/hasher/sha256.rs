@@ -1,5 +1,6 @@
 use sha2::{Digest, Sha256};
 
+use std::convert::TryFrom;
 use std::hash::Hasher as StdHasher;
 
 use anyhow::ensure;
@@ -13,7 +14,8 @@ use paired::bls12_381::{Bls12, Fr, FrRepr};
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
 
-use super::{Domain, HashFunction, Hasher};
+use super::{node_preimage, Domain, HashFunction, Hasher};
+use crate::bit_order::swap_bit_order;
 use crate::crypto::sloth;
 use crate::error::*;
 
@@ -24,6 +26,8 @@ impl Hasher for Sha256Hasher {
     type Domain = Sha256Domain;
     type Function = Sha256Function;
 
+    const NODE_HASH_CONSTRAINTS: usize = 44_874;
+
     fn name() -> String {
         "Sha256Hasher".into()
     }
@@ -74,8 +78,33 @@ impl AsRef<Sha256Domain> for Sha256Domain {
 
 impl Sha256Domain {
     fn trim_to_fr32(&mut self) {
+        // Conversions into `Fr` do not mask again and rely on this having been applied.
         // strip last two bits, to ensure result is in Fr.
         self.0[31] &= 0b0011_1111;
+        debug_assert!(self.is_field_safe(), "trimmed value is not in Fr");
+    }
+
+    /// Returns true if the bytes, read as little endian, are a valid element of `Fr`.
+    pub fn is_field_safe(&self) -> bool {
+        let mut repr = FrRepr::default();
+        repr.read_le(&self.0[..]).is_ok() && Fr::from_repr(repr).is_ok()
+    }
+
+    /// Converts a field element into its big endian byte representation. The default conversion
+    /// (`From<Fr>`) is little endian, this is only intended for interop with external systems.
+    pub fn from_fr_be(val: Fr) -> Self {
+        let mut res = Self::default();
+        val.into_repr().write_be(&mut res.0[0..32]).unwrap();
+
+        res
+    }
+
+    /// Interprets the bytes as a big endian field element, the inverse of `from_fr_be`.
+    pub fn to_fr_be(&self) -> Result<Fr> {
+        let mut res = FrRepr::default();
+        res.read_be(&self.0[0..32])?;
+
+        Fr::from_repr(res).map_err(|_| Error::BadFrBytes.into())
     }
 }
 
@@ -109,6 +138,7 @@ impl From<FrRepr> for Sha256Domain {
     }
 }
 
+/// Does not mask the value, it must already be in `Fr` (see `trim_to_fr32`).
 impl From<Sha256Domain> for Fr {
     fn from(val: Sha256Domain) -> Self {
         let mut res = FrRepr::default();
@@ -119,6 +149,8 @@ impl From<Sha256Domain> for Fr {
 }
 
 impl Domain for Sha256Domain {
+    const SIZE: usize = 32;
+
     fn serialize(&self) -> Vec<u8> {
         self.0.to_vec()
     }
@@ -128,23 +160,17 @@ impl Domain for Sha256Domain {
     }
 
     fn try_from_bytes(raw: &[u8]) -> Result<Self> {
-        ensure!(
-            raw.len() == Sha256Domain::byte_len(),
-            Error::InvalidInputSize
-        );
+        ensure!(raw.len() == Self::SIZE, Error::InvalidInputSize);
 
         let mut res = Sha256Domain::default();
-        res.0.copy_from_slice(&raw[0..Sha256Domain::byte_len()]);
+        res.0.copy_from_slice(&raw[0..Self::SIZE]);
         Ok(res)
     }
 
     fn write_bytes(&self, dest: &mut [u8]) -> Result<()> {
-        ensure!(
-            dest.len() >= Sha256Domain::byte_len(),
-            Error::InvalidInputSize
-        );
+        ensure!(dest.len() >= Self::SIZE, Error::InvalidInputSize);
 
-        dest[0..Sha256Domain::byte_len()].copy_from_slice(&self.0[..]);
+        dest[0..Self::SIZE].copy_from_slice(&self.0[..]);
         Ok(())
     }
 
@@ -156,7 +182,7 @@ impl Domain for Sha256Domain {
 
 impl Element for Sha256Domain {
     fn byte_len() -> usize {
-        32
+        Self::SIZE
     }
 
     fn from_slice(bytes: &[u8]) -> Self {
@@ -173,13 +199,17 @@ impl Element for Sha256Domain {
 
 impl HashFunction<Sha256Domain> for Sha256Function {
     fn hash(data: &[u8]) -> Sha256Domain {
-        let hashed = Sha256::digest(data);
         let mut res = Sha256Domain::default();
-        res.0.copy_from_slice(&hashed[..]);
-        res.trim_to_fr32();
+        Self::hash_into(data, &mut res);
         res
     }
 
+    fn hash_into(data: &[u8], out: &mut Sha256Domain) {
+        let hashed = Sha256::digest(data);
+        out.0.copy_from_slice(&hashed[..]);
+        out.trim_to_fr32();
+    }
+
     fn hash_leaf_circuit<E: JubjubEngine, CS: ConstraintSystem<E>>(
         cs: CS,
         left: &[boolean::Boolean],
@@ -187,6 +217,7 @@ impl HashFunction<Sha256Domain> for Sha256Function {
         _height: usize,
         params: &E::Params,
     ) -> std::result::Result<num::AllocatedNum<E>, SynthesisError> {
+        // Must match the byte layout of `node_preimage`.
         let mut preimage: Vec<boolean::Boolean> = vec![];
 
         let mut left_padded = left.to_vec();
@@ -194,56 +225,52 @@ impl HashFunction<Sha256Domain> for Sha256Function {
             left_padded.push(boolean::Boolean::Constant(false));
         }
 
-        preimage.extend(
-            left_padded
-                .chunks_exact(8)
-                .flat_map(|chunk| chunk.iter().rev())
-                .cloned(),
-        );
+        preimage.extend(swap_bit_order(&left_padded));
 
         let mut right_padded = right.to_vec();
         while right_padded.len() % 8 != 0 {
             right_padded.push(boolean::Boolean::Constant(false));
         }
 
-        preimage.extend(
-            right_padded
-                .chunks_exact(8)
-                .flat_map(|chunk| chunk.iter().rev())
-                .cloned(),
-        );
+        preimage.extend(swap_bit_order(&right_padded));
 
         Self::hash_circuit(cs, &preimage[..], params)
     }
 
     fn hash_circuit<E: JubjubEngine, CS: ConstraintSystem<E>>(
-        mut cs: CS,
+        cs: CS,
         bits: &[boolean::Boolean],
         _params: &E::Params,
     ) -> std::result::Result<num::AllocatedNum<E>, SynthesisError> {
-        let alloc_bits = sha256_circuit(cs.namespace(|| "hash"), &bits[..])?;
-        let fr = if alloc_bits[0].get_value().is_some() {
-            let be_bits = alloc_bits
-                .iter()
-                .map(|v| v.get_value().ok_or(SynthesisError::AssignmentMissing))
-                .collect::<std::result::Result<Vec<bool>, SynthesisError>>()?;
-
-            let le_bits = be_bits
-                .chunks(8)
-                .flat_map(|chunk| chunk.iter().rev())
-                .copied()
-                .take(E::Fr::CAPACITY as usize)
-                .collect::<Vec<bool>>();
-
-            Ok(multipack::compute_multipacking::<E>(&le_bits)[0])
-        } else {
-            Err(SynthesisError::AssignmentMissing)
-        };
-
-        num::AllocatedNum::<E>::alloc(cs.namespace(|| "result_num"), || fr)
+        hash_circuit_keeping::<E, _>(cs, bits, E::Fr::CAPACITY as usize)
     }
 }
 
+/// Hashes `bits` with sha256, keeping only the `keep_bits` least significant bits of the digest
+/// read as little endian.
+fn hash_circuit_keeping<E: JubjubEngine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    bits: &[boolean::Boolean],
+    keep_bits: usize,
+) -> std::result::Result<num::AllocatedNum<E>, SynthesisError> {
+    let alloc_bits = sha256_circuit(cs.namespace(|| "hash"), &bits[..])?;
+    let fr = if alloc_bits[0].get_value().is_some() {
+        let be_bits = alloc_bits
+            .iter()
+            .map(|v| v.get_value().ok_or(SynthesisError::AssignmentMissing))
+            .collect::<std::result::Result<Vec<bool>, SynthesisError>>()?;
+
+        let mut le_bits = swap_bit_order(&be_bits);
+        le_bits.truncate(keep_bits);
+
+        Ok(multipack::compute_multipacking::<E>(&le_bits)[0])
+    } else {
+        Err(SynthesisError::AssignmentMissing)
+    };
+
+    num::AllocatedNum::<E>::alloc(cs.namespace(|| "result_num"), || fr)
+}
+
 impl Algorithm<Sha256Domain> for Sha256Function {
     #[inline]
     fn hash(&mut self) -> Sha256Domain {
@@ -263,13 +290,61 @@ impl Algorithm<Sha256Domain> for Sha256Function {
         leaf
     }
 
-    fn node(&mut self, left: Sha256Domain, right: Sha256Domain, _height: usize) -> Sha256Domain {
-        left.hash(self);
-        right.hash(self);
+    fn node(&mut self, left: Sha256Domain, right: Sha256Domain, height: usize) -> Sha256Domain {
+        self.write(&node_preimage(&left, &right, height));
         self.hash()
     }
 }
 
+/// A sha256 variant whose digests are cut short to `truncate_to` bytes, zeroing the rest, for
+/// experiments with smaller trees at reduced security. The digests stay field safe, as with
+/// `Sha256Function`, to which it is equivalent for `truncate_to == 32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TruncatedSha256 {
+    truncate_to: usize,
+}
+
+impl TruncatedSha256 {
+    pub fn new(truncate_to: usize) -> Result<Self> {
+        ensure!(
+            truncate_to > 0 && truncate_to <= 32,
+            "can only truncate to between 1 and 32 bytes, not {}",
+            truncate_to
+        );
+
+        Ok(TruncatedSha256 { truncate_to })
+    }
+
+    pub fn truncate_to(&self) -> usize {
+        self.truncate_to
+    }
+
+    pub fn hash(&self, data: &[u8]) -> Sha256Domain {
+        let mut res = <Sha256Function as HashFunction<Sha256Domain>>::hash(data);
+        for byte in &mut res.0[self.truncate_to..] {
+            *byte = 0;
+        }
+
+        res
+    }
+
+    pub fn node(&self, left: Sha256Domain, right: Sha256Domain, height: usize) -> Sha256Domain {
+        self.hash(&node_preimage(&left, &right, height))
+    }
+
+    /// The circuit equivalent of `hash`, over `bits` laid out as for
+    /// `Sha256Function::hash_circuit`.
+    pub fn hash_circuit<E: JubjubEngine, CS: ConstraintSystem<E>>(
+        &self,
+        cs: CS,
+        bits: &[boolean::Boolean],
+    ) -> std::result::Result<num::AllocatedNum<E>, SynthesisError> {
+        let keep_bits = std::cmp::min(8 * self.truncate_to, E::Fr::CAPACITY as usize);
+
+        hash_circuit_keeping::<E, _>(cs, bits, keep_bits)
+    }
+}
+
 impl From<[u8; 32]> for Sha256Domain {
     #[inline]
     fn from(val: [u8; 32]) -> Self {
@@ -284,6 +359,22 @@ impl From<Sha256Domain> for [u8; 32] {
     }
 }
 
+impl From<&[u8; 32]> for Sha256Domain {
+    #[inline]
+    fn from(val: &[u8; 32]) -> Self {
+        Sha256Domain(*val)
+    }
+}
+
+impl TryFrom<&[u8]> for Sha256Domain {
+    type Error = anyhow::Error;
+
+    /// Same as `Domain::try_from_bytes`.
+    fn try_from(raw: &[u8]) -> Result<Self> {
+        Self::try_from_bytes(raw)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -291,6 +382,7 @@ mod tests {
     use crate::circuit::test::TestConstraintSystem;
     use crate::crypto;
     use crate::fr32::fr_into_bytes;
+    use crate::test_helper::seeded_rng;
     use crate::util::bytes_into_boolean_vec;
 
     use bellperson::gadgets::boolean::Boolean;
@@ -298,13 +390,11 @@ mod tests {
     use ff::Field;
     use merkletree::hash::Algorithm;
     use paired::bls12_381::{Bls12, Fr};
-    use rand::SeedableRng;
-    use rand_xorshift::XorShiftRng;
 
     #[test]
     fn hash_leaf_circuit() {
         let mut cs = TestConstraintSystem::<Bls12>::new();
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let left_fr = Fr::random(rng);
         let right_fr = Fr::random(rng);
@@ -344,4 +434,148 @@ mod tests {
             "circuit and non circuit do not match"
         );
     }
+
+    #[test]
+    fn std_conversions() {
+        let bytes = [7u8; 32];
+        let domain = Sha256Domain::from(&bytes);
+        assert_eq!(domain, Sha256Domain::from(bytes));
+        assert_eq!(Sha256Domain::try_from(&bytes[..]).unwrap(), domain);
+
+        for len in &[0, 31, 33] {
+            let raw = vec![7u8; *len];
+            match Sha256Domain::try_from(&raw[..]).map_err(|err| err.downcast::<Error>()) {
+                Err(Ok(Error::InvalidInputSize)) => {}
+                other => panic!("unexpected result for {} bytes: {:?}", len, other),
+            }
+        }
+    }
+
+    #[test]
+    fn from_fr_matches_fr_into_bytes() {
+        let rng = &mut seeded_rng();
+
+        // Commitments are computed both through domains and through `fr_into_bytes`, so the two
+        // serializations must agree.
+        for _ in 0..100 {
+            let fr = Fr::random(rng);
+            assert_eq!(
+                &Sha256Domain::from(fr).0[..],
+                &fr_into_bytes::<Bls12>(&fr)[..]
+            );
+        }
+    }
+
+    #[test]
+    fn truncated_hash() {
+        let rng = &mut seeded_rng();
+        let truncated = TruncatedSha256::new(16).unwrap();
+
+        for _ in 0..5 {
+            let mut data = vec![0u8; 64];
+            rng.fill_bytes(&mut data);
+
+            let full = <Sha256Function as HashFunction<Sha256Domain>>::hash(&data);
+            let native = truncated.hash(&data);
+            assert_eq!(&native.0[..16], &full.0[..16]);
+            assert_eq!(&native.0[16..], &[0u8; 16][..]);
+            assert!(native.is_field_safe());
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let data_bits: Vec<Boolean> = {
+                let mut cs = cs.namespace(|| "data");
+                bytes_into_boolean_vec(&mut cs, Some(data.as_slice()), data.len()).unwrap()
+            };
+            let out = truncated
+                .hash_circuit(cs.namespace(|| "hash"), &swap_bit_order(&data_bits))
+                .unwrap();
+            assert!(cs.is_satisfied(), "constraints not satisfied");
+
+            let expected: Fr = native.into();
+            assert_eq!(expected, out.get_value().unwrap());
+        }
+
+        // Not truncating at all is the regular hash.
+        let data = vec![1u8; 64];
+        assert_eq!(
+            TruncatedSha256::new(32).unwrap().hash(&data),
+            <Sha256Function as HashFunction<Sha256Domain>>::hash(&data)
+        );
+        assert!(TruncatedSha256::new(0).is_err());
+        assert!(TruncatedSha256::new(33).is_err());
+    }
+
+    #[test]
+    fn fr_endianness() {
+        let fr = Fr::from_str("1234").unwrap();
+
+        let le = Sha256Domain::from(fr);
+        let be = Sha256Domain::from_fr_be(fr);
+        assert_ne!(le, be);
+
+        let mut reversed = le.0;
+        reversed.reverse();
+        assert_eq!(reversed, be.0);
+
+        assert_eq!(Fr::from(le), fr);
+        assert_eq!(be.to_fr_be().unwrap(), fr);
+    }
+
+    #[test]
+    fn test_node_preimage() {
+        let rng = &mut seeded_rng();
+
+        for height in 0..4 {
+            let left = Sha256Domain::random(rng);
+            let right = Sha256Domain::random(rng);
+
+            let node = Sha256Function::default().node(left, right, height);
+            let expected = <Sha256Function as HashFunction<Sha256Domain>>::hash(&node_preimage(
+                &left, &right, height,
+            ));
+
+            assert_eq!(node, expected);
+        }
+    }
+
+    #[test]
+    fn test_hash_into() {
+        let rng = &mut seeded_rng();
+        let mut out = Sha256Domain::default();
+
+        for len in &[0, 1, 32, 64, 100] {
+            let mut data = vec![0u8; *len];
+            rng.fill_bytes(&mut data);
+
+            <Sha256Function as HashFunction<Sha256Domain>>::hash_into(&data, &mut out);
+            assert_eq!(
+                out,
+                <Sha256Function as HashFunction<Sha256Domain>>::hash(&data)
+            );
+        }
+    }
+
+    #[test]
+    fn test_hash_is_field_safe() {
+        let rng = &mut seeded_rng();
+
+        for _ in 0..100 {
+            let mut data = vec![0u8; 64];
+            rng.fill_bytes(&mut data);
+
+            let out = <Sha256Function as HashFunction<Sha256Domain>>::hash(&data);
+            assert!(out.is_field_safe());
+
+            let fr: Fr = out.into();
+            assert_eq!(Sha256Domain::from(fr), out);
+        }
+
+        assert!(!Sha256Domain([0xff; 32]).is_field_safe());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_into_fr_does_not_mask() {
+        let _: Fr = Sha256Domain([0xff; 32]).into();
+    }
 }
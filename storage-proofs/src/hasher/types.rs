@@ -1,13 +1,17 @@
+use anyhow::ensure;
 use bellperson::gadgets::{boolean, num};
 use bellperson::{ConstraintSystem, SynthesisError};
+use ff::{PrimeField, PrimeFieldRepr};
 use fil_sapling_crypto::jubjub::JubjubEngine;
 use merkletree::hash::{Algorithm as LightAlgorithm, Hashable as LightHashable};
 use merkletree::merkle::Element;
+use num_bigint::BigUint;
 use paired::bls12_381::{Fr, FrRepr};
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::fr32::bytes_into_fr_repr_safe;
 
 pub trait Domain:
     Ord
@@ -27,6 +31,10 @@ pub trait Domain:
     + Element
     + std::hash::Hash
 {
+    /// Size in bytes of the serialized element, the length accepted by `try_from_bytes` and
+    /// written by `write_bytes`.
+    const SIZE: usize;
+
     fn serialize(&self) -> Vec<u8>;
     fn into_bytes(&self) -> Vec<u8>;
     fn try_from_bytes(raw: &[u8]) -> Result<Self>;
@@ -34,6 +42,97 @@ pub trait Domain:
     fn write_bytes(&self, _: &mut [u8]) -> Result<()>;
 
     fn random<R: rand::RngCore>(rng: &mut R) -> Self;
+
+    /// Interprets the element as an unsigned integer, using the same little endian byte order as
+    /// the conversion into `Fr`.
+    fn to_biguint(&self) -> BigUint {
+        BigUint::from_bytes_le(&self.into_bytes())
+    }
+
+    /// Inverse of `to_biguint`, rejecting values that are not smaller than the modulus of `Fr`.
+    fn try_from_biguint(n: &BigUint) -> Result<Self> {
+        let mut modulus = Vec::with_capacity(Self::SIZE);
+        Fr::char().write_le(&mut modulus)?;
+        ensure!(n < &BigUint::from_bytes_le(&modulus), Error::BadFrBytes);
+
+        let mut bytes = n.to_bytes_le();
+        bytes.resize(Self::SIZE, 0);
+
+        Self::try_from_bytes(&bytes)
+    }
+
+    /// Encodes the element as URL safe base64 without padding, which is more compact than hex
+    /// in text protocols.
+    fn to_base64(&self) -> String {
+        base64::encode_config(&self.into_bytes(), base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Inverse of `to_base64`, rejecting malformed input and payloads that are not exactly
+    /// `SIZE` bytes long.
+    fn from_base64(s: &str) -> Result<Self> {
+        let bytes = base64::decode_config(s, base64::URL_SAFE_NO_PAD)
+            .map_err(|err| Error::InvalidBase64(err.to_string()))?;
+        ensure!(
+            bytes.len() == Self::SIZE,
+            Error::InvalidBase64(format!(
+                "decoded {} bytes, expected {}",
+                bytes.len(),
+                Self::SIZE
+            ))
+        );
+
+        Self::try_from_bytes(&bytes)
+    }
+
+    /// Encodes the element as lowercase hex, the format commitments show up in logs.
+    fn to_hex(&self) -> String {
+        hex::encode(&self.into_bytes())
+    }
+
+    /// Inverse of `to_hex`, rejecting non hex characters and payloads that are not exactly `SIZE`
+    /// bytes long. Both upper and lower case digits are accepted.
+    fn from_hex(s: &str) -> Result<Self> {
+        let bytes = hex::decode(s).map_err(|err| Error::InvalidHex(err.to_string()))?;
+        ensure!(
+            bytes.len() == Self::SIZE,
+            Error::InvalidHex(format!(
+                "decoded {} bytes, expected {}",
+                bytes.len(),
+                Self::SIZE
+            ))
+        );
+
+        Self::try_from_bytes(&bytes)
+    }
+}
+
+/// The canonical preimage of a merkle tree node for the digest based hashers: the bytes of
+/// `left` followed by the bytes of `right`. The height is currently not part of the preimage.
+///
+/// `hash_leaf_circuit` of the digest hashers must lay out its bits in exactly this order.
+pub fn node_preimage<D: Domain>(left: &D, right: &D, _height: usize) -> Vec<u8> {
+    let mut preimage = Vec::with_capacity(left.as_ref().len() + right.as_ref().len());
+    preimage.extend_from_slice(left.as_ref());
+    preimage.extend_from_slice(right.as_ref());
+    preimage
+}
+
+/// Hashes `bytes` of any length into a domain element, e.g. to derive a replica id from a label.
+/// The two most significant bits of the digest are cleared, so the result is always a valid
+/// field element.
+pub fn hash_bytes_into_domain<H: Hasher>(bytes: &[u8]) -> H::Domain {
+    let digest = <H::Function as HashFunction<H::Domain>>::hash(bytes);
+
+    bytes_into_fr_repr_safe(digest.as_ref()).into()
+}
+
+/// Folds `next` into the running commitment `acc`, as the node with `acc` on the left and `next`
+/// on the right. Folding a stream of values this way commits to all of them without building a
+/// tree.
+///
+/// Order matters: folding the same values in a different order gives a different commitment.
+pub fn accumulate<H: Hasher>(acc: H::Domain, next: &H::Domain) -> H::Domain {
+    H::Function::default().node(acc, *next, 0)
 }
 
 pub trait HashFunction<T: Domain>:
@@ -41,6 +140,31 @@ pub trait HashFunction<T: Domain>:
 {
     fn hash(data: &[u8]) -> T;
 
+    /// Like `hash`, but writes the result into `out`, so it can be reused across calls.
+    fn hash_into(data: &[u8], out: &mut T) {
+        *out = Self::hash(data);
+    }
+
+    /// Hashes each of `inputs` independently, as if by `hash`. Unlike reusing a single stateful
+    /// function through `write` and `hash`, no state can leak from one input into the next.
+    fn hash_many(inputs: &[&[u8]]) -> Vec<T> {
+        inputs.iter().map(|input| Self::hash(input)).collect()
+    }
+
+    /// Computes the parent of each of the `(left, right)` pairs at `height`, as if by `node`.
+    /// Merkle trees are built one row at a time through this, so hashers that can hash several
+    /// inputs at once only need to override it.
+    fn node_many(pairs: &[(T, T)], height: usize) -> Vec<T> {
+        let mut a = Self::default();
+        pairs
+            .iter()
+            .map(|(left, right)| {
+                a.reset();
+                a.node(*left, *right, height)
+            })
+            .collect()
+    }
+
     fn hash_leaf(data: &dyn LightHashable<Self>) -> T {
         let mut a = Self::default();
         data.hash(&mut a);
@@ -73,9 +197,415 @@ pub trait Hasher: Clone + ::std::fmt::Debug + Eq + Default + Send + Sync {
     type Domain: Domain + LightHashable<Self::Function> + AsRef<Self::Domain>;
     type Function: HashFunction<Self::Domain>;
 
+    /// The number of constraints of a single node hash in the circuits, i.e. of
+    /// `HashFunction::hash_leaf_circuit`. Allows estimating the size of a circuit, roughly as
+    /// `NODE_HASH_CONSTRAINTS * tree depth * challenges`, without synthesizing it.
+    const NODE_HASH_CONSTRAINTS: usize;
+
+    /// Derives the label (the key for encoding) of a node from `data`, the replica id and its
+    /// parents.
+    ///
+    /// The digest hashers clear the two most significant bits of the digest to land in `Fr`
+    /// instead of rejection sampling. The labels are then uniform over `[0, 2^254)`, a bit more
+    /// than half of the field, so they keep 254 bits of entropy. That is plenty for a key, and
+    /// unlike rejection sampling it is cheap to express in the circuits.
     fn create_label(data: &[u8], m: usize) -> Result<Self::Domain>;
     fn sloth_encode(key: &Self::Domain, ciphertext: &Self::Domain) -> Result<Self::Domain>;
     fn sloth_decode(key: &Self::Domain, ciphertext: &Self::Domain) -> Result<Self::Domain>;
 
     fn name() -> String;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ff::Field;
+    use paired::bls12_381::Bls12;
+    use rand::RngCore;
+
+    use crate::circuit::test::TestConstraintSystem;
+    use crate::crypto::pedersen::JJ_PARAMS;
+    use crate::hasher::{Blake2sHasher, PedersenHasher, Sha256Hasher};
+    use crate::test_helper::seeded_rng;
+
+    /// Measures the constraints of a single node hash, over inputs converted to bits the way the
+    /// PoR circuit does.
+    fn node_hash_constraints<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let left = num::AllocatedNum::alloc(cs.namespace(|| "left"), || Ok(Fr::random(rng)))
+            .unwrap()
+            .to_bits_le(cs.namespace(|| "left bits"))
+            .unwrap();
+        let right = num::AllocatedNum::alloc(cs.namespace(|| "right"), || Ok(Fr::random(rng)))
+            .unwrap()
+            .to_bits_le(cs.namespace(|| "right bits"))
+            .unwrap();
+
+        let before = cs.num_constraints();
+        H::Function::hash_leaf_circuit(cs.namespace(|| "node"), &left, &right, 0, &JJ_PARAMS)
+            .unwrap();
+        assert!(cs.is_satisfied(), "constraints not satisfied");
+        let measured = cs.num_constraints() - before;
+
+        // Allow for small changes in the gadgets, the constant is only used for estimates.
+        let tolerance = H::NODE_HASH_CONSTRAINTS / 20;
+        assert!(
+            (measured as i64 - H::NODE_HASH_CONSTRAINTS as i64).abs() <= tolerance as i64,
+            "{}: declared {} node hash constraints, measured {}",
+            H::name(),
+            H::NODE_HASH_CONSTRAINTS,
+            measured
+        );
+    }
+
+    #[test]
+    fn node_hash_constraints_pedersen() {
+        node_hash_constraints::<PedersenHasher>();
+    }
+
+    #[test]
+    fn node_hash_constraints_sha256() {
+        node_hash_constraints::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn node_hash_constraints_blake2s() {
+        node_hash_constraints::<Blake2sHasher>();
+    }
+
+    fn hash_bytes_into_domain_is_field_safe<H: Hasher>() {
+        for len in &[1, 32, 100] {
+            let bytes: Vec<u8> = (0..*len).map(|i| (i * 7) as u8).collect();
+            let domain = hash_bytes_into_domain::<H>(&bytes);
+
+            assert_eq!(domain, hash_bytes_into_domain::<H>(&bytes));
+            assert_eq!(domain.into_bytes()[31] & 0b1100_0000, 0);
+            assert!(crate::fr32::bytes_into_fr::<Bls12>(&domain.into_bytes()).is_ok());
+
+            let mut other = bytes.clone();
+            other[0] ^= 1;
+            assert_ne!(domain, hash_bytes_into_domain::<H>(&other));
+        }
+    }
+
+    #[test]
+    fn hash_bytes_into_domain_pedersen() {
+        hash_bytes_into_domain_is_field_safe::<PedersenHasher>();
+    }
+
+    #[test]
+    fn hash_bytes_into_domain_sha256() {
+        hash_bytes_into_domain_is_field_safe::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn hash_bytes_into_domain_blake2s() {
+        hash_bytes_into_domain_is_field_safe::<Blake2sHasher>();
+    }
+
+    /// Checks that the most and least significant bytes of `create_label` are uniformly
+    /// distributed over their range, with a chi-squared test at a 0.1% significance level.
+    fn create_label_is_uniform<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let samples = 20_000;
+
+        let mut high = vec![0usize; 64];
+        let mut low = vec![0usize; 256];
+        let mut data = vec![0u8; 64];
+        for _ in 0..samples {
+            rng.fill_bytes(&mut data);
+            let bytes = H::create_label(&data, 1).unwrap().into_bytes();
+
+            // The two most significant bits are masked, so the top byte is below 64.
+            assert!(bytes[31] < 64, "label not masked: {:?}", bytes);
+            high[bytes[31] as usize] += 1;
+            low[bytes[0] as usize] += 1;
+        }
+
+        let chi_squared = |buckets: &[usize]| {
+            let expected = samples as f64 / buckets.len() as f64;
+            buckets
+                .iter()
+                .map(|&observed| (observed as f64 - expected).powi(2) / expected)
+                .sum::<f64>()
+        };
+
+        // Critical values for 63 and 255 degrees of freedom.
+        let high_chi = chi_squared(&high);
+        assert!(high_chi < 103.4, "biased high byte, chi^2 = {}", high_chi);
+        let low_chi = chi_squared(&low);
+        assert!(low_chi < 330.5, "biased low byte, chi^2 = {}", low_chi);
+    }
+
+    #[test]
+    fn create_label_is_uniform_sha256() {
+        create_label_is_uniform::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn create_label_is_uniform_blake2s() {
+        create_label_is_uniform::<Blake2sHasher>();
+    }
+
+    fn accumulate_is_ordered<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let a = H::Domain::random(rng);
+        let b = H::Domain::random(rng);
+        let c = H::Domain::random(rng);
+
+        let fold = |values: &[H::Domain]| {
+            values
+                .iter()
+                .fold(H::Domain::default(), |acc, next| accumulate::<H>(acc, next))
+        };
+
+        let abc = fold(&[a, b, c]);
+        assert_eq!(abc, fold(&[a, b, c]));
+        assert_ne!(abc, fold(&[a, c, b]));
+        assert_ne!(abc, fold(&[c, b, a]));
+
+        let mut function = H::Function::default();
+        let mut expected = H::Domain::default();
+        for next in &[a, b, c] {
+            function.reset();
+            expected = function.node(expected, *next, 0);
+        }
+        assert_eq!(abc, expected);
+    }
+
+    #[test]
+    fn accumulate_pedersen() {
+        accumulate_is_ordered::<PedersenHasher>();
+    }
+
+    #[test]
+    fn accumulate_sha256() {
+        accumulate_is_ordered::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn accumulate_blake2s() {
+        accumulate_is_ordered::<Blake2sHasher>();
+    }
+
+    fn hash_many_is_independent<H: Hasher>() {
+        let a: &[u8] = b"first input";
+        let b: &[u8] = &[7u8; 64];
+        let hash = <H::Function as HashFunction<H::Domain>>::hash;
+
+        assert_eq!(
+            <H::Function as HashFunction<H::Domain>>::hash_many(&[a, b]),
+            vec![hash(a), hash(b)]
+        );
+        assert_eq!(
+            <H::Function as HashFunction<H::Domain>>::hash_many(&[b, a]),
+            vec![hash(b), hash(a)]
+        );
+        assert!(<H::Function as HashFunction<H::Domain>>::hash_many(&[]).is_empty());
+    }
+
+    #[test]
+    fn hash_many_pedersen() {
+        hash_many_is_independent::<PedersenHasher>();
+    }
+
+    #[test]
+    fn hash_many_sha256() {
+        hash_many_is_independent::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn hash_many_blake2s() {
+        hash_many_is_independent::<Blake2sHasher>();
+    }
+
+    fn biguint_roundtrip<H: Hasher>() {
+        let rng = &mut seeded_rng();
+
+        for _ in 0..10 {
+            let d = H::Domain::random(rng);
+            assert_eq!(H::Domain::try_from_biguint(&d.to_biguint()).unwrap(), d);
+        }
+
+        let d: H::Domain = Fr::from_str("1234").unwrap().into();
+        assert_eq!(d.to_biguint(), BigUint::from(1234u32));
+
+        let mut modulus = Vec::new();
+        Fr::char().write_le(&mut modulus).unwrap();
+        let modulus = BigUint::from_bytes_le(&modulus);
+
+        assert!(H::Domain::try_from_biguint(&modulus).is_err());
+        assert!(H::Domain::try_from_biguint(&(modulus.clone() << 8)).is_err());
+        assert!(H::Domain::try_from_biguint(&(modulus - 1u32)).is_ok());
+    }
+
+    #[test]
+    fn biguint_roundtrip_pedersen() {
+        biguint_roundtrip::<PedersenHasher>();
+    }
+
+    #[test]
+    fn biguint_roundtrip_sha256() {
+        biguint_roundtrip::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn biguint_roundtrip_blake2s() {
+        biguint_roundtrip::<Blake2sHasher>();
+    }
+
+    fn size_matches_bytes<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let d = H::Domain::random(rng);
+
+        assert_eq!(d.into_bytes().len(), H::Domain::SIZE);
+        assert_eq!(H::Domain::byte_len(), H::Domain::SIZE);
+
+        let mut buf = vec![0u8; H::Domain::SIZE];
+        d.write_bytes(&mut buf).unwrap();
+        assert_eq!(H::Domain::try_from_bytes(&buf).unwrap(), d);
+
+        assert!(d.write_bytes(&mut buf[1..]).is_err());
+        assert!(H::Domain::try_from_bytes(&buf[1..]).is_err());
+    }
+
+    #[test]
+    fn size_matches_bytes_pedersen() {
+        size_matches_bytes::<PedersenHasher>();
+    }
+
+    #[test]
+    fn size_matches_bytes_sha256() {
+        size_matches_bytes::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn size_matches_bytes_blake2s() {
+        size_matches_bytes::<Blake2sHasher>();
+    }
+
+    fn base64_roundtrip<H: Hasher>() {
+        let rng = &mut seeded_rng();
+
+        for _ in 0..10 {
+            let d = H::Domain::random(rng);
+            let encoded = d.to_base64();
+
+            assert_eq!(encoded.len(), 43);
+            assert!(!encoded.contains('='));
+            assert_eq!(H::Domain::from_base64(&encoded).unwrap(), d);
+        }
+
+        let encoded = H::Domain::random(rng).to_base64();
+        let too_short = base64::encode_config(&[1u8; 31], base64::URL_SAFE_NO_PAD);
+        let too_long = base64::encode_config(&[1u8; 33], base64::URL_SAFE_NO_PAD);
+
+        // Not base64 at all, standard instead of URL safe characters, and wrong lengths.
+        for malformed in &[
+            "not base64!",
+            "ab+/",
+            &encoded[..40],
+            &too_short[..],
+            &too_long[..],
+        ] {
+            match H::Domain::from_base64(malformed) {
+                Err(err) => match err.downcast::<Error>() {
+                    Ok(Error::InvalidBase64(_)) => {}
+                    other => panic!("unexpected error for {:?}: {:?}", malformed, other),
+                },
+                Ok(_) => panic!("decoded malformed input {:?}", malformed),
+            }
+        }
+    }
+
+    fn hex_roundtrip<H: Hasher>() {
+        let rng = &mut seeded_rng();
+
+        for _ in 0..10 {
+            let d = H::Domain::random(rng);
+            let encoded = d.to_hex();
+
+            assert_eq!(encoded.len(), 64);
+            assert_eq!(H::Domain::from_hex(&encoded).unwrap(), d);
+            assert_eq!(H::Domain::from_hex(&encoded.to_uppercase()).unwrap(), d);
+        }
+
+        let encoded = H::Domain::random(rng).to_hex();
+        let too_short = hex::encode(&[1u8; 31]);
+        let too_long = hex::encode(&[1u8; 33]);
+        let bad_char = format!("{}g", &encoded[..63]);
+
+        // Not hex at all, a non hex digit, an odd number of digits, and wrong lengths.
+        for malformed in &[
+            "not hex!",
+            &bad_char[..],
+            &encoded[..63],
+            &too_short[..],
+            &too_long[..],
+        ] {
+            match H::Domain::from_hex(malformed) {
+                Err(err) => match err.downcast::<Error>() {
+                    Ok(Error::InvalidHex(_)) => {}
+                    other => panic!("unexpected error for {:?}: {:?}", malformed, other),
+                },
+                Ok(_) => panic!("decoded malformed input {:?}", malformed),
+            }
+        }
+    }
+
+    #[test]
+    fn hex_roundtrip_pedersen() {
+        hex_roundtrip::<PedersenHasher>();
+    }
+
+    #[test]
+    fn hex_roundtrip_sha256() {
+        hex_roundtrip::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn hex_roundtrip_blake2s() {
+        hex_roundtrip::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn base64_roundtrip_pedersen() {
+        base64_roundtrip::<PedersenHasher>();
+    }
+
+    #[test]
+    fn base64_roundtrip_sha256() {
+        base64_roundtrip::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn base64_roundtrip_blake2s() {
+        base64_roundtrip::<Blake2sHasher>();
+    }
+
+    /// Fails to compile unless `D` can be hashed by `F`, which is required to build a `MerkleTree`
+    /// over `D`. Building a small tree checks the pair is usable in practice as well.
+    fn assert_hashable<D, F>()
+    where
+        D: Domain + LightHashable<F>,
+        F: HashFunction<D>,
+    {
+        let leaves = vec![D::default(); 4];
+        let tree = crate::merkle::MerkleTree::<D, F>::try_from_iter(leaves.into_iter().map(Ok))
+            .expect("failed to build tree");
+
+        assert_eq!(tree.leafs(), 4);
+    }
+
+    #[test]
+    fn domains_are_hashable() {
+        use crate::hasher::{blake2s, pedersen, sha256};
+
+        assert_hashable::<pedersen::PedersenDomain, pedersen::PedersenFunction>();
+        assert_hashable::<sha256::Sha256Domain, sha256::Sha256Function>();
+        assert_hashable::<blake2s::Blake2sDomain, blake2s::Blake2sFunction>();
+    }
+}
@@ -4,9 +4,14 @@
 
 #[macro_use]
 pub mod test_helper;
+#[macro_use]
+pub mod span;
 
 pub mod example_helper;
 
+pub mod bit_order;
+pub mod cancel;
+pub mod challenge_domain;
 pub mod circuit;
 pub mod compound_proof;
 pub mod crypto;
@@ -31,6 +36,8 @@ pub mod settings;
 pub mod stacked;
 pub mod stacked_old;
 pub mod util;
+#[cfg(test)]
+mod vectors;
 
 pub(crate) const TEST_SEED: [u8; 16] = [
     0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc, 0xe5,
@@ -1,18 +1,25 @@
 #![allow(clippy::len_without_is_empty)]
 
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
 use std::marker::PhantomData;
+use std::path::Path;
 
-use anyhow::ensure;
+use anyhow::{ensure, Context};
 use merkletree::hash::Algorithm;
 use merkletree::merkle;
+use merkletree::merkle::Element;
 use merkletree::proof;
 use merkletree::store::StoreConfig;
 use paired::bls12_381::Fr;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::cancel::CancellationToken;
+use crate::drgraph::{graph_height_arity, PARALLEL_MERKLE};
 use crate::error::*;
-use crate::hasher::{Domain, Hasher};
+use crate::hasher::{Domain, HashFunction, Hasher};
 use crate::util::{data_at_node, NODE_SIZE};
 
 // Reexport here, so we don't depend on merkletree directly in other places.
@@ -23,10 +30,114 @@ type DiskStore<E> = merkletree::store::DiskStore<E>;
 pub type MerkleTree<T, A> = merkle::MerkleTree<T, A, DiskStore<T>>;
 pub type MerkleStore<T> = DiskStore<T>;
 
+/// Additional accessors on `MerkleTree`.
+pub trait MerkleTreeExt<T: Element> {
+    /// Returns the number of leaves of the tree.
+    fn leaves(&self) -> usize;
+
+    /// Returns the depth of the tree, i.e. the length of an authentication path.
+    fn depth(&self) -> usize;
+
+    /// Like `gen_proof`, but returns an error instead of panicking if `i` is not a valid leaf
+    /// index.
+    fn gen_proof_checked(&self, i: usize) -> Result<proof::Proof<T>>;
+
+    /// Like `gen_proof_checked`, but also returns the value of leaf `i`. The leaf is taken from
+    /// the proof itself, so the two always agree.
+    fn gen_proof_with_leaf(&self, i: usize) -> Result<(T, proof::Proof<T>)>;
+
+    /// Generates the proofs for all `challenges`, identical to calling `gen_proof` for each of
+    /// them. Nodes shared between the paths, as for clustered challenges, are only read once.
+    fn gen_proofs(&self, challenges: &[usize]) -> Result<Vec<proof::Proof<T>>>;
+
+    /// Returns the root as a field element, as needed for circuit inputs. Use `root()` when
+    /// comparing against other domain elements, such as commitments or proof roots.
+    fn root_fr(&self) -> Fr
+    where
+        T: Into<Fr>;
+}
+
+impl<T: Element, A: Algorithm<T>> MerkleTreeExt<T> for MerkleTree<T, A> {
+    fn leaves(&self) -> usize {
+        self.leafs()
+    }
+
+    fn depth(&self) -> usize {
+        // `height` includes the leaves.
+        self.height() - 1
+    }
+
+    fn gen_proof_checked(&self, i: usize) -> Result<proof::Proof<T>> {
+        ensure!(i < self.leaves(), Error::OutOfBounds(i, self.leaves()));
+
+        self.gen_proof(i)
+    }
+
+    fn gen_proof_with_leaf(&self, i: usize) -> Result<(T, proof::Proof<T>)> {
+        let proof = self.gen_proof_checked(i)?;
+
+        Ok((proof.item(), proof))
+    }
+
+    fn gen_proofs(&self, challenges: &[usize]) -> Result<Vec<proof::Proof<T>>> {
+        let mut nodes: HashMap<usize, T> = HashMap::new();
+        let mut read = |index: usize| -> Result<T> {
+            if let Some(node) = nodes.get(&index) {
+                return Ok(node.clone());
+            }
+            let node = self.read_at(index)?;
+            nodes.insert(index, node.clone());
+
+            Ok(node)
+        };
+
+        let root = self.root();
+        challenges
+            .iter()
+            .map(|&challenge| {
+                ensure!(
+                    challenge < self.leaves(),
+                    Error::OutOfBounds(challenge, self.leaves())
+                );
+
+                let mut lemma = Vec::with_capacity(self.height() + 1);
+                let mut path = Vec::with_capacity(self.height() - 1);
+                lemma.push(read(challenge)?);
+
+                // Walk up the layers, which are stored one after the other, starting with the
+                // leaves.
+                let mut base = 0;
+                let mut width = self.leaves();
+                let mut j = challenge;
+                while width > 1 {
+                    let is_left = j % 2 == 0;
+                    let sibling = if is_left { j + 1 } else { j - 1 };
+                    lemma.push(read(base + sibling)?);
+                    path.push(is_left);
+
+                    base += width;
+                    width >>= 1;
+                    j >>= 1;
+                }
+                lemma.push(root.clone());
+
+                Ok(proof::Proof::new(lemma, path))
+            })
+            .collect()
+    }
+
+    fn root_fr(&self) -> Fr
+    where
+        T: Into<Fr>,
+    {
+        self.root().into()
+    }
+}
+
 /// Representation of a merkle proof.
 /// Each element in the `path` vector consists of a tuple `(hash, is_right)`, with `hash` being the the hash of the node at the current level and `is_right` a boolean indicating if the path is taking the right path.
 /// The first element is the hash of leaf itself, and the last is the root hash.
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MerkleProof<H: Hasher> {
     pub root: H::Domain,
     path: Vec<(H::Domain, bool)>,
@@ -98,21 +209,7 @@ impl<H: Hasher> MerkleProof<H> {
     }
 
     fn verify(&self) -> bool {
-        let mut a = H::Function::default();
-
-        self.root()
-            == &(0..self.path.len()).fold(self.leaf, |h, i| {
-                a.reset();
-                let is_right = self.path[i].1;
-
-                let (left, right) = if is_right {
-                    (self.path[i].0, h)
-                } else {
-                    (h, self.path[i].0)
-                };
-
-                a.node(left, right, i)
-            })
+        verify_inclusion::<H>(self.leaf, &self.path, self.root)
     }
 
     /// Validates the MerkleProof and that it corresponds to the supplied node.
@@ -186,6 +283,120 @@ impl<H: Hasher> MerkleProof<H> {
     }
 }
 
+#[cfg(test)]
+impl<H: Hasher> MerkleProof<H> {
+    /// Replaces the sibling at `index` of the path, counting from the leaf, to build invalid proofs.
+    pub fn with_tampered_sibling(mut self, index: usize, new_value: H::Domain) -> Self {
+        self.path[index].0 = new_value;
+        self
+    }
+
+    /// Replaces the root the proof claims to lead to.
+    pub fn with_tampered_root(mut self, root: H::Domain) -> Self {
+        self.root = root;
+        self
+    }
+}
+
+/// Many `MerkleProof`s over the same tree, storing every distinct sibling only once.
+///
+/// Proofs for nearby challenges share most of the upper part of their paths, so for clustered
+/// challenges this is much smaller than the proofs themselves. `decompress` restores the original
+/// proofs exactly.
+#[derive(Debug, Clone)]
+pub struct CompressedProofSet<H: Hasher> {
+    root: H::Domain,
+    depth: usize,
+    /// The siblings of all proofs, by level (counting from the leaves) and index in that level.
+    siblings: HashMap<(usize, usize), H::Domain>,
+    /// The challenged leaf index and leaf of every proof, in order.
+    leaves: Vec<(usize, H::Domain)>,
+}
+
+impl<H: Hasher> CompressedProofSet<H> {
+    /// Compresses `proofs`, which must all be for the same tree. Fails if they are not, or if two
+    /// proofs disagree on a shared sibling.
+    pub fn compress(proofs: &[MerkleProof<H>]) -> Result<Self> {
+        ensure!(!proofs.is_empty(), "no proofs to compress");
+
+        let root = *proofs[0].root();
+        let depth = proofs[0].path().len();
+        let mut siblings = HashMap::new();
+        let mut leaves = Vec::with_capacity(proofs.len());
+
+        for proof in proofs {
+            ensure!(*proof.root() == root, "proofs are for different trees");
+            ensure!(
+                proof.path().len() == depth,
+                Error::ProofPathLengthMismatch {
+                    expected: depth,
+                    got: proof.path().len(),
+                }
+            );
+
+            let challenge = path_index(proof.path());
+            for (level, (sibling, _)) in proof.path().iter().enumerate() {
+                let index = (challenge >> level) ^ 1;
+                let existing = siblings.entry((level, index)).or_insert(*sibling);
+                ensure!(
+                    *existing == *sibling,
+                    "proofs disagree on node {} of level {}",
+                    index,
+                    level
+                );
+            }
+            leaves.push((challenge, *proof.leaf()));
+        }
+
+        Ok(CompressedProofSet {
+            root,
+            depth,
+            siblings,
+            leaves,
+        })
+    }
+
+    /// Reconstructs the proofs passed to `compress`, in the same order.
+    pub fn decompress(&self) -> Vec<MerkleProof<H>> {
+        self.leaves
+            .iter()
+            .map(|&(challenge, leaf)| {
+                let path = (0..self.depth)
+                    .map(|level| {
+                        let index = (challenge >> level) ^ 1;
+                        (
+                            self.siblings[&(level, index)],
+                            (challenge >> level) & 1 == 1,
+                        )
+                    })
+                    .collect();
+
+                MerkleProof {
+                    root: self.root,
+                    path,
+                    leaf,
+                    _h: PhantomData,
+                }
+            })
+            .collect()
+    }
+
+    /// The number of proofs in the set.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// The number of domain elements stored: the distinct siblings, the leaves and the root.
+    pub fn stored_nodes(&self) -> usize {
+        self.siblings.len() + self.leaves.len() + 1
+    }
+
+    /// The number of domain elements the uncompressed proofs take, see `MerkleProof::len`.
+    pub fn uncompressed_nodes(&self) -> usize {
+        self.leaves.len() * (self.depth + 2)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IncludedNode<H: Hasher> {
     value: H::Domain,
@@ -213,22 +424,133 @@ impl<H: Hasher> std::ops::Deref for IncludedNode<H> {
     }
 }
 
+/// Checks that `leaf` is included in the tree with the given `root`, by climbing the authentication
+/// `path` (in the same `(hash, is_right)` format as `MerkleProof`). This is the native equivalent of
+/// what the PoR circuit enforces, and needs no access to the tree itself.
+pub fn verify_inclusion<H: Hasher>(
+    leaf: H::Domain,
+    path: &[(H::Domain, bool)],
+    root: H::Domain,
+) -> bool {
+    let mut a = H::Function::default();
+
+    root == path
+        .iter()
+        .enumerate()
+        .fold(leaf, |h, (i, (sibling, is_right))| {
+            a.reset();
+
+            let (left, right) = if *is_right {
+                (*sibling, h)
+            } else {
+                (h, *sibling)
+            };
+
+            a.node(left, right, i)
+        })
+}
+
 fn path_index<T: Domain>(path: &[(T, bool)]) -> usize {
     path.iter().rev().fold(0, |acc, (_, is_right)| {
         (acc << 1) + if *is_right { 1 } else { 0 }
     })
 }
 
+/// Controls how a single merkle tree is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MerkleBuildOptions {
+    /// Whether to hash the leaves in parallel. Disable it for builds which already run inside a
+    /// rayon task, to avoid nesting.
+    pub parallel: bool,
+    /// The number of threads of a dedicated pool for a parallel build. The global pool is used if
+    /// `None`.
+    pub threads: Option<usize>,
+}
+
+impl Default for MerkleBuildOptions {
+    fn default() -> Self {
+        MerkleBuildOptions {
+            parallel: PARALLEL_MERKLE,
+            threads: None,
+        }
+    }
+}
+
+impl MerkleBuildOptions {
+    /// Builds the dedicated pool of a parallel build, if requested.
+    fn thread_pool(&self) -> Result<Option<rayon::ThreadPool>> {
+        match self.threads {
+            Some(threads) if self.parallel => {
+                ensure!(threads > 0, "a parallel build needs at least one thread");
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .context("failed to build thread pool")?;
+
+                Ok(Some(pool))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
 /// Construct a new merkle tree.
 pub fn create_merkle_tree<H: Hasher>(
     config: Option<StoreConfig>,
     size: usize,
     data: &[u8],
 ) -> Result<MerkleTree<H::Domain, H::Function>> {
+    create_merkle_tree_with_options::<H>(config, size, data, MerkleBuildOptions::default())
+}
+
+/// Like `create_merkle_tree`, but built as controlled by `options` instead of the crate wide
+/// defaults.
+pub fn create_merkle_tree_with_options<H: Hasher>(
+    config: Option<StoreConfig>,
+    size: usize,
+    data: &[u8],
+    options: MerkleBuildOptions,
+) -> Result<MerkleTree<H::Domain, H::Function>> {
+    // Run the whole build on the dedicated pool, if any.
+    if let Some(pool) = options.thread_pool()? {
+        let options = MerkleBuildOptions {
+            threads: None,
+            ..options
+        };
+        return pool.install(|| create_merkle_tree_with_options::<H>(config, size, data, options));
+    }
+
     ensure!(
         data.len() == (NODE_SIZE * size) as usize,
         Error::InvalidMerkleTreeArgs(data.len(), NODE_SIZE, size)
     );
+    enter_span!("create_merkle_tree", nodes = size);
+
+    let parallel = options.parallel && rayon::current_num_threads() > 1;
+    if size.is_power_of_two() && size > 1 {
+        let leaf = |i| H::Domain::try_from_bytes(data_at_node(&data, i)?);
+        let leafs = if parallel {
+            (0..size)
+                .into_par_iter()
+                .map(leaf)
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            (0..size).map(leaf).collect::<Result<Vec<_>>>()?
+        };
+
+        return merkle_tree_from_leafs::<H>(leafs, config, parallel);
+    }
+
+    if !parallel {
+        // Nothing to gain from the parallel iterator, build serially instead, which produces the
+        // same tree and allows reporting bad input as an error.
+        let leafs = (0..size).map(|i| H::Domain::try_from_bytes(data_at_node(&data, i)?));
+
+        return match config {
+            Some(x) => MerkleTree::try_from_iter_with_config(leafs, x),
+            None => MerkleTree::try_from_iter(leafs),
+        };
+    }
 
     let f = |i| {
         // TODO Replace `expect()` with `context()` (problem is the parallel iterator)
@@ -247,6 +569,406 @@ pub fn create_merkle_tree<H: Hasher>(
     }
 }
 
+/// How many pairs of a row are handed to `HashFunction::node_many` at once.
+const ROW_CHUNK_SIZE: usize = 256;
+
+/// Hashes the row above `row`, whose nodes are at `height`, chunk by chunk, in parallel if
+/// `parallel` is set.
+fn hash_row<H: Hasher>(row: &[H::Domain], height: usize, parallel: bool) -> Vec<H::Domain> {
+    let node_many = |chunk: &[H::Domain]| {
+        let pairs: Vec<(H::Domain, H::Domain)> =
+            chunk.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+        <H::Function as HashFunction<H::Domain>>::node_many(&pairs, height)
+    };
+
+    if parallel {
+        row.par_chunks(2 * ROW_CHUNK_SIZE)
+            .flat_map(node_many)
+            .collect()
+    } else {
+        row.chunks(2 * ROW_CHUNK_SIZE).flat_map(node_many).collect()
+    }
+}
+
+/// Writes `row` into `store`, starting at node `start`, `ROW_CHUNK_SIZE` nodes at a time, so the
+/// row is never held as bytes as a whole.
+fn write_row<E: Domain>(store: &mut MerkleStore<E>, row: &[E], start: usize) -> Result<()> {
+    let mut buf = vec![0u8; ROW_CHUNK_SIZE * NODE_SIZE];
+    for (i, chunk) in row.chunks(ROW_CHUNK_SIZE).enumerate() {
+        let bytes = &mut buf[..chunk.len() * NODE_SIZE];
+        for (node, out) in chunk.iter().zip(bytes.chunks_mut(NODE_SIZE)) {
+            node.write_bytes(out)?;
+        }
+        store.copy_from_slice(bytes, start + i * ROW_CHUNK_SIZE)?;
+    }
+
+    Ok(())
+}
+
+/// Builds a tree over `leafs`, a power of two of them, one row at a time with `hash_row`. The
+/// nodes are laid out as `MerkleTree` does, row by row from the leaves up to the root, and every
+/// row is written to the store as soon as it is hashed, so only two rows are held in memory.
+fn merkle_tree_from_leafs<H: Hasher>(
+    leafs: Vec<H::Domain>,
+    config: Option<StoreConfig>,
+    parallel: bool,
+) -> Result<MerkleTree<H::Domain, H::Function>> {
+    let leaf_count = leafs.len();
+    ensure!(
+        leaf_count.is_power_of_two() && leaf_count > 1,
+        "leaf count {} is not a power of two larger than one",
+        leaf_count
+    );
+
+    let mut store = match config {
+        Some(config) => MerkleStore::new_with_config(2 * leaf_count - 1, config)?,
+        None => MerkleStore::new(2 * leaf_count - 1)?,
+    };
+
+    let mut row = leafs;
+    let mut row_start = 0;
+    let mut height = 0;
+    loop {
+        write_row(&mut store, &row, row_start)?;
+        if row.len() == 1 {
+            break;
+        }
+
+        row_start += row.len();
+        row = hash_row::<H>(&row, height, parallel);
+        height += 1;
+    }
+
+    MerkleTree::from_data_store(store, leaf_count)
+}
+
+/// Like `create_merkle_tree`, but checks `cancel` before every leaf and returns `Error::Cancelled`
+/// as soon as it is set. The tree is always built serially, so the check happens in order.
+pub fn create_merkle_tree_cancellable<H: Hasher>(
+    config: Option<StoreConfig>,
+    size: usize,
+    data: &[u8],
+    cancel: &CancellationToken,
+) -> Result<MerkleTree<H::Domain, H::Function>> {
+    ensure!(
+        data.len() == (NODE_SIZE * size) as usize,
+        Error::InvalidMerkleTreeArgs(data.len(), NODE_SIZE, size)
+    );
+    enter_span!("create_merkle_tree", nodes = size);
+
+    let leafs = (0..size).map(|i| {
+        cancel.check()?;
+        H::Domain::try_from_bytes(data_at_node(&data, i)?)
+    });
+
+    match config {
+        Some(x) => MerkleTree::try_from_iter_with_config(leafs, x),
+        None => MerkleTree::try_from_iter(leafs),
+    }
+}
+
+/// Combines complete subtrees, each held by one of `stores` and built over `leafs` leaves, into a
+/// single tree over all of their leaves, in order. Only the levels above the subtree roots are
+/// hashed, so the subtrees, e.g. one per piece, can be built independently and in parallel.
+pub fn combine_merkle_stores<H: Hasher>(
+    stores: &[MerkleStore<H::Domain>],
+    leafs: usize,
+) -> Result<MerkleTree<H::Domain, H::Function>> {
+    ensure!(
+        stores.len().is_power_of_two(),
+        "can only combine a power of two subtrees, got {}",
+        stores.len()
+    );
+    ensure!(
+        leafs.is_power_of_two(),
+        "subtree leaf count {} is not a power of two",
+        leafs
+    );
+    for store in stores {
+        ensure!(store.len() == 2 * leafs - 1, Error::MalformedMerkleTree);
+    }
+
+    let mut store = MerkleStore::new(2 * leafs * stores.len() - 1)?;
+    let mut pos = 0;
+
+    // The levels within the subtrees, from the leaves up to the subtree roots.
+    let mut roots = Vec::with_capacity(stores.len());
+    let mut start = 0;
+    let mut width = leafs;
+    while width > 0 {
+        for subtree in stores {
+            let level = subtree.read_range(start..start + width)?;
+            write_row(&mut store, &level, pos)?;
+            pos += width;
+            if width == 1 {
+                roots.push(level[0]);
+            }
+        }
+        start += width;
+        width /= 2;
+    }
+
+    // The levels above the subtree roots.
+    let mut height = graph_height_arity(leafs, 2);
+    let mut level = roots;
+    while level.len() > 1 {
+        level = hash_row::<H>(&level, height, true);
+        write_row(&mut store, &level, pos)?;
+        pos += level.len();
+        height += 1;
+    }
+
+    MerkleTree::from_data_store(store, leafs * stores.len())
+}
+
+/// Space reserved in the header for the name of the hasher, zero padded.
+const HASHER_NAME_SIZE: usize = 32;
+
+/// Size of the header written in front of a persisted merkle tree: the number of leaves as a
+/// little endian `u64`, the name of the hasher the tree was built with, and the root.
+const TREE_HEADER_SIZE: usize = 8 + HASHER_NAME_SIZE + NODE_SIZE;
+
+/// Persists all nodes of the tree to `path`, prefixed by a header holding the leaf count, the
+/// hasher name and the root, so it can later be reloaded with `load_verified_merkle_tree`.
+pub fn store_merkle_tree<H: Hasher, P: AsRef<Path>>(
+    tree: &MerkleTree<H::Domain, H::Function>,
+    path: P,
+) -> Result<()> {
+    let path = path.as_ref();
+    let name = H::name();
+    ensure!(
+        name.len() <= HASHER_NAME_SIZE,
+        "hasher name {:?} does not fit into the header",
+        name
+    );
+
+    let mut buf = vec![0u8; TREE_HEADER_SIZE + tree.len() * NODE_SIZE];
+
+    buf[..8].copy_from_slice(&(tree.leafs() as u64).to_le_bytes());
+    buf[8..8 + name.len()].copy_from_slice(name.as_bytes());
+    tree.root()
+        .write_bytes(&mut buf[8 + HASHER_NAME_SIZE..TREE_HEADER_SIZE])?;
+    for (i, node) in buf[TREE_HEADER_SIZE..].chunks_mut(NODE_SIZE).enumerate() {
+        tree.read_at(i)?.write_bytes(node)?;
+    }
+
+    let mut file = File::create(path).with_context(|| format!("could not create {:?}", path))?;
+    file.write_all(&buf)
+        .with_context(|| format!("could not write {:?}", path))?;
+
+    Ok(())
+}
+
+/// Loads a merkle tree persisted with `store_merkle_tree`, rebuilding it from the stored leaves.
+/// Fails with `Error::HasherMismatch` if the tree was stored with a different hasher than `H`, and
+/// with `Error::TreeCorrupted` if the rebuilt root does not match the header, or if any stored node
+/// differs from the rebuilt one.
+pub fn load_verified_merkle_tree<H: Hasher, P: AsRef<Path>>(
+    path: P,
+) -> Result<MerkleTree<H::Domain, H::Function>> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).with_context(|| format!("could not read {:?}", path))?;
+    ensure!(data.len() >= TREE_HEADER_SIZE, Error::TreeCorrupted);
+
+    let mut leafs = [0u8; 8];
+    leafs.copy_from_slice(&data[..8]);
+    let leafs = u64::from_le_bytes(leafs) as usize;
+
+    let name = &data[8..8 + HASHER_NAME_SIZE];
+    let name = &name[..name
+        .iter()
+        .position(|b| *b == 0)
+        .unwrap_or(HASHER_NAME_SIZE)];
+    ensure!(
+        name == H::name().as_bytes(),
+        Error::HasherMismatch {
+            expected: H::name(),
+            found: String::from_utf8_lossy(name).into_owned(),
+        }
+    );
+
+    let root = H::Domain::try_from_bytes(&data[8 + HASHER_NAME_SIZE..TREE_HEADER_SIZE])?;
+
+    let nodes = &data[TREE_HEADER_SIZE..];
+    let leafs_len = leafs.checked_mul(NODE_SIZE).ok_or(Error::TreeCorrupted)?;
+    ensure!(
+        leafs > 0 && nodes.len() % NODE_SIZE == 0 && nodes.len() >= leafs_len,
+        Error::TreeCorrupted
+    );
+
+    let tree = create_merkle_tree::<H>(None, leafs, &nodes[..leafs_len])?;
+    ensure!(tree.root() == root, Error::TreeCorrupted);
+    ensure!(tree.len() * NODE_SIZE == nodes.len(), Error::TreeCorrupted);
+
+    for (i, stored) in nodes.chunks(NODE_SIZE).enumerate() {
+        ensure!(
+            AsRef::<[u8]>::as_ref(&tree.read_at(i)?) == stored,
+            Error::TreeCorrupted
+        );
+    }
+
+    Ok(tree)
+}
+
+/// The arities supported by `ArityMerkleTree`.
+pub const SUPPORTED_ARITIES: &[usize] = &[2, 4];
+
+/// Hashes the `children` of a node at `height` (with the leaves at height 0). For two children this
+/// is the binary node hash used by `MerkleTree`, wider nodes hash the concatenation of the children.
+pub fn hash_children<H: Hasher>(children: &[H::Domain], height: usize) -> H::Domain {
+    if let [left, right] = children {
+        let mut a = H::Function::default();
+        return a.node(*left, *right, height);
+    }
+
+    let bytes: Vec<u8> = children.iter().flat_map(|c| c.into_bytes()).collect();
+    <H::Function as HashFunction<H::Domain>>::hash(&bytes)
+}
+
+/// An in memory merkle tree where every inner node has `arity` children. With arity 2 it has the
+/// same root as the `MerkleTree` over the same leaves, wider trees have shorter inclusion proofs.
+#[derive(Debug, Clone)]
+pub struct ArityMerkleTree<H: Hasher> {
+    arity: usize,
+    /// All layers, starting with the leaves and ending with the root.
+    layers: Vec<Vec<H::Domain>>,
+}
+
+impl<H: Hasher> ArityMerkleTree<H> {
+    /// Builds the tree over `leaves`, whose number must be a power of `arity`.
+    pub fn new(arity: usize, leaves: Vec<H::Domain>) -> Result<Self> {
+        ensure!(
+            SUPPORTED_ARITIES.contains(&arity),
+            Error::UnsupportedArity(arity)
+        );
+        let height = graph_height_arity(leaves.len(), arity);
+        ensure!(
+            !leaves.is_empty() && arity.pow(height as u32) == leaves.len(),
+            Error::InvalidInputSize
+        );
+
+        let mut layers = vec![leaves];
+        for height in 0..height {
+            let next = layers[height]
+                .chunks(arity)
+                .map(|children| hash_children::<H>(children, height))
+                .collect();
+            layers.push(next);
+        }
+
+        Ok(ArityMerkleTree { arity, layers })
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn leaves(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    /// Returns the depth of the tree, i.e. the length of an authentication path.
+    pub fn depth(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    pub fn root(&self) -> H::Domain {
+        self.layers[self.depth()][0]
+    }
+
+    /// Generates the inclusion proof of leaf `i`.
+    pub fn gen_proof(&self, i: usize) -> Result<ArityMerkleProof<H>> {
+        ensure!(i < self.leaves(), Error::OutOfBounds(i, self.leaves()));
+
+        let mut index = i;
+        let path = self.layers[..self.depth()]
+            .iter()
+            .map(|layer| {
+                let start = index - index % self.arity;
+                let position = index % self.arity;
+                let siblings = layer[start..start + self.arity]
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != position)
+                    .map(|(_, sibling)| *sibling)
+                    .collect();
+                index /= self.arity;
+
+                (siblings, position)
+            })
+            .collect();
+
+        Ok(ArityMerkleProof {
+            arity: self.arity,
+            root: self.root(),
+            leaf: self.layers[0][i],
+            path,
+        })
+    }
+}
+
+/// Builds an `ArityMerkleTree` over `size` nodes of `data`.
+pub fn create_merkle_tree_with_arity<H: Hasher>(
+    arity: usize,
+    size: usize,
+    data: &[u8],
+) -> Result<ArityMerkleTree<H>> {
+    ensure!(
+        data.len() == NODE_SIZE * size,
+        Error::InvalidMerkleTreeArgs(data.len(), NODE_SIZE, size)
+    );
+
+    let leaves = (0..size)
+        .map(|i| H::Domain::try_from_bytes(data_at_node(data, i)?))
+        .collect::<Result<Vec<_>>>()?;
+
+    ArityMerkleTree::new(arity, leaves)
+}
+
+/// Inclusion proof of an `ArityMerkleTree`. Each element of the `path` holds the `arity - 1`
+/// siblings at that level, in order, and the position of the climbed node among its siblings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArityMerkleProof<H: Hasher> {
+    pub arity: usize,
+    pub root: H::Domain,
+    pub leaf: H::Domain,
+    path: Vec<(Vec<H::Domain>, usize)>,
+}
+
+impl<H: Hasher> ArityMerkleProof<H> {
+    pub fn path(&self) -> &[(Vec<H::Domain>, usize)] {
+        &self.path
+    }
+
+    /// Returns the index of the leaf this proof is for.
+    pub fn index(&self) -> usize {
+        self.path
+            .iter()
+            .rev()
+            .fold(0, |acc, (_, position)| acc * self.arity + position)
+    }
+
+    /// Validates the proof and that it corresponds to the leaf `node`.
+    pub fn validate(&self, node: usize) -> bool {
+        if self.index() != node {
+            return false;
+        }
+
+        let mut current = self.leaf;
+        for (height, (siblings, position)) in self.path.iter().enumerate() {
+            if siblings.len() + 1 != self.arity || *position >= self.arity {
+                return false;
+            }
+
+            let mut children = siblings.clone();
+            children.insert(*position, current);
+            current = hash_children::<H>(&children, height);
+        }
+
+        current == self.root
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -254,8 +976,24 @@ mod tests {
     use rand;
     use std::io::Write;
 
+    use merkletree::store::DEFAULT_CACHED_ABOVE_BASE_LAYER;
+    use rand_xorshift::XorShiftRng;
+
     use crate::drgraph::{new_seed, BucketGraph, Graph, BASE_DEGREE};
     use crate::hasher::{Blake2sHasher, PedersenHasher, Sha256Hasher};
+    use crate::test_helper::{random_data, seeded_rng};
+
+    /// A tree over `random_data`, built through a `BucketGraph`, together with the data.
+    fn random_tree<H: Hasher>(
+        leafs: usize,
+        rng: &mut XorShiftRng,
+    ) -> (Vec<u8>, MerkleTree<H::Domain, H::Function>) {
+        let g = BucketGraph::<H>::new(leafs, BASE_DEGREE, 0, new_seed()).unwrap();
+        let data = random_data(leafs, rng);
+        let tree = g.merkle_tree(data.as_slice()).unwrap();
+
+        (data, tree)
+    }
 
     fn merklepath<H: Hasher>() {
         let g = BucketGraph::<H>::new(10, BASE_DEGREE, 0, new_seed()).unwrap();
@@ -301,4 +1039,695 @@ mod tests {
     fn merklepath_blake2s() {
         merklepath::<Blake2sHasher>();
     }
+
+    fn serial_fallback<H: Hasher>() {
+        let leafs = 64;
+        let rng = &mut seeded_rng();
+        let data = random_data(leafs, rng);
+
+        let parallel = create_merkle_tree::<H>(None, leafs, &data).unwrap();
+        let serial = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(|| create_merkle_tree::<H>(None, leafs, &data))
+            .unwrap();
+
+        assert_eq!(serial.root(), parallel.root());
+        assert_eq!(serial.len(), parallel.len());
+    }
+
+    #[test]
+    fn serial_fallback_pedersen() {
+        serial_fallback::<PedersenHasher>();
+    }
+
+    #[test]
+    fn serial_fallback_sha256() {
+        serial_fallback::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn serial_fallback_blake2s() {
+        serial_fallback::<Blake2sHasher>();
+    }
+
+    fn rows_match_merkletree<H: Hasher>() {
+        // Enough leaves for the lower rows to span several chunks.
+        let leafs = 4 * ROW_CHUNK_SIZE;
+        let rng = &mut seeded_rng();
+        let data = random_data(leafs, rng);
+
+        let expected = MerkleTree::<H::Domain, H::Function>::try_from_iter(
+            (0..leafs).map(|i| H::Domain::try_from_bytes(data_at_node(&data, i)?)),
+        )
+        .unwrap();
+        for parallel in &[false, true] {
+            let options = MerkleBuildOptions {
+                parallel: *parallel,
+                threads: None,
+            };
+            let tree = create_merkle_tree_with_options::<H>(None, leafs, &data, options).unwrap();
+
+            assert_eq!(tree.len(), expected.len());
+            for i in 0..tree.len() {
+                assert_eq!(tree.read_at(i).unwrap(), expected.read_at(i).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn rows_match_merkletree_pedersen() {
+        rows_match_merkletree::<PedersenHasher>();
+    }
+
+    #[test]
+    fn rows_match_merkletree_sha256() {
+        rows_match_merkletree::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn rows_match_merkletree_blake2s() {
+        rows_match_merkletree::<Blake2sHasher>();
+    }
+
+    fn build_options<H: Hasher>() {
+        let leafs = 64;
+        let rng = &mut seeded_rng();
+        let data = random_data(leafs, rng);
+
+        let default = create_merkle_tree::<H>(None, leafs, &data).unwrap();
+        for options in &[
+            MerkleBuildOptions {
+                parallel: false,
+                threads: None,
+            },
+            MerkleBuildOptions {
+                parallel: true,
+                threads: None,
+            },
+            MerkleBuildOptions {
+                parallel: true,
+                threads: Some(3),
+            },
+        ] {
+            let tree = create_merkle_tree_with_options::<H>(None, leafs, &data, *options).unwrap();
+            assert_eq!(tree.root(), default.root(), "{:?}", options);
+            assert_eq!(tree.len(), default.len());
+        }
+    }
+
+    #[test]
+    fn build_options_pedersen() {
+        build_options::<PedersenHasher>();
+    }
+
+    #[test]
+    fn build_options_sha256() {
+        build_options::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn build_options_blake2s() {
+        build_options::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn build_options_threads() {
+        let options = MerkleBuildOptions {
+            parallel: true,
+            threads: Some(3),
+        };
+        let pool = options.thread_pool().unwrap().expect("missing pool");
+        assert_eq!(pool.current_num_threads(), 3);
+
+        // Serial builds never spawn a pool.
+        let serial = MerkleBuildOptions {
+            parallel: false,
+            ..options
+        };
+        assert!(serial.thread_pool().unwrap().is_none());
+        assert!(MerkleBuildOptions::default()
+            .thread_pool()
+            .unwrap()
+            .is_none());
+
+        let none = MerkleBuildOptions {
+            parallel: true,
+            threads: Some(0),
+        };
+        assert!(none.thread_pool().is_err());
+    }
+
+    fn gen_proof_checked<H: Hasher>() {
+        let leafs = 16;
+        let rng = &mut seeded_rng();
+        let (_, tree) = random_tree::<H>(leafs, rng);
+
+        assert_eq!(tree.leaves(), leafs);
+        assert_eq!(tree.depth(), 4);
+
+        let proof = tree.gen_proof_checked(tree.leaves() - 1).unwrap();
+        assert_eq!(proof.path().len(), tree.depth());
+        assert!(proof.validate::<H::Function>());
+
+        assert!(tree.gen_proof_checked(tree.leaves()).is_err());
+    }
+
+    fn gen_proof_with_leaf<H: Hasher>() {
+        let leafs = 16;
+        let rng = &mut seeded_rng();
+        let (data, tree) = random_tree::<H>(leafs, rng);
+
+        for challenge in 0..leafs {
+            let (leaf, proof) = tree.gen_proof_with_leaf(challenge).unwrap();
+            let expected = H::Domain::try_from_bytes(data_at_node(&data, challenge).unwrap());
+            assert_eq!(leaf, expected.unwrap());
+
+            let proof = MerkleProof::<H>::new_from_proof(&proof);
+            assert_eq!(proof.leaf(), &leaf);
+            assert!(proof.validate(challenge));
+        }
+
+        assert!(tree.gen_proof_with_leaf(leafs).is_err());
+    }
+
+    fn gen_proofs<H: Hasher>() {
+        let leafs = 64;
+        let rng = &mut seeded_rng();
+        let (_, tree) = random_tree::<H>(leafs, rng);
+
+        // Clustered, repeated and scattered challenges.
+        let challenges = [0, 1, 2, 3, 17, 17, 18, 40, 63];
+        let proofs = tree.gen_proofs(&challenges).unwrap();
+        assert_eq!(proofs.len(), challenges.len());
+
+        for (&challenge, batched) in challenges.iter().zip(proofs.iter()) {
+            let single = tree.gen_proof(challenge).unwrap();
+            assert_eq!(batched.lemma(), single.lemma());
+            assert_eq!(batched.path(), single.path());
+            assert!(batched.validate::<H::Function>());
+        }
+
+        assert!(tree.gen_proofs(&[]).unwrap().is_empty());
+        assert!(tree.gen_proofs(&[0, leafs]).is_err());
+    }
+
+    fn tampered_proof<H: Hasher>() {
+        let leafs = 16;
+        let rng = &mut seeded_rng();
+        let (_, tree) = random_tree::<H>(leafs, rng);
+
+        let challenge = 5;
+        let proof = MerkleProof::<H>::new_from_proof(&tree.gen_proof(challenge).unwrap());
+        assert!(proof.validate(challenge));
+
+        for index in 0..proof.path().len() {
+            let sibling = proof.path()[index].0;
+            let mut other = H::Domain::random(rng);
+            while other == sibling {
+                other = H::Domain::random(rng);
+            }
+
+            let tampered = proof.clone().with_tampered_sibling(index, other);
+            assert!(
+                !tampered.validate(challenge),
+                "tampered sibling {} still validates",
+                index
+            );
+            // The direction bits are untouched.
+            assert!(tampered.proves_challenge(challenge));
+        }
+
+        let mut root = H::Domain::random(rng);
+        while root == *proof.root() {
+            root = H::Domain::random(rng);
+        }
+        assert!(!proof.clone().with_tampered_root(root).validate(challenge));
+
+        // Tampering with the original values is a no-op.
+        let root = *proof.root();
+        let sibling = proof.path()[1].0;
+        let same = proof
+            .clone()
+            .with_tampered_sibling(1, sibling)
+            .with_tampered_root(root);
+        assert_eq!(same, proof);
+        assert!(same.validate(challenge));
+    }
+
+    #[test]
+    fn tampered_proof_pedersen() {
+        tampered_proof::<PedersenHasher>();
+    }
+
+    #[test]
+    fn tampered_proof_sha256() {
+        tampered_proof::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn tampered_proof_blake2s() {
+        tampered_proof::<Blake2sHasher>();
+    }
+
+    fn compressed_proof_set<H: Hasher>() {
+        let leafs = 256;
+        let rng = &mut seeded_rng();
+        let (_, tree) = random_tree::<H>(leafs, rng);
+
+        // Two clusters and a repeated challenge.
+        let challenges = [0, 1, 2, 3, 5, 8, 8, 200, 201, 204];
+        let proofs: Vec<MerkleProof<H>> = challenges
+            .iter()
+            .map(|&c| MerkleProof::new_from_proof(&tree.gen_proof(c).unwrap()))
+            .collect();
+
+        let compressed = CompressedProofSet::compress(&proofs).unwrap();
+        assert_eq!(compressed.len(), proofs.len());
+
+        let decompressed = compressed.decompress();
+        assert_eq!(decompressed, proofs);
+        for (proof, &challenge) in decompressed.iter().zip(challenges.iter()) {
+            assert!(proof.validate(challenge));
+        }
+
+        assert!(
+            compressed.stored_nodes() < compressed.uncompressed_nodes() / 2,
+            "compressed {} nodes into {}",
+            compressed.uncompressed_nodes(),
+            compressed.stored_nodes()
+        );
+
+        // Proofs from another tree can not be mixed in.
+        let (_, other) = random_tree::<H>(leafs, rng);
+        let mut mixed = proofs;
+        mixed.push(MerkleProof::new_from_proof(&other.gen_proof(0).unwrap()));
+        assert!(CompressedProofSet::compress(&mixed).is_err());
+        assert!(CompressedProofSet::<H>::compress(&[]).is_err());
+    }
+
+    #[test]
+    fn compressed_proof_set_pedersen() {
+        compressed_proof_set::<PedersenHasher>();
+    }
+
+    #[test]
+    fn compressed_proof_set_sha256() {
+        compressed_proof_set::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn compressed_proof_set_blake2s() {
+        compressed_proof_set::<Blake2sHasher>();
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    /// The disk store only reads through `&self`, so the tree can be shared between threads
+    /// without any extra locking.
+    fn concurrent_gen_proof<H: Hasher + 'static>() {
+        assert_send_sync::<MerkleTree<H::Domain, H::Function>>();
+
+        let leafs = 64;
+        let rng = &mut seeded_rng();
+        let (_, tree) = random_tree::<H>(leafs, rng);
+        let tree = std::sync::Arc::new(tree);
+
+        let handles: Vec<_> = (0..8)
+            .map(|t| {
+                let tree = tree.clone();
+                std::thread::spawn(move || {
+                    (t..leafs)
+                        .step_by(8)
+                        .map(|c| {
+                            (
+                                c,
+                                MerkleProof::<H>::new_from_proof(&tree.gen_proof(c).unwrap()),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut proven = 0;
+        for handle in handles {
+            for (challenge, proof) in handle.join().unwrap() {
+                assert!(proof.validate(challenge));
+                assert_eq!(proof.root(), &tree.root());
+                proven += 1;
+            }
+        }
+        assert_eq!(proven, leafs);
+    }
+
+    #[test]
+    fn concurrent_gen_proof_pedersen() {
+        concurrent_gen_proof::<PedersenHasher>();
+    }
+
+    #[test]
+    fn concurrent_gen_proof_sha256() {
+        concurrent_gen_proof::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn concurrent_gen_proof_blake2s() {
+        concurrent_gen_proof::<Blake2sHasher>();
+    }
+
+    fn root_fr<H: Hasher>() {
+        let rng = &mut seeded_rng();
+
+        for leafs in &[2, 8, 32] {
+            let (_, tree) = random_tree::<H>(*leafs, rng);
+
+            let expected: Fr = tree.root().into();
+            assert_eq!(tree.root_fr(), expected);
+        }
+    }
+
+    #[test]
+    fn gen_proofs_pedersen() {
+        gen_proofs::<PedersenHasher>();
+    }
+
+    #[test]
+    fn gen_proofs_sha256() {
+        gen_proofs::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn gen_proofs_blake2s() {
+        gen_proofs::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn root_fr_pedersen() {
+        root_fr::<PedersenHasher>();
+    }
+
+    #[test]
+    fn root_fr_sha256() {
+        root_fr::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn root_fr_blake2s() {
+        root_fr::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn gen_proof_checked_pedersen() {
+        gen_proof_checked::<PedersenHasher>();
+    }
+
+    #[test]
+    fn gen_proof_checked_sha256() {
+        gen_proof_checked::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn gen_proof_checked_blake2s() {
+        gen_proof_checked::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn gen_proof_with_leaf_pedersen() {
+        gen_proof_with_leaf::<PedersenHasher>();
+    }
+
+    #[test]
+    fn gen_proof_with_leaf_sha256() {
+        gen_proof_with_leaf::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn gen_proof_with_leaf_blake2s() {
+        gen_proof_with_leaf::<Blake2sHasher>();
+    }
+
+    fn inclusion<H: Hasher>() {
+        let leafs = 16;
+        let rng = &mut seeded_rng();
+        let (_, tree) = random_tree::<H>(leafs, rng);
+
+        for i in 0..leafs {
+            let proof = MerkleProof::<H>::new_from_proof(&tree.gen_proof(i).unwrap());
+            let mut path = proof.path().clone();
+
+            assert!(verify_inclusion::<H>(*proof.leaf(), &path, tree.root()));
+
+            path[1].0 = H::Domain::random(rng);
+            assert!(!verify_inclusion::<H>(*proof.leaf(), &path, tree.root()));
+        }
+    }
+
+    #[test]
+    fn inclusion_pedersen() {
+        inclusion::<PedersenHasher>();
+    }
+
+    #[test]
+    fn inclusion_sha256() {
+        inclusion::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn inclusion_blake2s() {
+        inclusion::<Blake2sHasher>();
+    }
+
+    fn load_verified<H: Hasher>() {
+        let leafs = 16;
+        let rng = &mut seeded_rng();
+        let (_, tree) = random_tree::<H>(leafs, rng);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree");
+        store_merkle_tree::<H, _>(&tree, &path).unwrap();
+
+        let loaded = load_verified_merkle_tree::<H, _>(&path).unwrap();
+        assert_eq!(loaded.root(), tree.root());
+
+        // Flip a byte in an interior node.
+        let mut raw = std::fs::read(&path).unwrap();
+        let offset = TREE_HEADER_SIZE + (leafs + 1) * NODE_SIZE;
+        raw[offset] ^= 1;
+        std::fs::write(&path, &raw).unwrap();
+
+        match load_verified_merkle_tree::<H, _>(&path) {
+            Ok(_) => panic!("corrupted store must not load"),
+            Err(err) => match err.downcast::<Error>() {
+                Ok(Error::TreeCorrupted) => {}
+                other => panic!("unexpected error: {:?}", other),
+            },
+        }
+    }
+
+    fn load_with_other_hasher<H: Hasher, O: Hasher>() {
+        let leafs = 16;
+        let rng = &mut seeded_rng();
+        let (_, tree) = random_tree::<H>(leafs, rng);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree");
+        store_merkle_tree::<H, _>(&tree, &path).unwrap();
+
+        match load_verified_merkle_tree::<O, _>(&path) {
+            Ok(_) => panic!("tree must not load under a different hasher"),
+            Err(err) => match err.downcast::<Error>() {
+                Ok(Error::HasherMismatch { expected, found }) => {
+                    assert_eq!(expected, O::name());
+                    assert_eq!(found, H::name());
+                }
+                other => panic!("unexpected error: {:?}", other),
+            },
+        }
+    }
+
+    #[test]
+    fn load_with_other_hasher_fails() {
+        load_with_other_hasher::<Blake2sHasher, PedersenHasher>();
+        load_with_other_hasher::<PedersenHasher, Sha256Hasher>();
+        load_with_other_hasher::<Sha256Hasher, Blake2sHasher>();
+    }
+
+    fn combine_stores<H: Hasher>() {
+        let leafs = 8;
+        let rng = &mut seeded_rng();
+        let data = random_data(2 * leafs, rng);
+
+        let dir = tempfile::tempdir().unwrap();
+        let stores: Vec<MerkleStore<H::Domain>> = data
+            .chunks(leafs * NODE_SIZE)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let config = StoreConfig::new(
+                    dir.path(),
+                    format!("subtree-{}", i),
+                    DEFAULT_CACHED_ABOVE_BASE_LAYER,
+                );
+                let subtree = create_merkle_tree::<H>(Some(config.clone()), leafs, chunk).unwrap();
+                MerkleStore::new_from_disk(subtree.len(), &config).unwrap()
+            })
+            .collect();
+
+        let combined = combine_merkle_stores::<H>(&stores, leafs).unwrap();
+        let full = create_merkle_tree::<H>(None, 2 * leafs, &data).unwrap();
+
+        assert_eq!(combined.root(), full.root());
+        assert_eq!(combined.leafs(), full.leafs());
+        assert_eq!(combined.len(), full.len());
+        for i in &[0, 7, 8, 15] {
+            assert_eq!(
+                MerkleProof::<H>::new_from_proof(&combined.gen_proof(*i).unwrap()),
+                MerkleProof::<H>::new_from_proof(&full.gen_proof(*i).unwrap())
+            );
+        }
+
+        // Subtrees of a different size than announced are rejected.
+        assert!(combine_merkle_stores::<H>(&stores, leafs / 2).is_err());
+        assert!(combine_merkle_stores::<H>(&stores[..0], leafs).is_err());
+    }
+
+    #[test]
+    fn combine_stores_pedersen() {
+        combine_stores::<PedersenHasher>();
+    }
+
+    #[test]
+    fn combine_stores_sha256() {
+        combine_stores::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn combine_stores_blake2s() {
+        combine_stores::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn load_verified_pedersen() {
+        load_verified::<PedersenHasher>();
+    }
+
+    #[test]
+    fn load_verified_sha256() {
+        load_verified::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn load_verified_blake2s() {
+        load_verified::<Blake2sHasher>();
+    }
+
+    fn arity_tree<H: Hasher>() {
+        let leafs = 64;
+        let rng = &mut seeded_rng();
+        let data = random_data(leafs, rng);
+
+        let binary = create_merkle_tree_with_arity::<H>(2, leafs, &data).unwrap();
+        let quad = create_merkle_tree_with_arity::<H>(4, leafs, &data).unwrap();
+
+        // Arity 2 matches the regular merkle tree.
+        let tree = create_merkle_tree::<H>(None, leafs, &data).unwrap();
+        assert_eq!(binary.root(), tree.root());
+        assert_eq!(binary.depth(), tree.depth());
+
+        assert_eq!(quad.depth(), graph_height_arity(leafs, 4));
+        assert!(quad.depth() < binary.depth());
+
+        for i in 0..leafs {
+            let proof = quad.gen_proof(i).unwrap();
+            assert_eq!(proof.path().len(), quad.depth());
+            assert!(proof.path().len() < binary.gen_proof(i).unwrap().path().len());
+            assert!(proof.validate(i), "failed to validate arity 4 proof");
+            assert!(!proof.validate((i + 1) % leafs));
+
+            let mut bad = proof.clone();
+            bad.leaf = H::Domain::random(rng);
+            assert!(!bad.validate(i));
+        }
+
+        assert!(quad.gen_proof(leafs).is_err());
+        assert!(create_merkle_tree_with_arity::<H>(3, leafs, &data).is_err());
+        assert!(create_merkle_tree_with_arity::<H>(4, 32, &data[..32 * NODE_SIZE]).is_err());
+    }
+
+    #[test]
+    fn arity_tree_pedersen() {
+        arity_tree::<PedersenHasher>();
+    }
+
+    #[test]
+    fn arity_tree_sha256() {
+        arity_tree::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn arity_tree_blake2s() {
+        arity_tree::<Blake2sHasher>();
+    }
+
+    fn cancellable_tree<H: Hasher>() {
+        let leafs = 64;
+        let rng = &mut seeded_rng();
+        let data = random_data(leafs, rng);
+
+        let tree =
+            create_merkle_tree_cancellable::<H>(None, leafs, &data, &CancellationToken::new())
+                .unwrap();
+        assert_eq!(
+            tree.root(),
+            create_merkle_tree::<H>(None, leafs, &data).unwrap().root()
+        );
+    }
+
+    #[test]
+    fn cancellable_tree_pedersen() {
+        cancellable_tree::<PedersenHasher>();
+    }
+
+    #[test]
+    fn cancellable_tree_sha256() {
+        cancellable_tree::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn cancellable_tree_blake2s() {
+        cancellable_tree::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn cancel_tree_build_partway() {
+        use std::time::{Duration, Instant};
+
+        // Large enough that converting the leaves alone takes far longer than it takes to cancel.
+        let leafs = 1 << 20;
+        let data = vec![0u8; leafs * NODE_SIZE];
+        let cancel = CancellationToken::new();
+
+        let builder = {
+            let cancel = cancel.clone();
+            std::thread::spawn(move || {
+                let start = Instant::now();
+                let res =
+                    create_merkle_tree_cancellable::<PedersenHasher>(None, leafs, &data, &cancel);
+                (res.map(|_| ()), start.elapsed())
+            })
+        };
+        cancel.cancel();
+
+        let (res, elapsed) = builder.join().unwrap();
+        match res.map_err(|err| err.downcast::<Error>()) {
+            Err(Ok(Error::Cancelled)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(
+            elapsed < Duration::from_secs(1),
+            "cancellation took {:?}",
+            elapsed
+        );
+    }
 }
@@ -1,16 +1,22 @@
+use std::convert::TryFrom;
 use std::marker::PhantomData;
+use std::time::Duration;
 
 use anyhow::ensure;
-use serde::{Deserialize, Serialize};
+use merkletree::hash::Algorithm;
+use paired::bls12_381::{Bls12, Fr};
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::drgraph::graph_height;
 use crate::error::*;
-use crate::hasher::{Domain, Hasher};
+use crate::fr32::bytes_into_fr;
+use crate::hasher::{hash_bytes_into_domain, Domain, Hasher, Sha256Hasher};
 use crate::merkle::{MerkleProof, MerkleTree};
 use crate::parameter_cache::ParameterSetMetadata;
-use crate::proof::{NoRequirements, ProofScheme};
+use crate::proof::{NoRequirements, ProofScheme, ProveCostEstimate};
+use crate::util::NODE_SIZE;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DataProof<H: Hasher> {
     #[serde(bound(
         serialize = "MerkleProof<H>: Serialize",
@@ -26,13 +32,39 @@ pub struct PublicParams {
     /// How many leaves the underlying merkle tree has.
     pub leaves: usize,
     pub private: bool,
+    /// Whether the circuit binds proofs to the `context` of their public inputs.
+    pub bind_context: bool,
+}
+
+impl PublicParams {
+    /// Public params for a tree of `leaves` leaves, exposing its root.
+    pub fn new(leaves: usize) -> Self {
+        PublicParams {
+            leaves,
+            private: false,
+            bind_context: false,
+        }
+    }
+}
+
+impl Default for PublicParams {
+    fn default() -> Self {
+        PublicParams::new(16)
+    }
 }
 
 impl ParameterSetMetadata for PublicParams {
     fn identifier(&self) -> String {
+        // Only mention `bind_context` if set, so existing parameters keep their identifier.
+        let context = if self.bind_context {
+            "; bind_context: true"
+        } else {
+            ""
+        };
+
         format!(
-            "merklepor::PublicParams{{leaves: {}; private: {}}}",
-            self.leaves, self.private
+            "merklepor::PublicParams{{leaves: {}; private: {}{}}}",
+            self.leaves, self.private, context
         )
     }
 
@@ -48,6 +80,82 @@ pub struct PublicInputs<T: Domain> {
     pub commitment: Option<T>,
     /// The challenge, which leaf to prove.
     pub challenge: usize,
+    /// Arbitrary bytes, e.g. a nonce, the circuit binds the proof to if the public params ask for
+    /// it, so that it can not be replayed in another context. Empty by default.
+    pub context: Vec<u8>,
+}
+
+impl<T: Domain> PublicInputs<T> {
+    /// One set of public inputs for every leaf of `tree`, in order, all committing to its root.
+    pub fn for_all_leaves<A: Algorithm<T>>(tree: &MerkleTree<T, A>) -> Vec<Self> {
+        let root = tree.root();
+
+        (0..tree.leafs())
+            .map(|challenge| PublicInputs {
+                commitment: Some(root),
+                challenge,
+                context: Vec::new(),
+            })
+            .collect()
+    }
+}
+
+/// Hashes `context` into the field element exposed by the circuit.
+pub fn context_into_fr(context: &[u8]) -> Fr {
+    hash_bytes_into_domain::<Sha256Hasher>(context).into()
+}
+
+/// The serialized form of `PublicInputs`, independent of the platform and the domain.
+#[derive(Serialize, Deserialize)]
+struct PublicInputsRepr {
+    commitment: Option<[u8; NODE_SIZE]>,
+    challenge: u64,
+    #[serde(default)]
+    context: Vec<u8>,
+}
+
+impl<T: Domain> Serialize for PublicInputs<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let commitment = match self.commitment {
+            Some(commitment) => {
+                let mut bytes = [0u8; NODE_SIZE];
+                commitment
+                    .write_bytes(&mut bytes)
+                    .map_err(ser::Error::custom)?;
+                Some(bytes)
+            }
+            None => None,
+        };
+
+        PublicInputsRepr {
+            commitment,
+            challenge: self.challenge as u64,
+            context: self.context.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: Domain> Deserialize<'de> for PublicInputs<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let repr = PublicInputsRepr::deserialize(deserializer)?;
+
+        let challenge = usize::try_from(repr.challenge).map_err(de::Error::custom)?;
+        let commitment = match repr.commitment {
+            Some(bytes) => {
+                // Commitments are public inputs of the circuits, so they must be field elements.
+                bytes_into_fr::<Bls12>(&bytes).map_err(de::Error::custom)?;
+                Some(T::try_from_bytes(&bytes).map_err(de::Error::custom)?)
+            }
+            None => None,
+        };
+
+        Ok(PublicInputs {
+            commitment,
+            challenge,
+            context: repr.context,
+        })
+    }
 }
 
 /// The inputs that are only available to the prover.
@@ -74,6 +182,16 @@ impl<'a, H: Hasher> PrivateInputs<'a, H> {
 pub struct SetupParams {
     pub leaves: usize,
     pub private: bool,
+    pub bind_context: bool,
+}
+
+fn ensure_challenge_in_range(challenge: usize, leaves: usize) -> Result<()> {
+    ensure!(
+        challenge < leaves,
+        Error::ChallengeOutOfRange { challenge, leaves }
+    );
+
+    Ok(())
 }
 
 /// Merkle tree based proof of retrievability.
@@ -94,6 +212,7 @@ impl<'a, H: 'a + Hasher> ProofScheme<'a> for MerklePoR<H> {
         Ok(PublicParams {
             leaves: sp.leaves,
             private: sp.private,
+            bind_context: sp.bind_context,
         })
     }
 
@@ -102,7 +221,9 @@ impl<'a, H: 'a + Hasher> ProofScheme<'a> for MerklePoR<H> {
         pub_inputs: &'b Self::PublicInputs,
         priv_inputs: &'b Self::PrivateInputs,
     ) -> Result<Self::Proof> {
-        let challenge = pub_inputs.challenge % pub_params.leaves;
+        enter_span!("merklepor_prove", nodes = pub_params.leaves);
+        let challenge = pub_inputs.challenge;
+        ensure_challenge_in_range(challenge, pub_params.leaves)?;
         let tree = priv_inputs.tree;
 
         if let Some(ref commitment) = pub_inputs.commitment {
@@ -120,6 +241,26 @@ impl<'a, H: 'a + Hasher> ProofScheme<'a> for MerklePoR<H> {
         pub_inputs: &Self::PublicInputs,
         proof: &Self::Proof,
     ) -> Result<bool> {
+        ensure_challenge_in_range(pub_inputs.challenge, pub_params.leaves)?;
+
+        // A path of the wrong length can never prove inclusion in a tree of `leaves` leaves, so
+        // reject it explicitly instead of climbing it to some other root.
+        let expected = graph_height(pub_params.leaves);
+        let got = proof.proof.path().len();
+        ensure!(
+            expected == got,
+            Error::ProofPathLengthMismatch { expected, got }
+        );
+
+        // The domains are lenient when parsed from bytes, make sure the commitment is a field
+        // element instead of comparing against some silently masked value.
+        if let Some(ref commitment) = pub_inputs.commitment {
+            ensure!(
+                bytes_into_fr::<Bls12>(&commitment.into_bytes()).is_ok(),
+                Error::NonCanonicalCommitment
+            );
+        }
+
         {
             // This was verify_proof_meta.
             let commitments_match = match pub_inputs.commitment {
@@ -127,9 +268,7 @@ impl<'a, H: 'a + Hasher> ProofScheme<'a> for MerklePoR<H> {
                 None => true,
             };
 
-            let path_length_match = graph_height(pub_params.leaves) == proof.proof.path().len();
-
-            if !(commitments_match && path_length_match) {
+            if !commitments_match {
                 return Ok(false);
             }
         }
@@ -138,6 +277,35 @@ impl<'a, H: 'a + Hasher> ProofScheme<'a> for MerklePoR<H> {
 
         Ok(data_valid && path_valid)
     }
+
+    fn estimate_prove_cost(
+        pub_params: &Self::PublicParams,
+        cost_per_constraint: Duration,
+    ) -> Result<ProveCostEstimate> {
+        let constraints = crate::circuit::por::ppor_constraint_count::<H>(pub_params)?;
+
+        Ok(ProveCostEstimate::from_constraints(
+            constraints,
+            cost_per_constraint,
+        ))
+    }
+}
+
+/// Verifies `proof` for `challenge` against a commitment given as hex, e.g. copied from a log.
+/// Malformed hex is reported as `Error::InvalidHex`.
+pub fn verify_with_hex_commitment<H: Hasher>(
+    pub_params: &PublicParams,
+    challenge: usize,
+    comm_hex: &str,
+    proof: &DataProof<H>,
+) -> Result<bool> {
+    let pub_inputs = PublicInputs {
+        commitment: Some(H::Domain::from_hex(comm_hex)?),
+        challenge,
+        context: Vec::new(),
+    };
+
+    MerklePoR::<H>::verify(pub_params, &pub_inputs, proof)
 }
 
 #[cfg(test)]
@@ -146,23 +314,75 @@ mod tests {
 
     use ff::Field;
     use paired::bls12_381::{Bls12, Fr};
-    use rand::SeedableRng;
+    use rand::{Rng, SeedableRng};
     use rand_xorshift::XorShiftRng;
 
     use crate::drgraph::{new_seed, BucketGraph, Graph, BASE_DEGREE};
     use crate::fr32::fr_into_bytes;
     use crate::hasher::{Blake2sHasher, HashFunction, PedersenHasher, Sha256Hasher};
     use crate::merkle::make_proof_for_test;
+    use crate::test_helper::seeded_rng;
     use crate::util::data_at_node;
 
-    fn test_merklepor<H: Hasher>() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+    #[test]
+    fn test_public_params_identifier() {
+        let a = PublicParams::new(32);
+        let b = PublicParams::new(64);
 
-        let pub_params = PublicParams {
-            leaves: 32,
-            private: false,
+        assert_ne!(a.identifier(), b.identifier());
+        assert_eq!(a.identifier(), a.clone().identifier());
+    }
+
+    #[test]
+    fn test_public_params_new() {
+        let pub_params = PublicParams::new(16);
+        assert_eq!(pub_params.leaves, 16);
+        assert!(!pub_params.private);
+
+        let default = PublicParams::default();
+        assert!(default.leaves.is_power_of_two());
+        assert!(!default.private);
+    }
+
+    /// A tree over `leaves` random nodes drawn from `rng`, public params for it, and public inputs
+    /// challenging node 3 against its root.
+    fn setup<H: Hasher>(
+        leaves: usize,
+        rng: &mut XorShiftRng,
+    ) -> (
+        PublicParams,
+        Vec<u8>,
+        MerkleTree<H::Domain, H::Function>,
+        PublicInputs<H::Domain>,
+    ) {
+        let pub_params = PublicParams::new(leaves);
+
+        let data: Vec<u8> = (0..leaves)
+            .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
+            .collect();
+
+        let graph = BucketGraph::<H>::new(leaves, BASE_DEGREE, 0, new_seed()).unwrap();
+        let tree = graph.merkle_tree(data.as_slice()).unwrap();
+
+        let pub_inputs = PublicInputs {
+            challenge: 3,
+            commitment: Some(tree.root()),
+            context: Vec::new(),
         };
 
+        (pub_params, data, tree, pub_inputs)
+    }
+
+    /// The leaf at `node` of `data`.
+    fn leaf_at<H: Hasher>(data: &[u8], node: usize) -> H::Domain {
+        H::Domain::try_from_bytes(data_at_node(data, node).unwrap()).unwrap()
+    }
+
+    fn test_merklepor<H: Hasher>() {
+        let rng = &mut seeded_rng();
+
+        let pub_params = PublicParams::new(32);
+
         let data: Vec<u8> = (0..32)
             .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
             .collect();
@@ -173,6 +393,7 @@ mod tests {
         let pub_inputs = PublicInputs {
             challenge: 3,
             commitment: Some(tree.root()),
+            context: Vec::new(),
         };
 
         let leaf =
@@ -205,13 +426,213 @@ mod tests {
         test_merklepor::<Blake2sHasher>();
     }
 
+    fn test_merklepor_non_canonical_commitment<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let (pub_params, data, tree, mut pub_inputs) = setup::<H>(32, rng);
+        let leaf = leaf_at::<H>(&data, 3);
+        let priv_inputs = PrivateInputs::<H>::new(leaf, &tree);
+        let proof =
+            MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs).expect("proving failed");
+
+        // All bits set, far outside of the field.
+        pub_inputs.commitment = Some(H::Domain::try_from_bytes(&[0xff; 32]).unwrap());
+
+        let err = MerklePoR::<H>::verify(&pub_params, &pub_inputs, &proof).unwrap_err();
+        match err.downcast::<Error>() {
+            Ok(Error::NonCanonicalCommitment) => {}
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn merklepor_non_canonical_commitment_pedersen() {
+        test_merklepor_non_canonical_commitment::<PedersenHasher>();
+    }
+
+    #[test]
+    fn merklepor_non_canonical_commitment_sha256() {
+        test_merklepor_non_canonical_commitment::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn merklepor_non_canonical_commitment_blake2s() {
+        test_merklepor_non_canonical_commitment::<Blake2sHasher>();
+    }
+
+    fn test_merklepor_hex_commitment<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let (pub_params, data, tree, pub_inputs) = setup::<H>(32, rng);
+        let leaf = leaf_at::<H>(&data, 3);
+        let priv_inputs = PrivateInputs::<H>::new(leaf, &tree);
+        let proof =
+            MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs).expect("proving failed");
+
+        let comm_hex = tree.root().to_hex();
+        assert!(verify_with_hex_commitment(&pub_params, 3, &comm_hex, &proof).unwrap());
+        assert!(!verify_with_hex_commitment(&pub_params, 4, &comm_hex, &proof).unwrap());
+
+        let other_hex = H::Domain::random(rng).to_hex();
+        assert!(!verify_with_hex_commitment(&pub_params, 3, &other_hex, &proof).unwrap());
+
+        for malformed in &["", "0x1234", &comm_hex[..62]] {
+            let err = verify_with_hex_commitment(&pub_params, 3, malformed, &proof).unwrap_err();
+            match err.downcast::<Error>() {
+                Ok(Error::InvalidHex(_)) => {}
+                other => panic!("unexpected error for {:?}: {:?}", malformed, other),
+            }
+        }
+    }
+
+    #[test]
+    fn merklepor_hex_commitment_pedersen() {
+        test_merklepor_hex_commitment::<PedersenHasher>();
+    }
+
+    #[test]
+    fn merklepor_hex_commitment_sha256() {
+        test_merklepor_hex_commitment::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn merklepor_hex_commitment_blake2s() {
+        test_merklepor_hex_commitment::<Blake2sHasher>();
+    }
+
+    fn test_for_all_leaves<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let leaves = 16;
+        let (_, _, tree, _) = setup::<H>(leaves, rng);
+
+        let inputs = PublicInputs::for_all_leaves(&tree);
+        assert_eq!(inputs.len(), leaves);
+        for (i, input) in inputs.iter().enumerate() {
+            assert_eq!(input.challenge, i);
+            assert_eq!(input.commitment, Some(tree.root()));
+            assert!(input.context.is_empty());
+        }
+    }
+
+    #[test]
+    fn for_all_leaves_pedersen() {
+        test_for_all_leaves::<PedersenHasher>();
+    }
+
+    #[test]
+    fn for_all_leaves_sha256() {
+        test_for_all_leaves::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn for_all_leaves_blake2s() {
+        test_for_all_leaves::<Blake2sHasher>();
+    }
+
+    fn test_merklepor_deterministic<H: Hasher>() {
+        let prove = |challenge: usize| {
+            // Every prover builds its own tree, over the same data.
+            let rng = &mut seeded_rng();
+            let (pub_params, data, tree, mut pub_inputs) = setup::<H>(32, rng);
+            pub_inputs.challenge = challenge;
+
+            let leaf = leaf_at::<H>(&data, challenge);
+            let priv_inputs = PrivateInputs::<H>::new(leaf, &tree);
+
+            MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs).expect("proving failed")
+        };
+
+        let proof = prove(3);
+        assert_eq!(proof, prove(3));
+        assert_eq!(proof, proof.clone());
+        assert_ne!(proof, prove(4));
+    }
+
+    #[test]
+    fn merklepor_deterministic_pedersen() {
+        test_merklepor_deterministic::<PedersenHasher>();
+    }
+
+    #[test]
+    fn merklepor_deterministic_sha256() {
+        test_merklepor_deterministic::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn merklepor_deterministic_blake2s() {
+        test_merklepor_deterministic::<Blake2sHasher>();
+    }
+
+    fn public_inputs_serde<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let (pub_params, data, tree, mut pub_inputs) = setup::<H>(32, rng);
+        pub_inputs.challenge = 7;
+        pub_inputs.context = b"nonce".to_vec();
+        let decoded: PublicInputs<H::Domain> =
+            serde_cbor::from_slice(&serde_cbor::to_vec(&pub_inputs).unwrap()).unwrap();
+        assert_eq!(decoded.challenge, pub_inputs.challenge);
+        assert_eq!(decoded.commitment, pub_inputs.commitment);
+        assert_eq!(decoded.context, pub_inputs.context);
+
+        // The decoded inputs verify exactly the same proofs.
+        let leaf = leaf_at::<H>(&data, 7);
+        let priv_inputs = PrivateInputs::<H>::new(leaf, &tree);
+        let proof =
+            MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs).expect("proving failed");
+        assert!(MerklePoR::<H>::verify(&pub_params, &decoded, &proof).unwrap());
+
+        let other = PublicInputs {
+            challenge: 8,
+            ..decoded.clone()
+        };
+        let other: PublicInputs<H::Domain> =
+            serde_cbor::from_slice(&serde_cbor::to_vec(&other).unwrap()).unwrap();
+        assert!(!MerklePoR::<H>::verify(&pub_params, &other, &proof).unwrap());
+
+        let private = PublicInputs::<H::Domain> {
+            challenge: 3,
+            commitment: None,
+            context: Vec::new(),
+        };
+        let decoded: PublicInputs<H::Domain> =
+            serde_cbor::from_slice(&serde_cbor::to_vec(&private).unwrap()).unwrap();
+        assert_eq!(decoded.challenge, 3);
+        assert!(decoded.commitment.is_none());
+
+        // Commitments which are not field elements are rejected.
+        let invalid = PublicInputsRepr {
+            commitment: Some([0xff; NODE_SIZE]),
+            challenge: 3,
+            context: Vec::new(),
+        };
+        assert!(serde_cbor::from_slice::<PublicInputs<H::Domain>>(
+            &serde_cbor::to_vec(&invalid).unwrap()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn public_inputs_serde_pedersen() {
+        public_inputs_serde::<PedersenHasher>();
+    }
+
+    #[test]
+    fn public_inputs_serde_sha256() {
+        public_inputs_serde::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn public_inputs_serde_blake2s() {
+        public_inputs_serde::<Blake2sHasher>();
+    }
+
     // Construct a proof that satisfies a cursory validation:
     // Data and proof are minimally consistent.
     // Proof root matches that requested in public inputs.
     // However, note that data has no relationship to anything,
     // and proof path does not actually prove that data was in the tree corresponding to expected root.
+    // The path has the length of a tree of `leaves` leaves.
     fn make_bogus_proof<H: Hasher>(
         pub_inputs: &PublicInputs<H::Domain>,
+        leaves: usize,
         rng: &mut XorShiftRng,
     ) -> DataProof<H> {
         let bogus_leaf: H::Domain = H::Domain::random(rng);
@@ -222,18 +643,15 @@ mod tests {
             proof: make_proof_for_test(
                 pub_inputs.commitment.unwrap(),
                 hashed_leaf,
-                vec![(hashed_leaf, true)],
+                vec![(hashed_leaf, true); graph_height(leaves)],
             ),
         }
     }
 
     fn test_merklepor_validates<H: Hasher>() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
-        let pub_params = PublicParams {
-            leaves: 32,
-            private: false,
-        };
+        let pub_params = PublicParams::new(32);
 
         let data: Vec<u8> = (0..32)
             .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
@@ -245,9 +663,10 @@ mod tests {
         let pub_inputs = PublicInputs {
             challenge: 3,
             commitment: Some(tree.root()),
+            context: Vec::new(),
         };
 
-        let bad_proof = make_bogus_proof::<H>(&pub_inputs, rng);
+        let bad_proof = make_bogus_proof::<H>(&pub_inputs, pub_params.leaves, rng);
 
         let verified =
             MerklePoR::verify(&pub_params, &pub_inputs, &bad_proof).expect("verification failed");
@@ -271,13 +690,80 @@ mod tests {
         test_merklepor_validates::<PedersenHasher>();
     }
 
+    /// The ways a valid proof is tampered with in `test_merklepor_soundness`.
+    const MUTATIONS: &[&str] = &["leaf", "sibling", "root", "is_right"];
+
+    fn mutate<H: Hasher>(
+        proof: &DataProof<H>,
+        mutation: &str,
+        rng: &mut XorShiftRng,
+    ) -> DataProof<H> {
+        let mut data = proof.data;
+        let mut root = *proof.proof.root();
+        let mut path = proof.proof.path().clone();
+        let level = rng.gen_range(0, path.len());
+
+        match mutation {
+            "leaf" => data = H::Domain::random(rng),
+            "sibling" => path[level].0 = H::Domain::random(rng),
+            "root" => root = H::Domain::random(rng),
+            "is_right" => path[level].1 = !path[level].1,
+            _ => unreachable!(),
+        }
+
+        DataProof {
+            data,
+            proof: make_proof_for_test(root, *proof.proof.leaf(), path),
+        }
+    }
+
+    fn test_merklepor_soundness<H: Hasher>() {
+        let leaves = 32;
+
+        for seed in 0..16 {
+            let rng = &mut XorShiftRng::seed_from_u64(seed);
+            let (pub_params, data, tree, mut pub_inputs) = setup::<H>(leaves, rng);
+            pub_inputs.challenge = rng.gen_range(0, leaves);
+
+            let leaf = leaf_at::<H>(&data, pub_inputs.challenge);
+            let priv_inputs = PrivateInputs::<H>::new(leaf, &tree);
+
+            let proof = MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs)
+                .expect("proving failed");
+            assert!(MerklePoR::<H>::verify(&pub_params, &pub_inputs, &proof).unwrap());
+
+            for mutation in MUTATIONS {
+                let bad_proof = mutate(&proof, mutation, rng);
+                assert!(
+                    !MerklePoR::<H>::verify(&pub_params, &pub_inputs, &bad_proof).unwrap(),
+                    "proof with mutated {} verified (seed {}, challenge {})",
+                    mutation,
+                    seed,
+                    pub_inputs.challenge
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn merklepor_soundness_sha256() {
+        test_merklepor_soundness::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn merklepor_soundness_blake2s() {
+        test_merklepor_soundness::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn merklepor_soundness_pedersen() {
+        test_merklepor_soundness::<PedersenHasher>();
+    }
+
     fn test_merklepor_validates_challenge_identity<H: Hasher>() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
-        let pub_params = PublicParams {
-            leaves: 32,
-            private: false,
-        };
+        let pub_params = PublicParams::new(32);
 
         let data: Vec<u8> = (0..32)
             .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
@@ -289,6 +775,7 @@ mod tests {
         let pub_inputs = PublicInputs {
             challenge: 3,
             commitment: Some(tree.root()),
+            context: Vec::new(),
         };
 
         let leaf =
@@ -301,8 +788,9 @@ mod tests {
             MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs).expect("proving failed");
 
         let different_pub_inputs = PublicInputs {
-            challenge: 999,
+            challenge: 4,
             commitment: Some(tree.root()),
+            context: Vec::new(),
         };
 
         let verified = MerklePoR::<H>::verify(&pub_params, &different_pub_inputs, &proof)
@@ -326,4 +814,152 @@ mod tests {
     fn merklepor_actually_validates_challenge_identity_pedersen() {
         test_merklepor_validates_challenge_identity::<PedersenHasher>();
     }
+
+    fn test_merklepor_challenge_out_of_range<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let leaves = 32;
+        let (pub_params, data, tree, mut pub_inputs) = setup::<H>(leaves, rng);
+        pub_inputs.challenge = leaves;
+
+        let leaf = leaf_at::<H>(&data, 0);
+        let priv_inputs = PrivateInputs::<H>::new(leaf, &tree);
+
+        let assert_out_of_range = |err: anyhow::Error| match err.downcast::<Error>() {
+            Ok(Error::ChallengeOutOfRange {
+                challenge,
+                leaves: l,
+            }) => {
+                assert_eq!(challenge, leaves);
+                assert_eq!(l, leaves);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        };
+
+        match MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs) {
+            Ok(_) => panic!("proving must fail for an out of range challenge"),
+            Err(err) => assert_out_of_range(err),
+        }
+
+        let bogus_proof = make_bogus_proof::<H>(&pub_inputs, leaves, rng);
+        match MerklePoR::<H>::verify(&pub_params, &pub_inputs, &bogus_proof) {
+            Ok(_) => panic!("verifying must fail for an out of range challenge"),
+            Err(err) => assert_out_of_range(err),
+        }
+    }
+
+    #[test]
+    fn merklepor_challenge_out_of_range_sha256() {
+        test_merklepor_challenge_out_of_range::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn merklepor_challenge_out_of_range_blake2s() {
+        test_merklepor_challenge_out_of_range::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn merklepor_challenge_out_of_range_pedersen() {
+        test_merklepor_challenge_out_of_range::<PedersenHasher>();
+    }
+
+    fn test_merklepor_path_length_mismatch<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let (pub_params, data, tree, pub_inputs) = setup::<H>(32, rng);
+
+        let leaf = leaf_at::<H>(&data, pub_inputs.challenge);
+        let priv_inputs = PrivateInputs::<H>::new(leaf, &tree);
+
+        let proof =
+            MerklePoR::<H>::prove(&pub_params, &pub_inputs, &priv_inputs).expect("proving failed");
+        let path = proof.proof.path().clone();
+        let expected = path.len();
+
+        let mut truncated = path.clone();
+        truncated.pop();
+        let mut extended = path.clone();
+        extended.push((H::Domain::random(rng), true));
+
+        for bad_path in vec![truncated, extended] {
+            let got = bad_path.len();
+            let bad_proof = DataProof {
+                data: proof.data,
+                proof: make_proof_for_test(*proof.proof.root(), *proof.proof.leaf(), bad_path),
+            };
+
+            match MerklePoR::<H>::verify(&pub_params, &pub_inputs, &bad_proof) {
+                Ok(_) => panic!("a path of {} elements must be rejected", got),
+                Err(err) => match err.downcast::<Error>() {
+                    Ok(Error::ProofPathLengthMismatch {
+                        expected: e,
+                        got: g,
+                    }) => {
+                        assert_eq!(e, expected);
+                        assert_eq!(g, got);
+                    }
+                    other => panic!("unexpected error: {:?}", other),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn merklepor_path_length_mismatch_sha256() {
+        test_merklepor_path_length_mismatch::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn merklepor_path_length_mismatch_blake2s() {
+        test_merklepor_path_length_mismatch::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn merklepor_path_length_mismatch_pedersen() {
+        test_merklepor_path_length_mismatch::<PedersenHasher>();
+    }
+
+    fn test_merklepor_verify_partitions_checked<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let (pub_params, data, tree, pub_inputs) = setup::<H>(32, rng);
+
+        let leaf = leaf_at::<H>(&data, pub_inputs.challenge);
+        let priv_inputs = PrivateInputs::<H>::new(leaf, &tree);
+
+        let mut proofs =
+            MerklePoR::<H>::prove_all_partitions(&pub_params, &pub_inputs, &priv_inputs, 2)
+                .expect("proving failed");
+        MerklePoR::<H>::verify_all_partitions_checked(&pub_params, &pub_inputs, &proofs)
+            .expect("verification failed");
+        assert!(
+            MerklePoR::<H>::verify_all_partitions_checked(&pub_params, &pub_inputs, &[]).is_err()
+        );
+
+        // Corrupt the middle partition, the last one must not be reached.
+        let valid = proofs[1].clone();
+        proofs[1] = make_bogus_proof::<H>(&pub_inputs, pub_params.leaves, rng);
+        proofs.push(valid);
+
+        assert!(!MerklePoR::<H>::verify_all_partitions(&pub_params, &pub_inputs, &proofs).unwrap());
+        match MerklePoR::<H>::verify_all_partitions_checked(&pub_params, &pub_inputs, &proofs) {
+            Ok(_) => panic!("a corrupted partition must be rejected"),
+            Err(err) => match err.downcast::<Error>() {
+                Ok(Error::InvalidPartitionProof(k)) => assert_eq!(k, 1),
+                other => panic!("unexpected error: {:?}", other),
+            },
+        }
+    }
+
+    #[test]
+    fn merklepor_verify_partitions_checked_sha256() {
+        test_merklepor_verify_partitions_checked::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn merklepor_verify_partitions_checked_blake2s() {
+        test_merklepor_verify_partitions_checked::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn merklepor_verify_partitions_checked_pedersen() {
+        test_merklepor_verify_partitions_checked::<PedersenHasher>();
+    }
 }
@@ -1,7 +1,15 @@
-use crate::error::Result;
-use crate::hasher::{Domain, HashFunction, Hasher};
+use std::io::Write;
+
+use anyhow::ensure;
+use rayon::prelude::*;
+
+use crate::drgporep;
+use crate::drgraph::Graph;
+use crate::error::{Error, Result};
+use crate::hasher::{hash_bytes_into_domain, Domain, HashFunction, Hasher};
 use crate::merkle::MerkleTree;
 use crate::proof::ProofScheme;
+use crate::util::{data_at_node, data_at_node_offset, NODE_SIZE};
 
 use merkletree::store::StoreConfig;
 use serde::{Deserialize, Serialize};
@@ -85,3 +93,435 @@ pub fn replica_id<H: Hasher>(prover_id: [u8; 32], sector_id: [u8; 32]) -> H::Dom
 
     H::Function::hash_leaf(&to_hash)
 }
+
+/// Replicates `data` over `graph`, encoding every node with a key derived from `replica_id` and
+/// the already encoded parents of the node. Returns the replica, use `extract` to reverse it.
+pub fn replicate<H, G>(graph: &G, data: &[u8], replica_id: &H::Domain) -> Result<Vec<u8>>
+where
+    H: Hasher,
+    G::Key: AsRef<H::Domain>,
+    G: Graph<H>,
+{
+    let mut replica = data.to_vec();
+    drgporep::encode(graph, replica_id, &mut replica)?;
+
+    Ok(replica)
+}
+
+/// Groups the nodes of `graph` into levels that can each be encoded in parallel, while producing
+/// the same replica as encoding all nodes in index order, as `replicate` does.
+///
+/// In index order a node sees its lower parents already encoded and its higher parents still
+/// unencoded. Hence a node is placed after the levels of its lower parents, and before the levels
+/// of its higher parents. Self references read the node's own data before it is written, which
+/// holds within a level as well.
+pub fn replication_levels<H, G>(graph: &G) -> Result<Vec<Vec<usize>>>
+where
+    H: Hasher,
+    G: Graph<H>,
+{
+    let mut level = vec![0; graph.size()];
+    let mut parents = vec![0; graph.degree()];
+    let mut levels: Vec<Vec<usize>> = Vec::new();
+    for node in 0..graph.size() {
+        graph.parents(node, &mut parents)?;
+        // Nodes only raise the level of higher nodes, so `level[node]` already accounts for all
+        // lower nodes reading it unencoded.
+        let mut node_level = level[node];
+        for parent in parents.iter().map(|p| *p as usize).filter(|p| *p < node) {
+            node_level = node_level.max(level[parent] + 1);
+        }
+        level[node] = node_level;
+        for parent in parents.iter().map(|p| *p as usize).filter(|p| *p > node) {
+            level[parent] = level[parent].max(node_level + 1);
+        }
+
+        if levels.len() <= node_level {
+            levels.resize_with(node_level + 1, Vec::new);
+        }
+        levels[node_level].push(node);
+    }
+
+    Ok(levels)
+}
+
+/// Like `replicate`, but encodes each of the `replication_levels` in parallel. The replica is
+/// identical to the one `replicate` produces. Graphs which always include the predecessor of a
+/// node among its parents, like `BucketGraph`, end up with a single node per level, and gain
+/// nothing from this.
+pub fn parallel_replicate<H, G>(graph: &G, data: &[u8], replica_id: &H::Domain) -> Result<Vec<u8>>
+where
+    H: Hasher,
+    G::Key: AsRef<H::Domain>,
+    G: Graph<H> + Sync,
+{
+    graph.check_data_len(data)?;
+
+    let mut replica = data.to_vec();
+    for level in replication_levels(graph)? {
+        // Nodes within a level never read each other, so the whole level is encoded against the
+        // current replica before any of it is written back.
+        let encoded = level
+            .par_iter()
+            .map(|&node| {
+                let mut parents = vec![0; graph.degree()];
+                graph.parents(node, &mut parents)?;
+                let key = graph.create_key(replica_id, node, &parents, &replica, None)?;
+                let node_data = H::Domain::try_from_bytes(data_at_node(&replica, node)?)?;
+
+                Ok((node, drgporep::encode_node::<H>(key.as_ref(), &node_data)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        for (node, value) in encoded {
+            let start = data_at_node_offset(node);
+            value.write_bytes(&mut replica[start..start + NODE_SIZE])?;
+        }
+    }
+
+    Ok(replica)
+}
+
+/// Extracts the original data from a replica created by `replicate`.
+pub fn extract<H, G>(graph: &G, replica: &[u8], replica_id: &H::Domain) -> Result<Vec<u8>>
+where
+    H: Hasher,
+    G::Key: AsRef<H::Domain>,
+    G: Graph<H> + Sync,
+{
+    drgporep::decode(graph, replica_id, replica, None)
+}
+
+/// A replica together with a hash of the replica id it was created with.
+///
+/// Decoding with the wrong replica id does not fail, it silently produces garbage. Keeping the
+/// hash around lets `extract_bound` detect that instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundReplica<T: Domain> {
+    pub replica: Vec<u8>,
+    pub replica_id_hash: T,
+}
+
+/// The hash of `replica_id` recorded in a `BoundReplica`.
+pub fn replica_id_hash<H: Hasher>(replica_id: &H::Domain) -> H::Domain {
+    hash_bytes_into_domain::<H>(&replica_id.into_bytes())
+}
+
+/// Like `replicate`, but records which replica id the replica was created with.
+pub fn replicate_bound<H, G>(
+    graph: &G,
+    data: &[u8],
+    replica_id: &H::Domain,
+) -> Result<BoundReplica<H::Domain>>
+where
+    H: Hasher,
+    G::Key: AsRef<H::Domain>,
+    G: Graph<H>,
+{
+    Ok(BoundReplica {
+        replica: replicate(graph, data, replica_id)?,
+        replica_id_hash: replica_id_hash::<H>(replica_id),
+    })
+}
+
+/// Like `extract`, but fails with `Error::ReplicaIdMismatch` if `replica_id` is not the one
+/// `bound` was created with.
+pub fn extract_bound<H, G>(
+    graph: &G,
+    bound: &BoundReplica<H::Domain>,
+    replica_id: &H::Domain,
+) -> Result<Vec<u8>>
+where
+    H: Hasher,
+    G::Key: AsRef<H::Domain>,
+    G: Graph<H> + Sync,
+{
+    ensure!(
+        replica_id_hash::<H>(replica_id) == bound.replica_id_hash,
+        Error::ReplicaIdMismatch
+    );
+
+    extract(graph, &bound.replica, replica_id)
+}
+
+/// Like `extract`, but decodes the replica one node at a time, writing each node to `out` as soon
+/// as it is decoded.
+///
+/// The keys are derived from the encoded parents, which are read from `replica` itself, so no
+/// decoded data is kept around. Apart from `replica`, which is only read and may be memory mapped,
+/// memory use is bounded by the parents of a single node and one node of output.
+pub fn extract_streaming<H, G, W>(
+    graph: &G,
+    replica: &[u8],
+    replica_id: &H::Domain,
+    out: &mut W,
+) -> Result<()>
+where
+    H: Hasher,
+    G::Key: AsRef<H::Domain>,
+    G: Graph<H>,
+    W: Write,
+{
+    graph.check_data_len(replica)?;
+
+    let mut parents = vec![0; graph.degree()];
+    let mut node_bytes = [0u8; NODE_SIZE];
+    for node in 0..graph.size() {
+        graph.parents(node, &mut parents)?;
+        let key = graph.create_key(replica_id, node, &parents, replica, None)?;
+        let encoded = H::Domain::try_from_bytes(data_at_node(replica, node)?)?;
+
+        drgporep::decode_node::<H>(key.as_ref(), &encoded)?.write_bytes(&mut node_bytes)?;
+        out.write_all(&node_bytes)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::marker::PhantomData;
+
+    use rand::Rng;
+    use rand_xorshift::XorShiftRng;
+
+    use crate::drgraph::{BucketGraph, BASE_DEGREE};
+    use crate::hasher::{Blake2sHasher, PedersenHasher, Sha256Hasher};
+    use crate::test_helper::{random_data, seeded_rng};
+
+    /// A `BucketGraph` over `nodes` nodes, random data for it and a random replica id.
+    fn setup<H: Hasher>(
+        nodes: usize,
+        rng: &mut XorShiftRng,
+    ) -> (BucketGraph<H>, Vec<u8>, H::Domain) {
+        let graph = BucketGraph::<H>::new(nodes, BASE_DEGREE, 0, rng.gen()).unwrap();
+        let data = random_data(nodes, rng);
+        let replica_id = H::Domain::random(rng);
+
+        (graph, data, replica_id)
+    }
+
+    fn replicate_extract_roundtrip<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let (graph, data, replica_id) = setup::<H>(32, rng);
+
+        let replica = replicate(&graph, &data, &replica_id).unwrap();
+        assert_eq!(replica.len(), data.len());
+        assert_ne!(replica, data, "replication did not change the data");
+
+        assert_eq!(extract(&graph, &replica, &replica_id).unwrap(), data);
+
+        let other_id = H::Domain::random(rng);
+        assert_ne!(extract(&graph, &replica, &other_id).unwrap(), data);
+
+        assert!(replicate(&graph, &data[..data.len() - 32], &replica_id).is_err());
+    }
+
+    fn parallel_replicate_matches<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let nodes = 64;
+        let (graph, data, replica_id) = setup::<H>(nodes, rng);
+
+        let levels = replication_levels(&graph).unwrap();
+        let mut all: Vec<usize> = levels.iter().flatten().copied().collect();
+        all.sort();
+        assert_eq!(all, (0..nodes).collect::<Vec<_>>());
+
+        assert_eq!(
+            parallel_replicate(&graph, &data, &replica_id).unwrap(),
+            replicate(&graph, &data, &replica_id).unwrap()
+        );
+        assert!(parallel_replicate(&graph, &data[..data.len() - 32], &replica_id).is_err());
+    }
+
+    /// A binary tree: the parent of node `i` is `(i - 1) / 2`, and the root references itself.
+    /// All children of a node can be encoded at once, unlike the nodes of a `BucketGraph`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct TreeGraph<H: Hasher> {
+        nodes: usize,
+        _h: PhantomData<H>,
+    }
+
+    impl<H: Hasher> Graph<H> for TreeGraph<H> {
+        type Key = H::Domain;
+
+        fn parents(&self, node: usize, parents: &mut [u32]) -> Result<()> {
+            for parent in parents.iter_mut().take(self.degree()) {
+                *parent = (node.saturating_sub(1) / 2) as u32;
+            }
+
+            Ok(())
+        }
+
+        fn size(&self) -> usize {
+            self.nodes
+        }
+
+        fn degree(&self) -> usize {
+            2
+        }
+
+        fn new(nodes: usize, _: usize, _: usize, _: [u8; 28]) -> Result<Self> {
+            Ok(TreeGraph {
+                nodes,
+                _h: PhantomData,
+            })
+        }
+
+        fn seed(&self) -> [u8; 28] {
+            [0; 28]
+        }
+
+        fn create_key(
+            &self,
+            id: &H::Domain,
+            node: usize,
+            parents: &[u32],
+            parents_data: &[u8],
+            _exp_parents_data: Option<&[u8]>,
+        ) -> Result<Self::Key> {
+            let mut to_hash = id.into_bytes();
+            if node != parents[0] as usize {
+                for parent in parents {
+                    to_hash.extend_from_slice(data_at_node(parents_data, *parent as usize)?);
+                }
+            }
+
+            Ok(hash_bytes_into_domain::<H>(&to_hash))
+        }
+    }
+
+    fn parallel_replicate_wide_levels<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let nodes = 64;
+
+        let graph = TreeGraph::<H>::new(nodes, 0, 0, [0; 28]).unwrap();
+        let data = random_data(nodes, rng);
+        let replica_id = H::Domain::random(rng);
+
+        let levels = replication_levels(&graph).unwrap();
+        assert!(
+            levels.iter().any(|level| level.len() > 1),
+            "every level holds a single node: {:?}",
+            levels
+        );
+        assert_eq!(levels.iter().map(Vec::len).sum::<usize>(), nodes);
+
+        let replica = parallel_replicate(&graph, &data, &replica_id).unwrap();
+        assert_eq!(replica, replicate(&graph, &data, &replica_id).unwrap());
+        assert_eq!(extract(&graph, &replica, &replica_id).unwrap(), data);
+    }
+
+    #[test]
+    fn parallel_replicate_wide_levels_pedersen() {
+        parallel_replicate_wide_levels::<PedersenHasher>();
+    }
+
+    #[test]
+    fn parallel_replicate_wide_levels_sha256() {
+        parallel_replicate_wide_levels::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn parallel_replicate_wide_levels_blake2s() {
+        parallel_replicate_wide_levels::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn parallel_replicate_matches_pedersen() {
+        parallel_replicate_matches::<PedersenHasher>();
+    }
+
+    #[test]
+    fn parallel_replicate_matches_sha256() {
+        parallel_replicate_matches::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn parallel_replicate_matches_blake2s() {
+        parallel_replicate_matches::<Blake2sHasher>();
+    }
+
+    fn extract_bound_detects_wrong_id<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let (graph, data, replica_id) = setup::<H>(32, rng);
+
+        let bound = replicate_bound(&graph, &data, &replica_id).unwrap();
+        assert_eq!(
+            bound.replica,
+            replicate(&graph, &data, &replica_id).unwrap()
+        );
+        assert_eq!(extract_bound(&graph, &bound, &replica_id).unwrap(), data);
+
+        let other_id = H::Domain::random(rng);
+        match extract_bound(&graph, &bound, &other_id)
+            .unwrap_err()
+            .downcast::<Error>()
+        {
+            Ok(Error::ReplicaIdMismatch) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn extract_bound_detects_wrong_id_pedersen() {
+        extract_bound_detects_wrong_id::<PedersenHasher>();
+    }
+
+    #[test]
+    fn extract_bound_detects_wrong_id_sha256() {
+        extract_bound_detects_wrong_id::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn extract_bound_detects_wrong_id_blake2s() {
+        extract_bound_detects_wrong_id::<Blake2sHasher>();
+    }
+
+    fn extract_streaming_matches<H: Hasher>() {
+        let rng = &mut seeded_rng();
+        let (graph, data, replica_id) = setup::<H>(64, rng);
+        let replica = replicate(&graph, &data, &replica_id).unwrap();
+
+        let mut streamed = Vec::new();
+        extract_streaming(&graph, &replica, &replica_id, &mut streamed).unwrap();
+
+        assert_eq!(streamed, extract(&graph, &replica, &replica_id).unwrap());
+        assert_eq!(streamed, data);
+
+        let mut out = Vec::new();
+        assert!(extract_streaming(&graph, &replica[1..], &replica_id, &mut out).is_err());
+    }
+
+    #[test]
+    fn extract_streaming_matches_pedersen() {
+        extract_streaming_matches::<PedersenHasher>();
+    }
+
+    #[test]
+    fn extract_streaming_matches_sha256() {
+        extract_streaming_matches::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn extract_streaming_matches_blake2s() {
+        extract_streaming_matches::<Blake2sHasher>();
+    }
+
+    #[test]
+    fn replicate_extract_roundtrip_pedersen() {
+        replicate_extract_roundtrip::<PedersenHasher>();
+    }
+
+    #[test]
+    fn replicate_extract_roundtrip_sha256() {
+        replicate_extract_roundtrip::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn replicate_extract_roundtrip_blake2s() {
+        replicate_extract_roundtrip::<Blake2sHasher>();
+    }
+}
@@ -1,10 +1,104 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use anyhow::{bail, ensure};
+use bellperson::{groth16, Circuit, ConstraintSystem, SynthesisError};
+use ff::Field;
 use log::info;
+use paired::bls12_381::{Bls12, Fr};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+
+/// Number of constraints of the circuit used to calibrate the per constraint proving cost.
+const CALIBRATION_CONSTRAINTS: usize = 1 << 10;
+
+/// Estimated cost of generating a circuit proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProveCostEstimate {
+    /// The number of constraints of the circuit.
+    pub constraints: usize,
+    /// The expected proving time, extrapolated from `constraints`.
+    pub est_duration: Duration,
+}
+
+impl ProveCostEstimate {
+    /// Extrapolates the proving time of `constraints` constraints, each taking `cost_per_constraint`
+    /// to prove, as measured by `calibrate_prove_cost`.
+    pub fn from_constraints(constraints: usize, cost_per_constraint: Duration) -> Self {
+        let nanos = cost_per_constraint.as_nanos() * constraints as u128;
+
+        ProveCostEstimate {
+            constraints,
+            est_duration: Duration::from_nanos(nanos as u64),
+        }
+    }
+}
+
+/// A chain of squarings, where every constraint has the same shape.
+struct CalibrationCircuit {
+    value: Option<Fr>,
+}
+
+impl Circuit<Bls12> for CalibrationCircuit {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(
+        self,
+        cs: &mut CS,
+    ) -> std::result::Result<(), SynthesisError> {
+        let mut cur_val = self.value;
+        let mut cur = cs.alloc(
+            || "x 0",
+            || cur_val.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+
+        for i in 0..CALIBRATION_CONSTRAINTS {
+            let next_val = cur_val.map(|mut v| {
+                v.square();
+                v
+            });
+            let next = cs.alloc(
+                || format!("x {}", i + 1),
+                || next_val.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+
+            cs.enforce(
+                || format!("square {}", i),
+                |lc| lc + cur,
+                |lc| lc + cur,
+                |lc| lc + next,
+            );
+
+            cur = next;
+            cur_val = next_val;
+        }
+
+        Ok(())
+    }
+}
+
+/// Measures the average time it takes to prove a single constraint on this machine, by proving a
+/// small circuit. This takes a while, callers should measure once and reuse the result.
+pub fn calibrate_prove_cost() -> Result<Duration> {
+    let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+    let params = groth16::generate_random_parameters::<Bls12, _, _>(
+        CalibrationCircuit { value: None },
+        rng,
+    )?;
+
+    let start = Instant::now();
+    groth16::create_random_proof(
+        CalibrationCircuit {
+            value: Some(Fr::random(rng)),
+        },
+        &params,
+        rng,
+    )?;
+
+    Ok(start.elapsed() / CALIBRATION_CONSTRAINTS as u32)
+}
 
 /// The ProofScheme trait provides the methods that any proof scheme needs to implement.
 pub trait ProofScheme<'a> {
@@ -69,15 +163,26 @@ pub trait ProofScheme<'a> {
         unimplemented!();
     }
 
+    /// Verifies the proof of partition `k`. `pub_in` are the public inputs of the whole proof,
+    /// the default implementation derives the partition's inputs with `with_partition`.
+    fn verify_partition(
+        pub_params: &Self::PublicParams,
+        pub_in: &Self::PublicInputs,
+        proof: &Self::Proof,
+        k: usize,
+    ) -> Result<bool> {
+        let partition_pub_in = Self::with_partition((*pub_in).clone(), Some(k));
+
+        Self::verify(pub_params, &partition_pub_in, proof)
+    }
+
     fn verify_all_partitions(
         pub_params: &Self::PublicParams,
         pub_in: &Self::PublicInputs,
         proofs: &[Self::Proof],
     ) -> Result<bool> {
         for (k, proof) in proofs.iter().enumerate() {
-            let partition_pub_in = Self::with_partition((*pub_in).clone(), Some(k)); //
-
-            if !Self::verify(pub_params, &partition_pub_in, proof)? {
+            if !Self::verify_partition(pub_params, pub_in, proof, k)? {
                 return Ok(false);
             }
         }
@@ -85,6 +190,36 @@ pub trait ProofScheme<'a> {
         Ok(true)
     }
 
+    /// Like `verify_all_partitions`, but stops at the first partition which does not verify and
+    /// fails with `Error::InvalidPartitionProof` naming it, instead of returning false. Fails as
+    /// well if there are no proofs at all, as that proves nothing.
+    fn verify_all_partitions_checked(
+        pub_params: &Self::PublicParams,
+        pub_in: &Self::PublicInputs,
+        proofs: &[Self::Proof],
+    ) -> Result<()> {
+        ensure!(!proofs.is_empty(), "no partition proofs to verify");
+
+        for (k, proof) in proofs.iter().enumerate() {
+            ensure!(
+                Self::verify_partition(pub_params, pub_in, proof, k)?,
+                Error::InvalidPartitionProof(k)
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Estimates the cost of proving the circuit corresponding to this proof scheme, without
+    /// generating a proof, given the cost of a single constraint from `calibrate_prove_cost`. Only
+    /// supported by proof schemes which have a circuit.
+    fn estimate_prove_cost(
+        _pub_params: &Self::PublicParams,
+        _cost_per_constraint: Duration,
+    ) -> Result<ProveCostEstimate> {
+        bail!("prove cost estimation is not supported by this proof scheme")
+    }
+
     // This method must be specialized by concrete ProofScheme implementations which use partitions.
     fn with_partition(pub_in: Self::PublicInputs, _k: Option<usize>) -> Self::PublicInputs {
         pub_in
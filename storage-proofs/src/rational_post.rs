@@ -5,6 +5,7 @@ use anyhow::{bail, ensure, Context};
 use byteorder::{ByteOrder, LittleEndian};
 use serde::{Deserialize, Serialize};
 
+use crate::challenge_domain::ChallengeDomain;
 use crate::drgraph::graph_height;
 use crate::error::{Error, Result};
 use crate::hasher::{Domain, Hasher};
@@ -261,7 +262,8 @@ fn derive_challenge(
     sector_size: u64,
     sectors: &OrderedSectorSet,
 ) -> Result<Challenge> {
-    let mut data = seed.to_vec();
+    let mut data = ChallengeDomain::PoSt.tag().to_vec();
+    data.extend_from_slice(seed);
     data.extend_from_slice(&n.to_le_bytes()[..]);
     data.extend_from_slice(&attempt.to_le_bytes()[..]);
 
@@ -287,16 +289,17 @@ mod tests {
     use super::*;
     use ff::Field;
     use paired::bls12_381::{Bls12, Fr};
-    use rand::{Rng, SeedableRng};
+    use rand::Rng;
     use rand_xorshift::XorShiftRng;
 
     use crate::drgraph::{new_seed, BucketGraph, Graph, BASE_DEGREE};
     use crate::fr32::fr_into_bytes;
     use crate::hasher::{Blake2sHasher, HashFunction, PedersenHasher, Sha256Hasher};
     use crate::merkle::make_proof_for_test;
+    use crate::test_helper::seeded_rng;
 
     fn test_rational_post<H: Hasher>() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let leaves = 32;
         let sector_size = leaves * 32;
@@ -414,7 +417,7 @@ mod tests {
     }
 
     fn test_rational_post_validates<H: Hasher>() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let leaves = 32;
         let sector_size = leaves * 32;
@@ -486,7 +489,7 @@ mod tests {
     }
 
     fn test_rational_post_validates_challenge_identity<H: Hasher>() {
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let leaves = 32;
         let sector_size = leaves * 32;
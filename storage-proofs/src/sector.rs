@@ -1,9 +1,12 @@
 use std::collections::BTreeSet;
 use std::fmt;
+use std::str::FromStr;
 
 use byteorder::ByteOrder;
 use serde::{Deserialize, Serialize};
 
+use crate::error::Error;
+
 /// An ordered set of `SectorId`s.
 pub type OrderedSectorSet = BTreeSet<SectorId>;
 
@@ -31,10 +34,95 @@ impl fmt::Display for SectorId {
     }
 }
 
+/// Parses both the `Display` representation, `SectorId(n)`, and a plain number `n`.
+impl FromStr for SectorId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let n = if s.starts_with("SectorId(") && s.ends_with(')') {
+            &s["SectorId(".len()..s.len() - 1]
+        } else {
+            s
+        };
+
+        n.parse()
+            .map(SectorId)
+            .map_err(|_| Error::InvalidSectorId(s.to_string()))
+    }
+}
+
 impl SectorId {
     pub fn as_fr_safe(self) -> [u8; 31] {
         let mut buf: [u8; 31] = [0; 31];
         byteorder::LittleEndian::write_u64(&mut buf[0..8], self.0);
         buf
     }
+
+    /// Big endian bytes of the id, for use as a key prefix in ordered key-value stores: keys
+    /// sort in the same order as the ids.
+    pub fn to_key_prefix(self) -> [u8; 8] {
+        let mut buf = [0; 8];
+        byteorder::BigEndian::write_u64(&mut buf, self.0);
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        let id = SectorId::from(42);
+
+        assert_eq!(id.to_string(), "SectorId(42)");
+        assert_eq!(id.to_string().parse::<SectorId>().unwrap(), id);
+        assert_eq!("42".parse::<SectorId>().unwrap(), id);
+        assert_eq!(
+            u64::max_value().to_string().parse::<SectorId>().unwrap(),
+            SectorId::from(u64::max_value())
+        );
+
+        for invalid in &["", "-1", "SectorId()", "SectorId(42", "Sector(42)", "0x2a"] {
+            match invalid.parse::<SectorId>() {
+                Err(Error::InvalidSectorId(_)) => {}
+                res => panic!("{:?} parsed into {:?}", invalid, res),
+            }
+        }
+    }
+
+    #[test]
+    fn test_serde() {
+        let id = SectorId::from(7);
+        let json = serde_json::to_string(&id).unwrap();
+
+        assert_eq!(json, "7");
+        assert_eq!(serde_json::from_str::<SectorId>(&json).unwrap(), id);
+    }
+
+    #[test]
+    fn test_key_prefix_order() {
+        let ids: Vec<SectorId> = vec![0, 1, 255, 256, 65_536, u64::max_value()]
+            .into_iter()
+            .map(SectorId::from)
+            .collect();
+
+        let mut keys: Vec<Vec<u8>> = ids
+            .iter()
+            .rev()
+            .map(|id| {
+                let mut key = id.to_key_prefix().to_vec();
+                key.extend_from_slice(b"/sealed");
+                key
+            })
+            .collect();
+        keys.sort();
+
+        let sorted: Vec<SectorId> = keys
+            .iter()
+            .map(|key| SectorId::from(byteorder::BigEndian::read_u64(&key[..8])))
+            .collect();
+        assert_eq!(sorted, ids);
+    }
 }
@@ -0,0 +1,93 @@
+//! Optional `tracing` spans around the expensive operations, enabled by the `tracing` feature.
+//!
+//! `enter_span!(name, field = value, ...)` enters an info level span until the end of the enclosing
+//! block. Without the feature it expands to nothing, so the field expressions are not evaluated.
+
+#[cfg(feature = "tracing")]
+pub use tracing;
+
+#[cfg(feature = "tracing")]
+#[macro_export]
+macro_rules! enter_span {
+    ($name:expr, $($fields:tt)*) => {
+        let span = $crate::span::tracing::info_span!($name, $($fields)*);
+        let _enter = span.enter();
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+#[macro_export]
+macro_rules! enter_span {
+    ($($tt:tt)*) => {};
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::fmt;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::hasher::PedersenHasher;
+    use crate::merkle::create_merkle_tree;
+    use crate::util::NODE_SIZE;
+
+    /// Records the name and fields of every span created.
+    #[derive(Default)]
+    struct SpanRecorder {
+        spans: Arc<Mutex<Vec<String>>>,
+        next_id: AtomicU64,
+    }
+
+    struct FieldVisitor(String);
+
+    impl Visit for FieldVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for SpanRecorder {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes) -> Id {
+            let mut visitor = FieldVisitor(attrs.metadata().name().to_string());
+            attrs.record(&mut visitor);
+            self.spans.lock().unwrap().push(visitor.0);
+
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event) {}
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn test_create_merkle_tree_span() {
+        let recorder = SpanRecorder::default();
+        let spans = recorder.spans.clone();
+
+        let leafs = 16;
+        let data: Vec<u8> = (0..leafs * NODE_SIZE).map(|i| (i % 31) as u8).collect();
+        tracing::subscriber::with_default(recorder, || {
+            create_merkle_tree::<PedersenHasher>(None, leafs, &data).unwrap();
+        });
+
+        assert!(spans
+            .lock()
+            .unwrap()
+            .contains(&"create_merkle_tree nodes=16".to_string()));
+    }
+}
@@ -1,9 +1,7 @@
-use anyhow::{ensure, Context};
-use num_bigint::BigUint;
-use num_traits::cast::ToPrimitive;
+use anyhow::ensure;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 
+use crate::challenge_domain::{derive_challenge, ChallengeDomain};
 use crate::error::Result;
 use crate::hasher::Domain;
 
@@ -55,21 +53,11 @@ impl LayerChallenges {
             .map(|i| {
                 let j: u32 = ((challenges_count * k as usize) + i) as u32;
 
-                let hash = Sha256::new()
-                    .chain(replica_id.into_bytes())
-                    .chain(seed)
-                    .chain(&j.to_le_bytes())
-                    .result();
-
-                let big_challenge = BigUint::from_bytes_le(hash.as_ref());
-
                 // For now, we cannot try to prove the first or last node, so make sure the challenge
                 // can never be 0.
-                let big_mod_challenge = big_challenge % (leaves - 1);
-                let big_mod_challenge = big_mod_challenge
-                    .to_usize()
-                    .context("`big_mod_challenge` exceeds size of `usize`")?;
-                Ok(big_mod_challenge + 1)
+                let challenge =
+                    derive_challenge(ChallengeDomain::PoRep, replica_id, seed, j, leaves - 1)?;
+                Ok(challenge + 1)
             })
             .collect()
     }
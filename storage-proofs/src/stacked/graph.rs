@@ -179,6 +179,10 @@ where
         self.base_graph().degree() + self.expansion_degree()
     }
 
+    fn expansion_degree(&self) -> usize {
+        self.expansion_degree
+    }
+
     #[inline]
     fn parents(&self, raw_node: usize, parents: &mut [u32]) -> Result<()> {
         self.base_parents(raw_node, &mut parents[..self.base_graph().degree()])?;
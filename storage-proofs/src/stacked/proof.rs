@@ -33,7 +33,7 @@ use crate::stacked::{
     },
     EncodingProof, LabelingProof, OPENINGS_PER_WINDOW,
 };
-use crate::util::{data_at_node, data_at_node_offset, NODE_SIZE};
+use crate::util::{anon_mmap, data_at_node, data_at_node_offset, NODE_SIZE};
 
 #[derive(Debug)]
 pub struct StackedDrg<'a, H: 'a + Hasher, G: 'a + Hasher> {
@@ -555,7 +555,7 @@ impl<'a, H: 'static + Hasher, G: 'static + Hasher> StackedDrg<'a, H, G> {
         let window_graph = &pub_params.window_graph;
         let layers = pub_params.config.layers();
 
-        let mut layer_labels = vec![0u8; pub_params.window_size_bytes()];
+        let mut layer_labels = anon_mmap(pub_params.window_size_bytes())?;
         let mut parents = vec![0; window_graph.degree()];
         let mut exp_parents_data: Option<Vec<u8>> = None;
 
@@ -606,7 +606,7 @@ impl<'a, H: 'static + Hasher, G: 'static + Hasher> StackedDrg<'a, H, G> {
                 if let Some(ref mut exp_parents_data) = exp_parents_data {
                     exp_parents_data.copy_from_slice(&layer_labels);
                 } else {
-                    exp_parents_data = Some(layer_labels.clone());
+                    exp_parents_data = Some(layer_labels.to_vec());
                 }
             }
         }
@@ -652,7 +652,7 @@ impl<'a, H: 'static + Hasher, G: 'static + Hasher> StackedDrg<'a, H, G> {
             .into_par_iter()
             .zip(data.par_chunks_mut(pub_params.window_size_bytes()))
             .try_for_each(|(window_index, data_chunk)| -> Result<()> {
-                let mut layer_labels = vec![0u8; pub_params.window_size_bytes()];
+                let mut layer_labels = anon_mmap(pub_params.window_size_bytes())?;
                 let mut parents = vec![0; window_graph.degree()];
                 let mut exp_parents_data: Option<Vec<u8>> = None;
 
@@ -681,7 +681,7 @@ impl<'a, H: 'static + Hasher, G: 'static + Hasher> StackedDrg<'a, H, G> {
                         if let Some(ref mut exp_parents_data) = exp_parents_data {
                             exp_parents_data.copy_from_slice(&layer_labels);
                         } else {
-                            exp_parents_data = Some(layer_labels.clone());
+                            exp_parents_data = Some(layer_labels.to_vec());
                         }
                     }
                     // write result to disk
@@ -1011,8 +1011,7 @@ mod tests {
     use ff::Field;
     use merkletree::store::DEFAULT_CACHED_ABOVE_BASE_LAYER;
     use paired::bls12_381::Bls12;
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
 
     use crate::drgraph::{new_seed, BASE_DEGREE};
     use crate::fr32::fr_into_bytes;
@@ -1020,6 +1019,7 @@ mod tests {
     use crate::porep::PoRep;
     use crate::proof::ProofScheme;
     use crate::stacked::{PrivateInputs, SetupParams, EXP_DEGREE};
+    use crate::test_helper::seeded_rng;
 
     const DEFAULT_STACKED_LAYERS: usize = 4;
 
@@ -1052,7 +1052,7 @@ mod tests {
         //     .start(log::LevelFilter::Trace)
         //     .ok();
 
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
         let replica_id: H::Domain = H::Domain::random(rng);
         let nodes = 8 * 32;
 
@@ -1133,7 +1133,7 @@ mod tests {
         //     .start(log::LevelFilter::Trace)
         //     .ok();
 
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
         let replica_id: H::Domain = H::Domain::random(rng);
         let nodes = 4 * 32;
 
@@ -1221,7 +1221,7 @@ mod tests {
         //     .start(log::LevelFilter::Trace)
         //     .ok();
 
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let degree = BASE_DEGREE;
         let expansion_degree = EXP_DEGREE;
@@ -1296,6 +1296,25 @@ mod tests {
         .expect("failed to verify partition proofs");
 
         assert!(proofs_are_valid);
+        StackedDrg::<H, Blake2sHasher>::verify_all_partitions_checked(
+            &pp,
+            &pub_inputs,
+            all_partition_proofs,
+        )
+        .expect("failed to verify partition proofs");
+
+        // Without tau nothing verifies, not even an empty set of proofs.
+        let no_tau = PublicInputs {
+            tau: None,
+            ..pub_inputs.clone()
+        };
+        assert!(!StackedDrg::<H, Blake2sHasher>::verify_all_partitions(
+            &pp,
+            &no_tau,
+            all_partition_proofs,
+        )
+        .unwrap());
+        assert!(!StackedDrg::<H, Blake2sHasher>::verify_all_partitions(&pp, &no_tau, &[]).unwrap());
     }
 
     table_tests! {
@@ -96,19 +96,13 @@ impl<'a, 'c, H: 'static + Hasher, G: 'static + Hasher> ProofScheme<'a> for Stack
     ) -> Result<bool> {
         trace!("verify_all_partitions");
 
-        let expected_comm_r = if let Some(ref tau) = pub_inputs.tau {
-            &tau.comm_r
-        } else {
+        // Without tau there is no comm_r to verify against, not even for an empty set of proofs.
+        if pub_inputs.tau.is_none() {
             return Ok(false);
-        };
+        }
 
         for (k, proof) in partition_proofs.iter().enumerate() {
-            trace!(
-                "verifying partition proof {}/{}",
-                k + 1,
-                partition_proofs.len()
-            );
-            if !Self::verify_single_partition(pub_params, pub_inputs, proof, expected_comm_r, k)? {
+            if !Self::verify_partition(pub_params, pub_inputs, proof, k)? {
                 return Ok(false);
             }
         }
@@ -116,6 +110,23 @@ impl<'a, 'c, H: 'static + Hasher, G: 'static + Hasher> ProofScheme<'a> for Stack
         Ok(true)
     }
 
+    fn verify_partition(
+        pub_params: &Self::PublicParams,
+        pub_inputs: &Self::PublicInputs,
+        proof: &Self::Proof,
+        k: usize,
+    ) -> Result<bool> {
+        trace!("verifying partition proof {}", k);
+
+        let expected_comm_r = if let Some(ref tau) = pub_inputs.tau {
+            &tau.comm_r
+        } else {
+            return Ok(false);
+        };
+
+        Self::verify_single_partition(pub_params, pub_inputs, proof, expected_comm_r, k)
+    }
+
     fn with_partition(pub_in: Self::PublicInputs, k: Option<usize>) -> Self::PublicInputs {
         self::PublicInputs {
             replica_id: pub_in.replica_id,
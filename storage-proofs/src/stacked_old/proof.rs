@@ -625,8 +625,7 @@ mod tests {
 
     use ff::Field;
     use paired::bls12_381::Bls12;
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
 
     use crate::drgraph::{new_seed, BASE_DEGREE};
     use crate::fr32::fr_into_bytes;
@@ -634,6 +633,7 @@ mod tests {
     use crate::porep::PoRep;
     use crate::proof::ProofScheme;
     use crate::stacked_old::{PrivateInputs, SetupParams, EXP_DEGREE};
+    use crate::test_helper::seeded_rng;
 
     const DEFAULT_STACKED_LAYERS: usize = 4;
 
@@ -668,7 +668,7 @@ mod tests {
         //     .start(log::LevelFilter::Trace)
         //     .ok();
 
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
         let replica_id: H::Domain = H::Domain::random(rng);
         let nodes = 8;
 
@@ -738,7 +738,7 @@ mod tests {
         //     .start(log::LevelFilter::Trace)
         //     .ok();
 
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         let degree = BASE_DEGREE;
         let expansion_degree = EXP_DEGREE;
@@ -812,6 +812,25 @@ mod tests {
         .expect("failed to verify partition proofs");
 
         assert!(proofs_are_valid);
+        StackedDrg::<H, Blake2sHasher>::verify_all_partitions_checked(
+            &pp,
+            &pub_inputs,
+            all_partition_proofs,
+        )
+        .expect("failed to verify partition proofs");
+
+        // Without tau nothing verifies, not even an empty set of proofs.
+        let no_tau = PublicInputs {
+            tau: None,
+            ..pub_inputs.clone()
+        };
+        assert!(!StackedDrg::<H, Blake2sHasher>::verify_all_partitions(
+            &pp,
+            &no_tau,
+            all_partition_proofs,
+        )
+        .unwrap());
+        assert!(!StackedDrg::<H, Blake2sHasher>::verify_all_partitions(&pp, &no_tau, &[]).unwrap());
     }
 
     table_tests! {
@@ -82,6 +82,28 @@ impl<'a, 'c, H: 'static + Hasher, G: 'static + Hasher> ProofScheme<'a> for Stack
     ) -> Result<bool> {
         trace!("verify_all_partitions");
 
+        // Without tau there is no comm_r to verify against, not even for an empty set of proofs.
+        if pub_inputs.tau.is_none() {
+            return Ok(false);
+        }
+
+        for (k, proof) in partition_proofs.iter().enumerate() {
+            if !Self::verify_partition(pub_params, pub_inputs, proof, k)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn verify_partition(
+        pub_params: &Self::PublicParams,
+        pub_inputs: &Self::PublicInputs,
+        proofs: &Self::Proof,
+        k: usize,
+    ) -> Result<bool> {
+        trace!("verifying partition proof {}", k);
+
         // generate graphs
         let graph = &pub_params.graph;
 
@@ -91,51 +113,39 @@ impl<'a, 'c, H: 'static + Hasher, G: 'static + Hasher> ProofScheme<'a> for Stack
             return Ok(false);
         };
 
-        for (k, proofs) in partition_proofs.iter().enumerate() {
-            trace!(
-                "verifying partition proof {}/{}",
-                k + 1,
-                partition_proofs.len()
-            );
-
-            trace!("verify comm_r");
-            let actual_comm_r: H::Domain = {
-                let comm_c = proofs[0].comm_c();
-                let comm_r_last = proofs[0].comm_r_last();
-                Fr::from(hash2(comm_c, comm_r_last)).into()
-            };
-
-            if expected_comm_r != &actual_comm_r {
-                return Ok(false);
-            }
-
-            let challenges =
-                pub_inputs.all_challenges(&pub_params.layer_challenges, graph.size(), Some(k));
+        trace!("verify comm_r");
+        let actual_comm_r: H::Domain = {
+            let comm_c = proofs[0].comm_c();
+            let comm_r_last = proofs[0].comm_r_last();
+            Fr::from(hash2(comm_c, comm_r_last)).into()
+        };
 
-            let valid = proofs.par_iter().enumerate().all(|(i, proof)| {
-                trace!("verify challenge {}/{}", i + 1, challenges.len());
+        if expected_comm_r != &actual_comm_r {
+            return Ok(false);
+        }
 
-                // Validate for this challenge
-                let challenge = challenges[i];
+        let challenges =
+            pub_inputs.all_challenges(&pub_params.layer_challenges, graph.size(), Some(k));
 
-                // make sure all proofs have the same comm_c
-                if proof.comm_c() != proofs[0].comm_c() {
-                    return false;
-                }
-                // make sure all proofs have the same comm_r_last
-                if proof.comm_r_last() != proofs[0].comm_r_last() {
-                    return false;
-                }
+        let valid = proofs.par_iter().enumerate().all(|(i, proof)| {
+            trace!("verify challenge {}/{}", i + 1, challenges.len());
 
-                proof.verify(pub_params, pub_inputs, challenge, i, graph)
-            });
+            // Validate for this challenge
+            let challenge = challenges[i];
 
-            if !valid {
-                return Ok(false);
+            // make sure all proofs have the same comm_c
+            if proof.comm_c() != proofs[0].comm_c() {
+                return false;
+            }
+            // make sure all proofs have the same comm_r_last
+            if proof.comm_r_last() != proofs[0].comm_r_last() {
+                return false;
             }
-        }
 
-        Ok(true)
+            proof.verify(pub_params, pub_inputs, challenge, i, graph)
+        });
+
+        Ok(valid)
     }
 
     fn with_partition(pub_in: Self::PublicInputs, k: Option<usize>) -> Self::PublicInputs {
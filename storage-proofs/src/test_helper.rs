@@ -1,7 +1,8 @@
 use ff::{BitIterator, Field, PrimeField, PrimeFieldRepr};
 use fil_sapling_crypto::pedersen_hash::Personalization;
 use paired::bls12_381::{Bls12, Fr};
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
 
 use crate::crypto;
 use crate::error;
@@ -24,6 +25,18 @@ macro_rules! table_tests {
     }
 }
 
+/// Returns a deterministic rng, seeded with the seed shared by all tests.
+pub fn seeded_rng() -> XorShiftRng {
+    XorShiftRng::from_seed(crate::TEST_SEED)
+}
+
+/// Returns `nodes` random field elements drawn from `rng`, as bytes.
+pub fn random_data(nodes: usize, rng: &mut XorShiftRng) -> Vec<u8> {
+    (0..nodes)
+        .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
+        .collect()
+}
+
 pub struct FakeDrgParams {
     pub replica_id: Fr,
     pub replica_nodes: Vec<Fr>,
@@ -199,3 +212,22 @@ pub fn random_merkle_path<R: Rng>(
 
     (path, value, root)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_rng_is_deterministic() {
+        let values = |rng: XorShiftRng| -> Vec<u64> {
+            rng.sample_iter(rand::distributions::Standard)
+                .take(8)
+                .collect()
+        };
+
+        let first = values(seeded_rng());
+        assert_eq!(first, values(seeded_rng()));
+        assert_eq!(first, values(XorShiftRng::from_seed(crate::TEST_SEED)));
+        assert_ne!(first[0], first[1]);
+    }
+}
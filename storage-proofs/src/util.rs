@@ -1,8 +1,10 @@
-use anyhow::ensure;
+use anyhow::{ensure, Context};
 use bellperson::gadgets::boolean::{self, AllocatedBit, Boolean};
 use bellperson::{ConstraintSystem, SynthesisError};
+use memmap::{MmapMut, MmapOptions};
 use paired::Engine;
 
+use crate::bit_order;
 use crate::error;
 
 pub const NODE_SIZE: usize = 32;
@@ -24,12 +26,18 @@ pub fn data_at_node(data: &[u8], v: usize) -> error::Result<&[u8]> {
     Ok(&data[offset..offset + NODE_SIZE])
 }
 
+/// Returns a zero-initialized anonymous memory map of `len` bytes, to be used as scratch space
+/// instead of large heap allocations.
+pub fn anon_mmap(len: usize) -> error::Result<MmapMut> {
+    MmapOptions::new()
+        .len(len)
+        .map_anon()
+        .with_context(|| format!("could not create anonymous memory map of {} bytes", len))
+}
+
 /// Converts bytes into their bit representation, in little endian format.
 pub fn bytes_into_bits(bytes: &[u8]) -> Vec<bool> {
-    bytes
-        .iter()
-        .flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1u8 == 1u8))
-        .collect()
+    bit_order::bytes_to_bits_le(bytes)
 }
 
 /// Converts bytes into their bit representation, in little endian format.
@@ -98,45 +106,37 @@ pub fn bytes_into_boolean_vec_be<E: Engine, CS: ConstraintSystem<E>>(
     Ok(bits)
 }
 
-#[allow(dead_code)]
-#[inline]
-fn bool_to_u8(bit: bool, offset: usize) -> u8 {
-    if bit {
-        1u8 << offset
-    } else {
-        0u8
-    }
-}
-
 /// Converts a slice of bools into their byte representation, in little endian.
-#[allow(dead_code)]
 pub fn bits_to_bytes(bits: &[bool]) -> Vec<u8> {
-    bits.chunks(8)
-        .map(|bits| {
-            bool_to_u8(bits[7], 7)
-                | bool_to_u8(bits[6], 6)
-                | bool_to_u8(bits[5], 5)
-                | bool_to_u8(bits[4], 4)
-                | bool_to_u8(bits[3], 3)
-                | bool_to_u8(bits[2], 2)
-                | bool_to_u8(bits[1], 1)
-                | bool_to_u8(bits[0], 0)
-        })
-        .collect()
+    bit_order::bits_to_bytes_le(bits)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::circuit::test::*;
+    use crate::test_helper::seeded_rng;
     use paired::bls12_381::*;
-    use rand::{Rng, SeedableRng};
-    use rand_xorshift::XorShiftRng;
+    use rand::Rng;
+
+    #[test]
+    fn test_anon_mmap() {
+        let len = 4 * NODE_SIZE;
+        let mut mm = anon_mmap(len).unwrap();
+
+        assert_eq!(mm.len(), len);
+        assert!(mm.iter().all(|b| *b == 0));
+
+        mm[len - 1] = 7;
+        mm[..NODE_SIZE].copy_from_slice(&[1; NODE_SIZE]);
+        assert_eq!(mm[len - 1], 7);
+        assert_eq!(data_at_node(&mm, 0).unwrap(), &[1; NODE_SIZE][..]);
+    }
 
     #[test]
     fn test_bytes_into_boolean_vec() {
         let mut cs = TestConstraintSystem::<Bls12>::new();
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         for i in 0..100 {
             let data: Vec<u8> = (0..i + 10).map(|_| rng.gen()).collect();
@@ -157,14 +157,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_bool_to_u8() {
-        assert_eq!(bool_to_u8(false, 2), 0b0000_0000);
-        assert_eq!(bool_to_u8(true, 0), 0b0000_0001);
-        assert_eq!(bool_to_u8(true, 1), 0b0000_0010);
-        assert_eq!(bool_to_u8(true, 7), 0b1000_0000);
-    }
-
     #[test]
     fn test_bits_into_bytes() {
         assert_eq!(
@@ -184,7 +176,7 @@ mod tests {
             vec![true, false, false, false, false, false, false, false]
         );
 
-        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let rng = &mut seeded_rng();
 
         for i in 10..100 {
             let bytes: Vec<u8> = (0..i).map(|_| rng.gen()).collect();
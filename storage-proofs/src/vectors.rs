@@ -0,0 +1,101 @@
+//! Golden merkle roots, guarding the hashers and the tree construction against accidental changes,
+//! which would invalidate all existing sealed sectors.
+//!
+//! The vectors were computed independently of this crate: a binary tree over the raw nodes, where
+//! every inner node is the digest of `left || right` with its two most significant bits cleared.
+//! For pedersen the digest is the x-coordinate of the Jubjub pedersen hash over the low 255 bits
+//! of `left` followed by the low 255 bits of `right`.
+//! Only change an entry when the construction is changed on purpose, the failing test prints the
+//! new value.
+
+use crate::hasher::{Blake2sHasher, Domain, Hasher, PedersenHasher, Sha256Hasher};
+use crate::merkle::create_merkle_tree;
+use crate::util::NODE_SIZE;
+
+/// `(hasher, sector size in bytes, expected root as hex)`
+const VECTORS: &[(&str, usize, &str)] = &[
+    (
+        "pedersen",
+        1024,
+        "81238e1dc04b40d726d8cdfd51009468793e56711a2bf77b68022d65af890c31",
+    ),
+    (
+        "pedersen",
+        4096,
+        "ce421615d94135fe5ef3364721484c94dc3aac9cd1b292ea27bbf82390ad7f08",
+    ),
+    (
+        "sha256",
+        1024,
+        "93408f9d068e55f3704b236ca5ec569a095bf0e819191d1d39560fe0dee0891b",
+    ),
+    (
+        "sha256",
+        4096,
+        "7b95b3ff5ef91b6582aee7589756c83a0d80d38d92a4948f541ccc8a1ca32f0b",
+    ),
+    (
+        "blake2s",
+        1024,
+        "5e749971878c8e6437cb63ddb2d64c61efe1785dadaa8f33e515f8e7c1f50d28",
+    ),
+    (
+        "blake2s",
+        4096,
+        "68c2725666bdb02791c37e7f8b90a275d5702c0cdba127e8771fffa5238f6a05",
+    ),
+];
+
+/// The sector data the vectors are computed over: byte `i` is `(7 * i + 3) mod 251`, with the two
+/// most significant bits of every node cleared, so that each node is a valid field element.
+fn sector_data(sector_size: usize) -> Vec<u8> {
+    let mut data: Vec<u8> = (0..sector_size)
+        .map(|i| ((7 * i + 3) % 251) as u8)
+        .collect();
+    for node in data.chunks_mut(NODE_SIZE) {
+        node[NODE_SIZE - 1] &= 0b0011_1111;
+    }
+
+    data
+}
+
+fn root<H: Hasher>(sector_size: usize) -> String {
+    let data = sector_data(sector_size);
+    let tree = create_merkle_tree::<H>(None, sector_size / NODE_SIZE, &data)
+        .expect("failed to build tree");
+
+    hex::encode(tree.root().into_bytes())
+}
+
+fn compute_root(hasher: &str, sector_size: usize) -> String {
+    match hasher {
+        "pedersen" => root::<PedersenHasher>(sector_size),
+        "sha256" => root::<Sha256Hasher>(sector_size),
+        "blake2s" => root::<Blake2sHasher>(sector_size),
+        _ => panic!("no hasher registered for {:?}", hasher),
+    }
+}
+
+#[test]
+fn test_golden_merkle_roots() {
+    let mismatches: Vec<String> = VECTORS
+        .iter()
+        .filter_map(|&(hasher, sector_size, expected)| {
+            let actual = compute_root(hasher, sector_size);
+            if actual == expected {
+                return None;
+            }
+
+            Some(format!(
+                "  ({:?}, {}): expected {}, got {}",
+                hasher, sector_size, expected, actual
+            ))
+        })
+        .collect();
+
+    assert!(
+        mismatches.is_empty(),
+        "merkle roots changed:\n{}",
+        mismatches.join("\n")
+    );
+}
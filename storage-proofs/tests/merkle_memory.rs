@@ -0,0 +1,83 @@
+//! Guards the memory use of building a merkle tree, so that refactors of the (parallel) build path
+//! do not accidentally buffer extra copies of the data or the tree.
+//!
+//! The global allocator of this test binary counts live heap bytes, which is why this lives in its
+//! own integration test. Run with `cargo test --features mem-test --test merkle_memory`.
+#![cfg(feature = "mem-test")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rand::{RngCore, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use storage_proofs::drgraph::{new_seed, BucketGraph, Graph, BASE_DEGREE};
+use storage_proofs::hasher::Sha256Hasher;
+use storage_proofs::util::NODE_SIZE;
+
+/// Allocations during the build may add up to at most this multiple of the data size. A binary
+/// tree over `n` bytes of leaves takes `2n` bytes, whether it is kept in memory or not, the rest
+/// is slack for the leaves in flight and the bookkeeping of the build.
+const MEMORY_CEILING_FACTOR: usize = 3;
+
+struct CountingAllocator;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let live = LIVE.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            let mut peak = PEAK.load(Ordering::SeqCst);
+            while live > peak {
+                match PEAK.compare_exchange(peak, live, Ordering::SeqCst, Ordering::SeqCst) {
+                    Ok(_) => break,
+                    Err(current) => peak = current,
+                }
+            }
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn merkle_tree_memory_ceiling() {
+    let nodes = 1 << 19;
+    let data_size = nodes * NODE_SIZE;
+
+    let rng = &mut XorShiftRng::from_seed([7; 16]);
+    let mut data = vec![0u8; data_size];
+    rng.fill_bytes(&mut data);
+    // Keep every node a valid field element.
+    for node in data.chunks_mut(NODE_SIZE) {
+        node[NODE_SIZE - 1] &= 0b0011_1111;
+    }
+
+    let graph = BucketGraph::<Sha256Hasher>::new(nodes, BASE_DEGREE, 0, new_seed()).unwrap();
+
+    // Only count what the build itself allocates on top of the data and the graph.
+    let before = LIVE.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+
+    let tree = graph.merkle_tree(&data).unwrap();
+    let peak = PEAK.load(Ordering::SeqCst) - before;
+    drop(tree);
+
+    assert!(
+        peak <= MEMORY_CEILING_FACTOR * data_size,
+        "building a tree over {} bytes allocated up to {} bytes, more than {}x",
+        data_size,
+        peak,
+        MEMORY_CEILING_FACTOR
+    );
+}